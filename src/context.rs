@@ -31,7 +31,9 @@ use winit::{
     keyboard::{Key, NamedKey},
 };
 
+use std::ffi::c_int;
 use std::fs::File;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::thread;
 
 /// Uses `winit` to acquire a graphical frame and create an event loop for it
@@ -56,12 +58,44 @@ pub fn init_context(
     (frame, event_loop)
 }
 
+unsafe extern "C" {
+    fn signal(signum: c_int, handler: usize) -> usize;
+    fn raise(sig: c_int) -> c_int;
+}
+
+const SIGINT: c_int = 2;
+const SIG_DFL: usize = 0;
+
+/// Sail thread to interrupt when Ctrl-C is pressed at the shell
+static INTERRUPT_TGT: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn on_interrupt(_sig: c_int) {
+    let tgt = INTERRUPT_TGT.load(Ordering::Acquire) as *const sail::thread::ThreadHull;
+
+    match unsafe { tgt.as_ref() } {
+        Some(t) if t.interrupt_bounded() => (),
+        // no shell input is being evaluated, so quit as usual
+        _ => unsafe {
+            signal(SIGINT, SIG_DFL);
+            raise(SIGINT);
+        },
+    }
+}
+
+/// Has Ctrl-C interrupt whatever the given Sail thread is evaluating
+/// within an `eval` or `try`, rather than end the program
+fn catch_interrupts(sl_thr: usize) {
+    INTERRUPT_TGT.store(sl_thr, Ordering::Release);
+    unsafe { signal(SIGINT, on_interrupt as extern "C" fn(c_int) as usize) };
+}
+
 /// Serves as the main loop for the context thread; occupies a `winit` event loop
 pub fn run_loop<Ij: 'static>(
     event_loop: EventLoop<()>,
     threads: Ij,
-    sl_weft: sail::thread::Weft,
+    sl_weft: Box<sail::thread::Weft>,
     sl_reg: usize,
+    m_thr: usize,
     m_send: usize,
     r_send: usize,
     fr_dims: sail::SlHndl,
@@ -71,6 +105,9 @@ pub fn run_loop<Ij: 'static>(
 {
     let mut joins = Some(threads);
 
+    // runaway expressions typed at the shell are stopped with Ctrl-C
+    catch_interrupts(m_thr);
+
     let dummy_env = sail::env_create(sl_reg as _, None);
     let dm_env_ax = dummy_env.clone();
 
@@ -230,6 +267,10 @@ pub fn run_loop<Ij: 'static>(
                                     // switch drawing mode
                                     Some(sail::sym_init(sl_reg, sail::K_CX_KEY_M.0))
                                 }
+                                b'w' => {
+                                    // write the document
+                                    Some(sail::sym_init(sl_reg, sail::K_CX_KEY_W.0))
+                                }
                                 _ => None,
                             };
                             if let Some(ks) = key_sym {
@@ -274,9 +274,9 @@ pub fn render_loop(name: &'static str, frame: FrameHandles, sl_thr_ptr: usize) {
     engine.setup();
     engine.set_clear([1.0, 1.0, 1.0, 1.0]);
 
-    let prog_txt = &std::fs::read_to_string("scripts/rndr.sl").unwrap();
-
-    thread_ref.load_from_text(prog_txt, true).unwrap();
+    if let Err(err) = thread_ref.load_from_file("rndr.sl") {
+        panic!("{}", sail::context(thread_ref.context().symtab(), err));
+    }
 
     while thread_ref.advance() {}
 
@@ -295,6 +295,12 @@ pub fn render_loop(name: &'static str, frame: FrameHandles, sl_thr_ptr: usize) {
         }
     }
 
+    if let Some(mut res) = thread_ref.result() {
+        if res.core_type() == Some(sail::CoreType::ErrObj) {
+            println!("{}", sail::context(thread_ref.context().symtab(), res));
+        }
+    }
+
     // TODO: dispose of Sail stack, region, etc
     drop(engine);
 }
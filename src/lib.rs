@@ -135,8 +135,9 @@ pub fn manager_loop(frame: Frame, sl_thr_ptr: usize) {
         mngr_fns,
     );
 
-    let prog_txt = &std::fs::read_to_string("scripts/main.sl").unwrap();
-    thread_ref.load_from_text(prog_txt, true).unwrap();
+    if let Err(err) = thread_ref.load_from_file("main.sl") {
+        panic!("{}", sail::context(thread_ref.context().symtab(), err));
+    }
 
     while thread_ref.advance() {}
 
@@ -148,4 +149,10 @@ pub fn manager_loop(frame: Frame, sl_thr_ptr: usize) {
             break;
         }
     }
+
+    if let Some(mut res) = thread_ref.result() {
+        if res.core_type() == Some(sail::CoreType::ErrObj) {
+            println!("{}", sail::context(thread_ref.context().symtab(), res));
+        }
+    }
 }
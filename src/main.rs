@@ -62,16 +62,26 @@ fn main() {
 
     // cargo run file <filename> to run a Sail file
     // cargo run repl for Sail REPL
+    // cargo run <document> to open a drawing document
     let args: Vec<String> = env::args().collect();
-    if args.len() >= 3 {
-        match sail::run_file(&args[2]) {
-            Ok(out) => println!("{}", out),
-            Err(err) => println!("{:?}", err),
+    let doc_path = match args.get(1).map(String::as_str) {
+        Some("file") => {
+            let Some(path) = args.get(2) else {
+                eprintln!("usage: {} file <filename>", args[0]);
+                std::process::exit(2);
+            };
+            match sail::run_file(path) {
+                Ok(out) => println!("{}", out),
+                Err(err) => println!("{}", err),
+            }
+            std::process::exit(0);
         }
-        std::process::exit(0);
-    } else if args.len() >= 2 {
-        sail::repl(io::stdin())
-    }
+        Some("repl") => {
+            sail::repl(io::stdin());
+            None
+        }
+        path => path.map(str::to_string),
+    };
 
     let (frame, event_loop) = context::init_context(NAME, ICON, SIZE[0], SIZE[1]);
 
@@ -83,10 +93,15 @@ fn main() {
     let mut global_interact = sail::thread::Tact::create(251);
     sail::global_ctx_setup(&mut global_interact);
 
+    // files are loaded from scripts/, unless STARK_PATH lists others
+    if let Some(dirs) = env::var_os("STARK_PATH") {
+        global_interact.set_search_path(env::split_paths(&dirs));
+    }
+
     // data structure that accounts for the global interaction
     // state, as well as all threads, using an array of pointers
-    // to pinned thread hulls
-    let mut global_weft = sail::thread::Weft::create(global_interact);
+    // to pinned thread hulls; boxed, since every thread refers to it
+    let mut global_weft = Box::new(sail::thread::Weft::create(global_interact));
 
     let main_thr = sail::thread::ThreadHull::summon(&mut global_weft, 10000, 1 << 20, None);
     sail::thread_env_setup(main_thr);
@@ -102,11 +117,11 @@ fn main() {
     global_weft.assign_special(main_thr_ref.id);
     global_weft.assign_special(rndr_thr_ref.id);
 
-    // TODO: match available parallelism or setting
-    global_weft.add_worker();
-    global_weft.add_worker();
+    // one worker per processor, unless STARK_WORKERS says otherwise
+    let workers = env::var("STARK_WORKERS").ok().and_then(|w| w.parse().ok());
+    global_weft.add_workers(workers);
 
-    let rdr_tgt_obj = sail::warp_hdl_init(main_thr_ref.region(), rndr_qin);
+    let rdr_tgt_obj = sail::warp_hdl_init(main_thr_ref.region(), rndr_thr_ref.id, None);
     sail::env_scope_ins_by_id(
         main_thr_ref.region(),
         main_thr_ref.top_env(),
@@ -122,7 +137,7 @@ fn main() {
         rdr_id_obj,
     );
 
-    let mgr_tgt_obj = sail::warp_hdl_init(rndr_thr_ref.region(), main_qin);
+    let mgr_tgt_obj = sail::warp_hdl_init(rndr_thr_ref.region(), main_thr_ref.id, None);
     sail::env_scope_ins_by_id(
         rndr_thr_ref.region(),
         rndr_thr_ref.top_env(),
@@ -156,6 +171,18 @@ fn main() {
         cur_pos.clone(),
     );
 
+    // a document named on the command line is opened by main.sl
+    let doc_path = match doc_path {
+        Some(path) => sail::string_init(main_thr_ref.region(), &path),
+        None => sail::bool_init(main_thr_ref.region(), false),
+    };
+    sail::env_scope_ins_by_id(
+        main_thr_ref.region(),
+        main_thr_ref.top_env(),
+        sail::S_DOC_PATH.0,
+        doc_path,
+    );
+
     let ctxt_region = ctxt_region as usize;
 
     let (sl_main_thr, sl_rndr_thr) = (main_thr as usize, rndr_thr as usize);
@@ -181,6 +208,7 @@ fn main() {
         vec![manager, render].into_iter(),
         global_weft,
         ctxt_region,
+        sl_main_thr,
         sl_main_qin,
         sl_rndr_qin,
         fr_dims,
@@ -0,0 +1,426 @@
+// STARK, a system for computer augmented design.
+
+// SPDX-FileCopyrightText: © 2021 Matthew Rothlisberger
+// SPDX-License-Identifier: AGPL-3.0-only
+
+// STARK is licensed under the terms of the GNU Affero General Public
+// License version 3. See the top-level LICENSES directory for the
+// license text.
+
+// Find full copyright information in the top-level COPYRIGHT file.
+
+// <>
+
+// src/sail/arr.rs
+
+// Vectorised operations over homogeneous arrays. Arrays of numbers are
+// taken apart whole, combined element by element under the promotion
+// and overflow rules of the numeric tower, and written out as new
+// arrays; slicing and concatenation serve arrays of any base sized
+// type.
+
+// <>
+
+use super::core::*;
+use super::num::{self, Num, NumType, Op};
+use super::{SlErrCode, memmgt};
+
+use std::cmp::Ordering;
+
+/// Reason an array operation failed, with a message for the error
+pub type ArrErr = (SlErrCode, &'static str);
+
+/// Bytes before the elements of an array: its element type and length
+const ARR_HEAD: u32 = 8;
+
+/// Borrows the bytes of an array's elements
+fn elts(loc: &mut SlHndl) -> &[u8] {
+    let (typ, len) = (
+        super::arrvec_get_typ(loc.clone()),
+        super::arrvec_get_len(loc.clone()),
+    );
+    let size = (temp_get_size(typ) * len) as usize;
+    unsafe { std::slice::from_raw_parts(loc.value_ptr().add(ARR_HEAD as usize), size) }
+}
+
+/// Creates an array of a base sized type from the bytes of its
+/// elements
+fn from_bytes(reg: *mut memmgt::Region, typ: u32, bytes: &[u8]) -> SlHndl {
+    let len = bytes.len() as u32 / temp_get_size(typ);
+
+    unsafe {
+        let size = vec_size(ARR_HEAD, temp_get_size(typ), len);
+        let mut out =
+            SlHndl::from_raw_unchecked(memmgt::alloc(reg, size, memmgt::cap(Cfg::VecArr)));
+
+        write_field_unchecked::<u32>(out.clone(), 0, typ);
+        write_field_unchecked::<u32>(out.clone(), 4, len);
+
+        std::ptr::copy_nonoverlapping(
+            bytes.as_ptr(),
+            out.value_ptr().add(ARR_HEAD as usize),
+            bytes.len(),
+        );
+
+        out
+    }
+}
+
+/// Creates an array of `len` elements of a base sized type, each a
+/// copy of the value held by `init`
+pub fn fill(
+    reg: *mut memmgt::Region,
+    typ: u32,
+    len: u32,
+    mut init: SlHndl,
+) -> Result<SlHndl, ArrErr> {
+    if !temp_base_sized_p(typ) {
+        return Err((SlErrCode::TypeInvalid, "array element type must be sized"));
+    }
+    if init.size() != temp_get_size(typ) {
+        return Err((SlErrCode::TypeMismatch, "value does not match element type"));
+    }
+
+    let one = unsafe { std::slice::from_raw_parts(init.value_ptr(), init.size() as usize) };
+    Ok(from_bytes(reg, typ, &one.repeat(len as usize)))
+}
+
+/// Reads every element of an array of numbers, with their type
+pub fn nums(mut loc: SlHndl) -> Result<(Vec<Num>, NumType), ArrErr> {
+    let Some(typ) = NumType::of_sym(super::arrvec_get_typ(loc.clone())) else {
+        return Err((SlErrCode::TypeInvalid, "array elements must be numbers"));
+    };
+
+    let size = (typ.bits() / 8) as usize;
+    let out = elts(&mut loc)
+        .chunks_exact(size)
+        .map(|b| Num::read(typ, b))
+        .collect();
+
+    Ok((out, typ))
+}
+
+/// Creates an array of numbers, each converted to the given type
+pub fn init(reg: *mut memmgt::Region, typ: NumType, vals: &[Num]) -> Result<SlHndl, ArrErr> {
+    let size = (typ.bits() / 8) as usize;
+    let mut bytes = vec![0; size * vals.len()];
+
+    for (v, b) in vals.iter().zip(bytes.chunks_exact_mut(size)) {
+        v.to(typ)
+            .ok_or((
+                SlErrCode::ArithOverflow,
+                "value does not fit the element type",
+            ))?
+            .write(b);
+    }
+
+    Ok(from_bytes(reg, typ.sym(), &bytes))
+}
+
+/// Creates an array of bytes
+pub fn from_u8s(reg: *mut memmgt::Region, bytes: &[u8]) -> SlHndl {
+    from_bytes(reg, super::T_U8.0, bytes)
+}
+
+/// Copies out the elements of an array of bytes
+pub fn u8s(mut loc: SlHndl) -> Result<Vec<u8>, ArrErr> {
+    if super::arrvec_get_typ(loc.clone()) != super::T_U8.0 {
+        return Err((SlErrCode::TypeMismatch, "array elements must be u8"));
+    }
+
+    Ok(elts(&mut loc).to_vec())
+}
+
+/// Overwrites every element of an array of numbers, converting the
+/// values to its element type
+pub fn replace(mut loc: SlHndl, vals: &[Num]) -> Result<(), ArrErr> {
+    let (old, typ) = nums(loc.clone())?;
+    if old.len() != vals.len() {
+        return Err((SlErrCode::TypeMismatch, "arrays differ in length"));
+    }
+
+    let size = (typ.bits() / 8) as usize;
+    let mut bytes = vec![0; size * vals.len()];
+
+    for (v, b) in vals.iter().zip(bytes.chunks_exact_mut(size)) {
+        v.to(typ)
+            .ok_or((
+                SlErrCode::ArithOverflow,
+                "value does not fit the element type",
+            ))?
+            .write(b);
+    }
+
+    unsafe {
+        std::ptr::copy_nonoverlapping(
+            bytes.as_ptr(),
+            loc.value_ptr().add(ARR_HEAD as usize),
+            bytes.len(),
+        )
+    }
+
+    Ok(())
+}
+
+/// Reads two arrays of numbers of the same length, with the type to
+/// which their elements are promoted
+fn pair(a: SlHndl, b: SlHndl) -> Result<(Vec<Num>, Vec<Num>, NumType), ArrErr> {
+    let (x, tx) = nums(a)?;
+    let (y, ty) = nums(b)?;
+
+    if x.len() != y.len() {
+        return Err((SlErrCode::TypeMismatch, "arrays differ in length"));
+    }
+
+    Ok((x, y, NumType::common(tx, ty)))
+}
+
+fn arith(op: Op, a: Num, b: Num) -> Result<Num, ArrErr> {
+    num::arith(op, a, b).map_err(|code| match code {
+        SlErrCode::ArithDivByZero => (code, "integer division by zero"),
+        _ => (code, "array element overflowed its type"),
+    })
+}
+
+/// Applies an arithmetic operation to each pair of elements of two
+/// arrays of the same length
+pub fn zip(reg: *mut memmgt::Region, op: Op, a: SlHndl, b: SlHndl) -> Result<SlHndl, ArrErr> {
+    let (x, y, typ) = pair(a, b)?;
+    let out = x
+        .into_iter()
+        .zip(y)
+        .map(|(p, q)| arith(op, p, q))
+        .collect::<Result<Vec<_>, _>>()?;
+    init(reg, typ, &out)
+}
+
+/// Multiplies every element of an array by a number
+pub fn scale(reg: *mut memmgt::Region, a: SlHndl, k: Num) -> Result<SlHndl, ArrErr> {
+    let (x, tx) = nums(a)?;
+    let out = x
+        .into_iter()
+        .map(|p| arith(Op::Mul, p, k))
+        .collect::<Result<Vec<_>, _>>()?;
+    init(reg, NumType::common(tx, k.typ()), &out)
+}
+
+/// Gives the dot product of two arrays of the same length
+pub fn dot(a: SlHndl, b: SlHndl) -> Result<Num, ArrErr> {
+    let (x, y, typ) = pair(a, b)?;
+    let zero = Num::U8(0).to(typ).unwrap();
+
+    x.into_iter().zip(y).try_fold(zero, |acc, (p, q)| {
+        arith(Op::Add, acc, arith(Op::Mul, p, q)?)
+    })
+}
+
+/// Gives the cross product of two arrays of three elements
+pub fn cross(reg: *mut memmgt::Region, a: SlHndl, b: SlHndl) -> Result<SlHndl, ArrErr> {
+    let (x, y, typ) = pair(a, b)?;
+    if x.len() != 3 {
+        return Err((
+            SlErrCode::TypeMismatch,
+            "cross product needs three elements",
+        ));
+    }
+
+    let term = |i: usize, j: usize| {
+        arith(
+            Op::Sub,
+            arith(Op::Mul, x[i], y[j])?,
+            arith(Op::Mul, x[j], y[i])?,
+        )
+    };
+    init(reg, typ, &[term(1, 2)?, term(2, 0)?, term(0, 1)?])
+}
+
+/// Gives the Euclidean length of an array; single precision arrays
+/// have a single precision length, and all others double
+pub fn norm(a: SlHndl) -> Result<Num, ArrErr> {
+    let (x, typ) = nums(a)?;
+    let sum: f64 = x.into_iter().map(|p| float(p).powi(2)).sum();
+
+    Ok(match typ {
+        NumType::F32 => Num::F32(sum.sqrt() as f32),
+        _ => Num::F64(sum.sqrt()),
+    })
+}
+
+/// Interpolates linearly between two arrays, reaching `b` when `t` is
+/// one; the result is single precision only if every input is
+pub fn lerp(reg: *mut memmgt::Region, a: SlHndl, b: SlHndl, t: Num) -> Result<SlHndl, ArrErr> {
+    let (x, y, typ) = pair(a, b)?;
+    let typ = match NumType::common(typ, t.typ()) {
+        NumType::F32 => NumType::F32,
+        _ => NumType::F64,
+    };
+
+    let t = float(t);
+    let out: Vec<_> = x
+        .into_iter()
+        .zip(y)
+        .map(|(p, q)| Num::F64(float(p) + (float(q) - float(p)) * t))
+        .collect();
+    init(reg, typ, &out)
+}
+
+/// Picks the lesser or greater of each pair of elements of two arrays,
+/// by `keep`; where the two cannot be compared, the first is kept
+pub fn pick(
+    reg: *mut memmgt::Region,
+    a: SlHndl,
+    b: SlHndl,
+    keep: Ordering,
+) -> Result<SlHndl, ArrErr> {
+    let (x, y, typ) = pair(a, b)?;
+    let out: Vec<_> = x
+        .into_iter()
+        .zip(y)
+        .map(|(p, q)| match num::compare(q, p) {
+            Some(o) if o == keep => q,
+            _ => p,
+        })
+        .collect();
+    init(reg, typ, &out)
+}
+
+/// Copies the elements of an array from `start` up to `end`
+pub fn slice(
+    reg: *mut memmgt::Region,
+    mut a: SlHndl,
+    start: i64,
+    end: i64,
+) -> Result<SlHndl, ArrErr> {
+    let (typ, len) = (
+        super::arrvec_get_typ(a.clone()),
+        super::arrvec_get_len(a.clone()),
+    );
+    let size = temp_get_size(typ) as usize;
+
+    let (Ok(start), Ok(end)) = (usize::try_from(start), usize::try_from(end)) else {
+        return Err((SlErrCode::IndexOutOfRange, "array index out of range"));
+    };
+    if end > len as usize {
+        return Err((SlErrCode::IndexOutOfRange, "array index out of range"));
+    }
+    if start > end {
+        return Err((
+            SlErrCode::IndexOutOfRange,
+            "array slice ends before it starts",
+        ));
+    }
+
+    Ok(from_bytes(
+        reg,
+        typ,
+        &elts(&mut a)[start * size..end * size],
+    ))
+}
+
+/// Joins two arrays of the same element type into a new one
+pub fn concat(reg: *mut memmgt::Region, mut a: SlHndl, mut b: SlHndl) -> Result<SlHndl, ArrErr> {
+    let typ = super::arrvec_get_typ(a.clone());
+    if typ != super::arrvec_get_typ(b.clone()) {
+        return Err((SlErrCode::TypeMismatch, "arrays differ in element type"));
+    }
+
+    Ok(from_bytes(reg, typ, &[elts(&mut a), elts(&mut b)].concat()))
+}
+
+/// Converts every element of an array of numbers to another type
+pub fn convert(reg: *mut memmgt::Region, a: SlHndl, typ: NumType) -> Result<SlHndl, ArrErr> {
+    init(reg, typ, &nums(a)?.0)
+}
+
+fn float(n: Num) -> f64 {
+    match n.to(NumType::F64) {
+        Some(Num::F64(f)) => f,
+        _ => unreachable!("every number converts to a double"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn f32s(reg: *mut memmgt::Region, vals: &[f32]) -> SlHndl {
+        super::super::arrvec_init(reg, super::super::T_F32.0, vals.len() as u32, vals)
+    }
+
+    #[test]
+    fn vector_math() {
+        let reg = memmgt::Region::acq(1000);
+        let (a, b) = (f32s(reg, &[1.0, 2.0, 3.0]), f32s(reg, &[4.0, 5.0, 6.0]));
+
+        let sum = nums(zip(reg, Op::Add, a.clone(), b.clone()).unwrap()).unwrap();
+        assert_eq!(
+            sum,
+            (
+                vec![Num::F32(5.0), Num::F32(7.0), Num::F32(9.0)],
+                NumType::F32
+            )
+        );
+
+        assert_eq!(dot(a.clone(), b.clone()), Ok(Num::F32(32.0)));
+        assert_eq!(
+            nums(cross(reg, a.clone(), b.clone()).unwrap()).unwrap().0,
+            vec![Num::F32(-3.0), Num::F32(6.0), Num::F32(-3.0)]
+        );
+        assert_eq!(norm(f32s(reg, &[3.0, 4.0])), Ok(Num::F32(5.0)));
+
+        let mid = lerp(reg, a.clone(), b.clone(), Num::F32(0.5)).unwrap();
+        assert_eq!(nums(mid).unwrap().0[0], Num::F32(2.5));
+
+        let low = pick(reg, a.clone(), f32s(reg, &[0.0, 9.0, 3.0]), Ordering::Less).unwrap();
+        assert_eq!(
+            nums(low).unwrap().0,
+            vec![Num::F32(0.0), Num::F32(2.0), Num::F32(3.0)]
+        );
+
+        let ints = convert(reg, b.clone(), NumType::U8).unwrap();
+        let scaled = scale(reg, ints.clone(), Num::I64(-2)).unwrap();
+        assert_eq!(
+            nums(scaled).unwrap(),
+            (
+                vec![Num::I64(-8), Num::I64(-10), Num::I64(-12)],
+                NumType::I64
+            )
+        );
+        assert_eq!(
+            scale(reg, ints, Num::U8(60)).unwrap_err().0,
+            SlErrCode::ArithOverflow
+        );
+
+        assert_eq!(
+            zip(reg, Op::Add, a.clone(), f32s(reg, &[1.0]))
+                .unwrap_err()
+                .0,
+            SlErrCode::TypeMismatch
+        );
+    }
+
+    #[test]
+    fn slices() {
+        let reg = memmgt::Region::acq(1000);
+        let (a, b) = (f32s(reg, &[1.0, 2.0, 3.0]), f32s(reg, &[4.0]));
+
+        let joined = concat(reg, slice(reg, a.clone(), 1, 3).unwrap(), b).unwrap();
+        assert_eq!(
+            nums(joined).unwrap().0,
+            vec![Num::F32(2.0), Num::F32(3.0), Num::F32(4.0)]
+        );
+
+        assert!(slice(reg, a.clone(), 0, 0).is_ok());
+        assert_eq!(
+            slice(reg, a.clone(), 2, 4).unwrap_err().0,
+            SlErrCode::IndexOutOfRange
+        );
+        assert_eq!(
+            slice(reg, a.clone(), 2, 1).unwrap_err().0,
+            SlErrCode::IndexOutOfRange
+        );
+
+        let ints = convert(reg, a.clone(), NumType::I32).unwrap();
+        assert_eq!(concat(reg, a, ints).unwrap_err().0, SlErrCode::TypeMismatch);
+    }
+}
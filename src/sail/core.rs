@@ -21,8 +21,10 @@
 
 //! TODO: Gradual typing; more extensible type system; subtypes
 
+use std::collections::{BTreeMap, HashMap};
 use std::mem;
 use std::ptr;
+use std::sync::Mutex;
 
 use super::thread::ThreadHull;
 
@@ -223,6 +225,18 @@ impl SlHndl {
         }
     }
 
+    /// Determines whether the object has a body of Sail code: either
+    /// a lambda procedure or a macro
+    #[inline(always)]
+    pub fn lambda_p(&mut self) -> bool {
+        unsafe { self.correct_pos() };
+
+        match self.core_type() {
+            Some(t) if t == CoreType::ProcLambda || t == CoreType::ProcMacro => true,
+            _ => false,
+        }
+    }
+
     #[inline(always)]
     pub fn type_fld_p(&mut self) -> bool {
         unsafe { self.correct_pos() };
@@ -419,10 +433,11 @@ enum_and_tryfrom! {
         VecArr = 0b11001000,
         VecAny = 0b11001100,
         VecHash = 0b11010000,
+        VecErr = 0b11010100,
         VecOther = 0b11011100,
         ProcLambda = 0b11100000,
         ProcNative = 0b11100100,
-        // ProcLbdaCk = 0b11101000,
+        ProcMacro = 0b11101000,
         // ProcNatvCk = 0b11101100,
         TyMfst = 0b11110000,
         EnvScope = 0b11110100,
@@ -494,8 +509,10 @@ pub enum CoreType {
     VecArr,
     VecAny,
     VecHash,
+    ErrObj,
     ProcLambda,
     ProcNative,
+    ProcMacro,
     EnvScope,
     EnvLayer,
 }
@@ -529,8 +546,10 @@ impl TryFrom<Cfg> for CoreType {
             Cfg::VecArr => Ok(Self::VecArr),
             Cfg::VecAny => Ok(Self::VecAny),
             Cfg::VecHash => Ok(Self::VecHash),
+            Cfg::VecErr => Ok(Self::ErrObj),
             Cfg::ProcLambda => Ok(Self::ProcLambda),
             Cfg::ProcNative => Ok(Self::ProcNative),
+            Cfg::ProcMacro => Ok(Self::ProcMacro),
             Cfg::TyMfst => Ok(Self::TyMfst),
             Cfg::EnvScope => Ok(Self::EnvScope),
             Cfg::EnvLayer => Ok(Self::EnvLayer),
@@ -584,6 +603,19 @@ pub const fn mode_of_sym(sym: u32) -> SymbolMode {
     unsafe { mem::transmute::<u8, SymbolMode>((sym >> 30) as u8) }
 }
 
+/// Symbols made by `gensym` count down from this ID; they are never
+/// entered in the symbol table, so no name the reader produces can
+/// refer to one
+pub const GENSYM_TOP: u32 = 0x3FFFFFFE;
+
+/// How many IDs below `GENSYM_TOP` are kept for `gensym`
+pub const GENSYM_SPAN: u32 = 1 << 29;
+
+/// Checks whether a symbol ID was made by `gensym`
+pub const fn gensym_id_p(sym: u32) -> bool {
+    sym <= GENSYM_TOP && sym > GENSYM_TOP - GENSYM_SPAN
+}
+
 /// Checks whether a valid Sail object contains the 4-byte type ID
 /// field
 pub fn raw_typ_fld_p(loc: *mut SlHead) -> bool {
@@ -776,7 +808,7 @@ pub fn ty_manifest_size(fldct: u32) -> u32 {
 /// Gives the overall size of a lambda procedure by argument count
 #[inline(always)]
 fn proc_lambda_size(argct: u16) -> u32 {
-    (NUM_16_LEN + PTR_LEN) + (SYMBOL_LEN * argct as u32)
+    (NUM_16_LEN + 2 * PTR_LEN) + (SYMBOL_LEN * argct as u32)
 }
 
 /// Gives the overall size of a native procedure
@@ -801,8 +833,12 @@ fn proc_native_size() -> u32 {
 // (maybe binary?) to verify whether target object resides in the
 // current region. can we do anything meaningful without region?
 
-/// Increment the reference count stored in a Sail object, up to a
-/// maximum of 255; if the count is at 255, return true
+/// References to objects beyond the 255 that their heads can count,
+/// by object address; only consulted once a head count saturates
+static REFC_SPILL: Mutex<BTreeMap<usize, u32>> = Mutex::new(BTreeMap::new());
+
+/// Increment the reference count stored in a Sail object; past 255,
+/// further references spill into a side table and this returns true
 pub fn inc_refc(loc: *mut SlHead) -> bool {
     assert!(!nil_p(loc));
 
@@ -813,6 +849,16 @@ pub fn inc_refc(loc: *mut SlHead) -> bool {
         match cur {
             0 => panic!("attempted reference to dead object"),
             255 => {
+                let mut spill = REFC_SPILL.lock().unwrap();
+
+                // a saturated count only changes under the table lock
+                cur = unsafe { std::intrinsics::atomic_load_acquire(rc_pos) };
+                if cur != 255 {
+                    continue;
+                }
+
+                *spill.entry(loc as usize).or_insert(0) += 1;
+
                 if cfg!(feature = "memdbg") {
                     println!("O {} SAT", unsafe {
                         ptr::read_unaligned((loc as *const u32).add(2))
@@ -844,8 +890,9 @@ pub fn inc_refc(loc: *mut SlHead) -> bool {
     false
 }
 
-/// Decrement the reference count stored in a Sail object, down to a
-/// minimum of 0; if the count reaches 0, return true
+/// Decrement the reference count stored in a Sail object (drawing
+/// from the side table first), down to a minimum of 0; if the count
+/// reaches 0, return true
 pub fn dec_refc(loc: *mut SlHead) -> bool {
     assert!(!nil_p(loc));
     let mut out;
@@ -858,11 +905,31 @@ pub fn dec_refc(loc: *mut SlHead) -> bool {
             0 => panic!("too many reference count decrements"),
             1 => out = true,
             255 => {
-                if cfg!(feature = "memdbg") {
-                    println!("O {} ESC", unsafe {
-                        ptr::read_unaligned((loc as *const u32).add(2))
-                    })
+                let mut spill = REFC_SPILL.lock().unwrap();
+
+                cur = unsafe { std::intrinsics::atomic_load_acquire(rc_pos) };
+                if cur != 255 {
+                    continue;
+                }
+
+                if let Some(extra) = spill.get_mut(&(loc as usize)) {
+                    *extra -= 1;
+                    if *extra == 0 {
+                        spill.remove(&(loc as usize));
+                    }
+
+                    if cfg!(feature = "memdbg") {
+                        println!("O {} ESC", unsafe {
+                            ptr::read_unaligned((loc as *const u32).add(2))
+                        })
+                    }
+                    return false;
                 }
+
+                // nothing has spilled, so the head holds the full count
+                // and no other thread may change it while we hold the
+                // table lock
+                unsafe { std::intrinsics::atomic_store_release(rc_pos, 254) };
                 return false;
             }
             _ => out = false,
@@ -889,15 +956,55 @@ pub fn dec_refc(loc: *mut SlHead) -> bool {
     out
 }
 
+/// Adds references to a fresh object whose head count has been set
+/// to 255, for counts that the head cannot hold
+pub fn refc_spill(loc: *mut SlHead, extra: u32) {
+    assert_eq!(_raw_refc_byte(loc), 255);
+
+    if extra > 0 {
+        *REFC_SPILL.lock().unwrap().entry(loc as usize).or_insert(0) += extra;
+    }
+}
+
+/// Forgets any spilled references of objects within the given range of
+/// addresses, as the memory there is freed; objects later made there
+/// must not inherit them
+pub fn refc_spill_forget(start: usize, end: usize) {
+    let mut spill = REFC_SPILL.lock().unwrap();
+
+    let mut within = spill.split_off(&start);
+    let mut after = within.split_off(&end);
+    spill.append(&mut after);
+}
+
+/// Returns the full reference count of a Sail object, including any
+/// references that have spilled beyond its head
+pub fn refc_total(loc: *mut SlHead) -> u32 {
+    match _raw_refc_byte(loc) {
+        255 => {
+            255 + REFC_SPILL
+                .lock()
+                .unwrap()
+                .get(&(loc as usize))
+                .copied()
+                .unwrap_or(0)
+        }
+        rc => rc as u32,
+    }
+}
+
 pub fn discern_ref_offsets_core(loc: *mut SlHead) -> Vec<u32> {
     let mut acc = Vec::new();
 
     unsafe {
         match raw_core_type(loc) {
             Some(CoreType::Ref) => acc.push(0),
-            Some(CoreType::ProcLambda) => acc.push(2),
+            Some(CoreType::ProcLambda) | Some(CoreType::ProcMacro) => {
+                acc.push(2);
+                acc.push(2 + PTR_LEN)
+            }
             Some(CoreType::TyDsc) => acc.push(8),
-            Some(CoreType::EnvScope) => {
+            Some(CoreType::EnvScope) | Some(CoreType::ErrObj) => {
                 for l in 0..3 {
                     acc.push(l * PTR_LEN)
                 }
@@ -918,6 +1025,7 @@ pub fn discern_ref_offsets_core(loc: *mut SlHead) -> Vec<u32> {
                 }
             }
             // TODO: Handle VecArr?
+            None if raw_typ_fld_p(loc) && raw_type_id(loc) == super::T_THR_REF_ID.0 => acc.push(8),
             _ => (),
         }
     }
@@ -952,7 +1060,9 @@ fn discern_refs_core(loc: *mut SlHead) -> Vec<*mut SlHead> {
 fn discern_refs(env: SlHndl, loc: *mut SlHead) -> Vec<*mut SlHead> {
     let acc = discern_refs_core(loc);
 
-    if raw_core_type(loc).is_none() {
+    // a redirect, left behind by an object which moved, holds only
+    // the object, as its next element
+    if raw_core_type(loc).is_none() && raw_typ_fld_p(loc) {
         let tid = raw_type_id(loc);
 
         // TODO: adapt to work with custom types in time
@@ -1037,6 +1147,83 @@ pub fn destroy_obj(env: SlHndl, loc: *mut SlHead) {
     unsafe { memmgt::dealloc(loc) }
 }
 
+/// Frees all objects in a region that are kept alive only by
+/// reference cycles, returning how many were freed
+///
+/// Any object with more references than can be found within the
+/// region is held from outside of it (by a handle, the evaluator,
+/// another region), so everything reachable from such objects
+/// survives; the rest may only be referenced by each other
+///
+/// # Safety
+///
+/// `reg` must point to a live region.
+pub unsafe fn collect_cycles(reg: *mut Region) -> usize {
+    unsafe {
+        (*reg).lock();
+        (*reg).collect_due = false;
+
+        let objects = memmgt::region_objects(reg);
+        let index: HashMap<*mut SlHead, usize> =
+            objects.iter().enumerate().map(|(i, o)| (*o, i)).collect();
+        let edges: Vec<Vec<*mut SlHead>> = objects.iter().map(|o| discern_refs_core(*o)).collect();
+
+        let mut outside: Vec<u32> = objects.iter().map(|o| refc_total(*o)).collect();
+
+        for r in edges.iter().flatten() {
+            if let Some(&i) = index.get(r) {
+                outside[i] = outside[i].saturating_sub(1);
+            }
+        }
+
+        let mut live = vec![false; objects.len()];
+        let mut pending: Vec<usize> = (0..objects.len()).filter(|i| outside[*i] > 0).collect();
+
+        while let Some(i) = pending.pop() {
+            if live[i] {
+                continue;
+            }
+            live[i] = true;
+
+            for r in &edges[i] {
+                match index.get(r) {
+                    Some(&j) if !live[j] => pending.push(j),
+                    _ => (),
+                }
+            }
+        }
+
+        // release everything the garbage holds beyond itself first,
+        // then free the garbage without following its references
+        for i in (0..objects.len()).filter(|i| !live[*i]) {
+            for r in &edges[i] {
+                match index.get(r) {
+                    Some(&j) if !live[j] => (),
+                    _ => {
+                        if dec_refc(*r) {
+                            destroy_obj_core(*r)
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut freed = 0;
+        for i in (0..objects.len()).filter(|i| !live[*i]) {
+            if _raw_refc_byte(objects[i]) == 255 {
+                REFC_SPILL.lock().unwrap().remove(&(objects[i] as usize));
+            }
+
+            memmgt::dealloc(objects[i]);
+            freed += 1;
+        }
+
+        (*reg).unlock();
+
+        freed
+    }
+}
+
 #[cfg(test)]
 mod refc_tests {
     use super::*;
@@ -1124,6 +1311,119 @@ mod refc_tests {
             assert_eq!(_raw_refc_byte(ptr_a), 0);
         }
     }
+
+    #[test]
+    fn saturation() {
+        unsafe {
+            let reg = memmgt::Region::acq(1000);
+
+            let item = u64_init(reg, 9);
+            let ptr_item = item.get_raw();
+
+            let clones: Vec<SlHndl> = (0..5000).map(|_| item.clone()).collect();
+
+            assert_eq!(_raw_refc_byte(ptr_item), 255);
+            assert_eq!(refc_total(ptr_item), 5001);
+
+            drop(clones);
+
+            assert_eq!(refc_total(ptr_item), 1);
+            assert_eq!(u64_get(item.clone()), 9);
+
+            drop(item);
+
+            let new = memmgt::alloc(reg, 8, memmgt::cap(Cfg::B8U64));
+            assert_eq!(ptr_item, new);
+        }
+    }
+
+    #[test]
+    fn saturation_threads() {
+        unsafe {
+            let reg = memmgt::Region::acq(1000);
+
+            let item = u64_init(reg, 9);
+            let ptr_item = item.get_raw();
+
+            // every thread repeatedly crosses the saturation boundary
+            std::thread::scope(|s| {
+                for _ in 0..4 {
+                    s.spawn(|| {
+                        for _ in 0..20 {
+                            let clones: Vec<SlHndl> = (0..300).map(|_| item.clone()).collect();
+                            drop(clones);
+                        }
+                    });
+                }
+            });
+
+            assert_eq!(refc_total(ptr_item), 1);
+        }
+    }
+
+    #[test]
+    fn saturation_dropped() {
+        unsafe {
+            let reg = memmgt::Region::acq(1000);
+
+            let item = u64_init(reg, 9);
+            let ptr_item = item.get_raw();
+
+            // leave the object saturated while its region goes away
+            let clones: Vec<SlHndl> = (0..400).map(|_| item.clone()).collect();
+            std::mem::forget(clones);
+            std::mem::forget(item);
+            assert_eq!(refc_total(ptr_item), 401);
+
+            drop(Box::from_raw(reg));
+
+            assert!(
+                !REFC_SPILL
+                    .lock()
+                    .unwrap()
+                    .contains_key(&(ptr_item as usize))
+            );
+        }
+    }
+
+    #[test]
+    fn cycles() {
+        unsafe {
+            let reg = memmgt::Region::acq(1000);
+
+            let dme = env_create(reg, None);
+
+            let held = u64_init(reg, 7);
+            let kept = ref_init(reg, held.clone());
+
+            // a ring of three list elements, each holding the last
+            let ring_a = bool_make(reg);
+            let ring_b = ref_init(reg, held.clone());
+            let ring_c = bool_make(reg);
+            set_next_list_elt(dme.clone(), ring_a.clone(), ring_b.clone());
+            set_next_list_elt(dme.clone(), ring_b.clone(), ring_c.clone());
+            set_next_list_elt(dme.clone(), ring_c.clone(), ring_a.clone());
+
+            // a pair of references pointing at each other
+            let pair_a = ref_make(reg);
+            let pair_b = ref_init(reg, pair_a.clone());
+            ref_set(dme.clone(), pair_a.clone(), pair_b.clone());
+
+            assert_eq!(collect_cycles(reg), 0);
+
+            drop((ring_a, ring_b, ring_c));
+            drop((pair_a, pair_b));
+
+            let ptr_held = held.get_raw();
+            assert_eq!(_raw_refc_byte(ptr_held), 3);
+
+            assert_eq!(collect_cycles(reg), 5);
+            assert_eq!(_raw_refc_byte(ptr_held), 2);
+
+            assert_eq!(u64_get(ref_get(kept).unwrap()), 7);
+            assert_eq!(collect_cycles(reg), 0);
+        }
+    }
 }
 
 pub fn write_ptr(env: SlHndl, mut loc: SlHndl, offset: u32, pto: SlHndl) {
@@ -1608,7 +1908,7 @@ pub fn hashvec_make(reg: *mut Region, size: u32) -> SlHndl {
 
 #[inline(always)]
 pub fn proc_lambda_make(reg: *mut Region, argct: u16) -> SlHndl {
-    // argct, pointer, (symbol * argct)
+    // argct, body pointer, env pointer, (symbol * argct)
     unsafe {
         let size = proc_lambda_size(argct);
         let out =
@@ -1621,6 +1921,26 @@ pub fn proc_lambda_make(reg: *mut Region, argct: u16) -> SlHndl {
     }
 }
 
+/// Creates a macro; the last argument may collect all remaining
+/// argument forms as a list
+///
+/// # Safety
+///
+/// `reg` must point to a live region.
+#[inline(always)]
+pub unsafe fn proc_macro_make(reg: *mut Region, argct: u16, rest: bool) -> SlHndl {
+    // argct & rest flag, body pointer, env pointer, (symbol * argct)
+    unsafe {
+        let size = proc_lambda_size(argct);
+        let out = SlHndl::from_raw_unchecked(memmgt::alloc(reg, size, memmgt::cap(Cfg::ProcMacro)));
+
+        let flag = if rest { PROC_REST_FLAG } else { 0 };
+        write_field_unchecked::<u16>(out.clone(), 0, argct | flag);
+
+        out
+    }
+}
+
 #[inline(always)]
 pub fn proc_native_make(reg: *mut Region, argct: u16) -> SlHndl {
     // argct, pointer
@@ -1812,6 +2132,21 @@ mod redir_test {
 
         assert_eq!(get_next_list_elt(fr).unwrap().raw, gt.raw);
     }
+
+    #[test]
+    fn string_grow() {
+        let region = memmgt::Region::acq(1000);
+        let s = string_init(region, "ab");
+
+        // blocks growth in place, so the string must move
+        let _after = bool_init(region, true);
+
+        string_set(s.clone(), "a much longer string");
+        let s = string_push(s, "!");
+
+        assert_eq!(string_get(s.clone()), "a much longer string!");
+        assert!(string_get_cap(s) >= 21);
+    }
 }
 
 #[inline(always)]
@@ -1842,6 +2177,31 @@ pub fn stdvec_push(mut loc: SlHndl, item: SlHndl) {
     stdvec_set_len(hdl, len + 1);
 }
 
+/// Removes and returns the last item of a standard vector, if any
+pub fn stdvec_pop(mut loc: SlHndl) -> Option<SlHndl> {
+    // ensure that we're pointing at the true object
+    unsafe { loc.correct_pos() };
+
+    let len = stdvec_get_len(loc.clone());
+    if len == 0 {
+        return None;
+    }
+
+    let item = stdvec_idx(loc.clone(), len - 1);
+
+    // the vector's reference passes to the returned handle
+    unsafe {
+        dec_refc(item.get_raw());
+        ptr::write_unaligned(
+            get_ptr_ptr_unchecked(loc.clone(), 4 + 4 + ((len - 1) * 8)),
+            nil(),
+        );
+    }
+
+    stdvec_set_len(loc, len - 1);
+    Some(item)
+}
+
 #[inline(always)]
 fn string_get_len(mut loc: SlHndl) -> u32 {
     coretypck!(loc ; VecStr);
@@ -1860,26 +2220,73 @@ fn string_get_cap(mut loc: SlHndl) -> u32 {
     read_field(loc, 0)
 }
 
+/// Grows an object to the given size; if it moves, the handle follows
+/// it past the redirect left behind
+fn resize_in_place(loc: &mut SlHndl, size: u32) {
+    unsafe {
+        let raw = loc.get_raw();
+        let new_ptr = memmgt::realloc(raw, size);
+
+        if new_ptr != raw {
+            loc.correct_pos();
+            // the count for the return; the redirect keeps its own
+            dec_refc(new_ptr);
+        }
+    }
+}
+
+/// Ensures a string can hold at least `cap` bytes, growing it if not;
+/// a grown string may move, leaving a redirect behind
+fn string_reserve(mut loc: SlHndl, cap: u32) -> SlHndl {
+    assert!(cap <= u32::MAX - 2 * NUM_32_LEN);
+
+    let cur = string_get_cap(loc.clone());
+    if cap <= cur {
+        return loc;
+    }
+
+    let new_cap = cap
+        .max(cur.saturating_mul(2))
+        .min(u32::MAX - 2 * NUM_32_LEN);
+
+    unsafe { loc.correct_pos() };
+    resize_in_place(&mut loc, vec_size(NUM_32_LEN * 2, NUM_8_LEN, new_cap));
+
+    write_field(loc.clone(), 0, new_cap);
+    loc
+}
+
+/// Replaces the contents of a string, growing it as needed
 #[inline(always)]
-pub fn string_set(mut loc: SlHndl, val: &str) {
-    assert!(val.len() <= u32::MAX as usize - 8);
+pub fn string_set(loc: SlHndl, val: &str) {
+    let len = u32::try_from(val.len()).expect("string too long");
+    let mut loc = string_reserve(loc, len);
 
-    // NOTE: if loc is a redirect, there will be two handle rewrites
-    // TODO: add a way to assert first that any rewrites are done
+    unsafe {
+        std::slice::from_raw_parts_mut(loc.value_ptr().add(2 * NUM_32_LEN as usize), len as _)
+            .copy_from_slice(val.as_bytes())
+    }
 
-    let cap = string_get_cap(loc.clone());
-    let len = val.len() as u32;
+    string_set_len(loc, len);
+}
 
-    if len <= cap {
-        unsafe {
-            std::slice::from_raw_parts_mut(loc.value_ptr().add(2 * NUM_32_LEN as usize), len as _)
-                .copy_from_slice(val.as_bytes())
-        }
+/// Appends to the contents of a string, growing it as needed; gives
+/// the string, which may have moved
+pub fn string_push(loc: SlHndl, val: &str) -> SlHndl {
+    let len = string_get_len(loc.clone());
+    let add = u32::try_from(val.len()).expect("string too long");
+    let mut loc = string_reserve(loc, len.checked_add(add).expect("string too long"));
 
-        string_set_len(loc, len);
-    } else {
-        panic!("not enough space in string");
+    unsafe {
+        std::slice::from_raw_parts_mut(
+            loc.value_ptr().add((2 * NUM_32_LEN + len) as usize),
+            add as _,
+        )
+        .copy_from_slice(val.as_bytes())
     }
+
+    string_set_len(loc.clone(), len + add);
+    loc
 }
 
 #[inline(always)]
@@ -1910,116 +2317,486 @@ fn id(fst: SlHndl, lst: SlHndl) -> bool {
 
 /// Returns true if both arguments' values are equal
 ///
-/// TODO: make eq and hash actually function for all types
-#[inline(always)]
+/// Lists, vectors, maps, arrays and errors are equal when their
+/// contents are; procedures and other objects only equal themselves.
 pub fn core_eq(mut fst: SlHndl, mut lst: SlHndl) -> bool {
     if id(fst.clone(), lst.clone()) {
         true
     } else {
         match fst.core_type() {
             Some(CoreType::Ref) => {
-                if ref_get(fst).is_none() {
-                    coretypp!(lst ; Ref) && ref_get(lst).is_none()
-                } else {
-                    false
+                if !coretypp!(lst ; Ref) {
+                    return false;
+                }
+                let (mut a, mut b) = (ref_get(fst), ref_get(lst));
+                loop {
+                    match (a, b) {
+                        (None, None) => return true,
+                        (Some(x), Some(y)) if core_eq(x.clone(), y.clone()) => {
+                            (a, b) = (get_next_list_elt(x), get_next_list_elt(y))
+                        }
+                        _ => return false,
+                    }
                 }
             }
             Some(CoreType::Nil) => coretypp!(lst ; Ref) && ref_get(lst).is_none(),
-            Some(typ) if typ != lst.core_type().unwrap() => false,
+            Some(typ) if lst.core_type().as_ref() != Some(&typ) => false,
             Some(CoreType::Bool) => bool_get(fst) == bool_get(lst),
             Some(CoreType::Symbol) => sym_get_id(fst) == sym_get_id(lst),
+            Some(CoreType::F32) => f32_get(fst) == f32_get(lst),
+            Some(CoreType::F64) => f64_get(fst) == f64_get(lst),
+            Some(typ) if int_type_p(&typ) => value_bytes(&mut fst) == value_bytes(&mut lst),
             Some(CoreType::VecStr) => string_get(fst) == string_get(lst),
+            Some(CoreType::VecStd) => {
+                let len = stdvec_get_len(fst.clone());
+                len == stdvec_get_len(lst.clone())
+                    && (0..len)
+                        .all(|i| core_eq(stdvec_idx(fst.clone(), i), stdvec_idx(lst.clone(), i)))
+            }
+            Some(CoreType::VecHash) => {
+                hash_map_len(fst.clone()) == hash_map_len(lst.clone())
+                    && hash_map_entries(fst).into_iter().all(|(key, val)| {
+                        hash_map_lookup(lst.clone(), key).is_some_and(|v| core_eq(val, v))
+                    })
+            }
+            Some(CoreType::VecArr) => {
+                match (super::arr::nums(fst.clone()), super::arr::nums(lst.clone())) {
+                    (Ok(a), Ok(b)) => a.1 == b.1 && a.0 == b.0,
+                    (Err(_), Err(_)) => {
+                        super::arrvec_get_typ(fst.clone()) == super::arrvec_get_typ(lst.clone())
+                            && value_bytes(&mut fst) == value_bytes(&mut lst)
+                    }
+                    _ => false,
+                }
+            }
+            Some(CoreType::ErrCode) => super::errcode_get(fst) == super::errcode_get(lst),
+            Some(CoreType::ErrObj) => {
+                super::err_get_code(fst.clone()) == super::err_get_code(lst.clone())
+                    && super::err_get_msg(fst.clone()) == super::err_get_msg(lst.clone())
+                    && match (super::err_get_form(fst), super::err_get_form(lst)) {
+                        (None, None) => true,
+                        (Some(a), Some(b)) => core_eq(a, b),
+                        _ => false,
+                    }
+            }
+            // objects of types beyond the core ones are scalars for now
+            None => {
+                fst.type_fld_p()
+                    && lst.type_fld_p()
+                    && fst.type_id() == lst.type_id()
+                    && value_bytes(&mut fst) == value_bytes(&mut lst)
+            }
             _ => false,
         }
     }
 }
 
-/// Computes a hash value for the provided object
+/// Computes a hash value for the provided object; objects equal under
+/// `core_eq` hash alike
 #[inline(always)]
 fn core_hash(mut loc: SlHndl) -> u32 {
-    match loc.core_type().expect("not a core type") {
-        CoreType::Symbol => sym_get_id(loc),
-        CoreType::VecStr => str_hash(string_get(loc)),
-        _ => 0,
+    match loc.core_type() {
+        Some(CoreType::Symbol) => sym_get_id(loc),
+        // adding zero folds negative zero into positive zero
+        Some(CoreType::F32) => bytes_hash(&(f32_get(loc) + 0.0).to_le_bytes()),
+        Some(CoreType::F64) => bytes_hash(&(f64_get(loc) + 0.0).to_le_bytes()),
+        Some(typ) if int_type_p(&typ) => bytes_hash(value_bytes(&mut loc)),
+        Some(CoreType::VecStr) => bytes_hash(string_get(loc).as_bytes()),
+        Some(_) => 0,
+        None => bytes_hash(value_bytes(&mut loc)),
     }
 }
 
-/// Provides a simple hash function for string slices
+/// Provides a simple hash function for byte slices
 #[inline(always)]
-fn str_hash(slice: &str) -> u32 {
+fn bytes_hash(slice: &[u8]) -> u32 {
     let mut out: u32 = 1;
-    for c in slice.bytes() {
+    for &c in slice {
         out = out.wrapping_add(out << 5).wrapping_add(c as u32);
     }
     out
 }
 
-// TODO: automatically resize as needed (probably as an option) (need "fill" field in subhead)
-// TODO: clean up shadowed entries sometime
-pub fn hash_map_insert(reg: *mut Region, loc: SlHndl, key: SlHndl, val: SlHndl) {
-    let entry = core_cons_copy(reg, key.clone(), val);
+#[inline(always)]
+fn int_type_p(typ: &CoreType) -> bool {
+    use CoreType::*;
+    matches!(
+        typ,
+        U8 | I8 | U16 | I16 | U32 | I32 | U64 | I64 | U128 | I128
+    )
+}
 
-    let size = hashvec_get_size(loc.clone());
-    let hash = core_hash(key) % size;
-    let idx = 4 + 4 + (hash * PTR_LEN);
+/// Borrows the bytes of a scalar object's value
+#[inline(always)]
+fn value_bytes(loc: &mut SlHndl) -> &[u8] {
+    let size = loc.size() as usize;
+    unsafe { std::slice::from_raw_parts(loc.value_ptr(), size) }
+}
+
+// Maps are chains of entries, each a reference to a copy of the key
+// whose next element is a copy of the value; a hash map keeps one
+// chain per bucket, while an association list map is a reference to
+// a single chain
+
+/// Past this proportion of entries to buckets, a hash map grows
+const HASH_MAP_LOAD: (u32, u32) = (3, 4);
+
+#[inline(always)]
+fn hashvec_get_fill(mut loc: SlHndl) -> u32 {
+    coretypck!(loc ; VecHash);
+    read_field(loc, NUM_32_LEN)
+}
+
+#[inline(always)]
+fn hashvec_set_fill(mut loc: SlHndl, fill: u32) {
+    coretypck!(loc ; VecHash);
+    write_field(loc, NUM_32_LEN, fill)
+}
+
+/// Gets the offset of the bucket where a key belongs in a hash map
+#[inline(always)]
+fn hashvec_bucket(loc: SlHndl, key: SlHndl) -> u32 {
+    let size = hashvec_get_size(loc);
+    2 * NUM_32_LEN + (core_hash(key) % size) * PTR_LEN
+}
 
-    let next = read_ptr(loc.clone(), idx);
+/// Writes a pointer field of an object within a map's structure,
+/// releasing the previous target
+#[inline(always)]
+fn map_write_ptr(mut loc: SlHndl, offset: u32, pto: Option<SlHndl>) {
+    assert!(offset + PTR_LEN <= loc.size());
 
-    if let Some(nx) = next {
-        // total references to next entry unchanged by insertion
-        unsafe { set_next_list_elt_unsafe_unchecked(entry.clone(), nx) };
+    unsafe {
+        let dst = loc.value_ptr().add(offset as usize) as *mut *mut SlHead;
+        let cur = ptr::read_unaligned(dst);
+
+        let tgt = match pto {
+            Some(p) => {
+                inc_refc(p.get_raw());
+                p.get_raw()
+            }
+            None => nil(),
+        };
+        ptr::write_unaligned(dst, tgt);
+
+        if !nil_p(cur) && dec_refc(cur) {
+            destroy_obj_core(cur)
+        }
     }
+}
+
+/// Sets the next element of an object within a map's structure,
+/// releasing the previous one
+#[inline(always)]
+fn map_set_next(loc: SlHndl, next: Option<SlHndl>) {
+    let prev = get_next_list_elt(loc.clone());
 
     unsafe {
-        inc_refc(entry.get_raw());
-        write_ptr_unsafe_unchecked(loc, idx, entry);
+        match next {
+            Some(n) => {
+                inc_refc(n.get_raw());
+                set_next_list_elt_unsafe_unchecked(loc, n)
+            }
+            None => {
+                let cfg = ptr::read_unaligned(loc.get_raw() as *mut u16);
+                ptr::write_unaligned(loc.get_raw() as *mut u64, cfg as u64)
+            }
+        }
+
+        // the handle held above keeps the count from reaching zero
+        if let Some(p) = prev {
+            dec_refc(p.get_raw());
+        }
     }
 }
 
-// fn alist_map_insert(reg: *mut Region, loc: *mut SlHead, key: *mut SlHead, val: *mut SlHead) {
-//     let entry = core_cons_copy(reg, key, val);
-//     let next = core_read_field(loc, 0);
-//     if !nil_p(next) {
-//         set_next_list_elt(entry, next);
-//     }
-//     core_write_field(loc, 0, entry)
-// }
+/// Finds the entry for a key in the chain whose head is the pointer
+/// at the given offset
+fn map_chain_find(loc: SlHndl, offset: u32, key: SlHndl) -> Option<SlHndl> {
+    let mut pos = read_ptr(loc, offset);
 
-// fn hash_map_lookup(loc: *mut SlHead, key: *mut SlHead) -> *mut SlHead {
-//     let size = hashvec_get_size(loc);
-//     let hash = core_hash(key) % size;
-//     let entry = core_read_field(loc, 4 + 4 + (hash as usize * PTR_LEN as usize));
-//     alist_search(entry, key)
-// }
+    while let Some(entry) = pos {
+        if core_eq(ref_get(entry.clone()).unwrap(), key.clone()) {
+            return Some(entry);
+        }
+        pos = get_next_list_elt(entry);
+    }
 
-// fn alist_map_lookup(loc: *mut SlHead, key: *mut SlHead) -> *mut SlHead {
-//     let entry = core_read_field(loc, 0);
-//     alist_search(entry, key)
-// }
+    None
+}
 
-// fn alist_search(head: *mut SlHead, target: *mut SlHead) -> *mut SlHead {
-//     let mut pos = head;
-//     loop {
-//         if nil_p(pos) {
-//             return nil();
-//         }
-//         if core_eq(ref_get(pos), target) {
-//             return pos;
-//         }
-//         pos = get_next_list_elt(pos);
-//     }
-// }
+/// Inserts a key and the element holding its value into the chain
+/// whose head is the pointer at the given offset, replacing any
+/// existing value; returns true if a new entry was added
+fn map_chain_insert(reg: *mut Region, loc: SlHndl, offset: u32, key: SlHndl, cell: SlHndl) -> bool {
+    match map_chain_find(loc.clone(), offset, key.clone()) {
+        Some(entry) => {
+            map_set_next(ref_get(entry).unwrap(), Some(cell));
+            false
+        }
+        None => {
+            let entry = map_entry_init(reg, key, cell);
+            map_set_next(entry.clone(), read_ptr(loc.clone(), offset));
+            map_write_ptr(loc, offset, Some(entry));
+            true
+        }
+    }
+}
+
+/// Unlinks the entry for a key from the chain whose head is the
+/// pointer at the given offset; returns true if one was found
+fn map_chain_remove(loc: SlHndl, offset: u32, key: SlHndl) -> bool {
+    let mut prev: Option<SlHndl> = None;
+    let mut pos = read_ptr(loc.clone(), offset);
+
+    while let Some(entry) = pos {
+        let next = get_next_list_elt(entry.clone());
+
+        if core_eq(ref_get(entry.clone()).unwrap(), key.clone()) {
+            match prev {
+                Some(p) => map_set_next(p, next),
+                None => map_write_ptr(loc, offset, next),
+            }
+            return true;
+        }
+
+        prev = Some(entry);
+        pos = next;
+    }
+
+    false
+}
+
+/// Collects the entries of the chain whose head is the pointer at the
+/// given offset
+fn map_chain_entries(loc: SlHndl, offset: u32, acc: &mut Vec<SlHndl>) {
+    let mut pos = read_ptr(loc, offset);
+
+    while let Some(entry) = pos {
+        pos = get_next_list_elt(entry.clone());
+        acc.push(entry);
+    }
+}
+
+/// Splits map entries into their keys and the elements holding their
+/// values
+fn map_entry_pairs(entries: Vec<SlHndl>) -> Vec<(SlHndl, SlHndl)> {
+    entries
+        .into_iter()
+        .map(|e| {
+            let key = ref_get(e).unwrap();
+            let val = get_next_list_elt(key.clone()).unwrap();
+            (key, val)
+        })
+        .collect()
+}
+
+/// Rebuilds a hash map with the given number of buckets, moving every
+/// entry to its new bucket
+fn hash_map_resize(mut loc: SlHndl, size: u32) -> SlHndl {
+    unsafe { loc.correct_pos() };
+
+    let old_size = hashvec_get_size(loc.clone());
+
+    let mut entries = vec![];
+    for i in 0..old_size {
+        let offset = 2 * NUM_32_LEN + i * PTR_LEN;
+        map_chain_entries(loc.clone(), offset, &mut entries);
+        map_write_ptr(loc.clone(), offset, None);
+    }
+
+    for e in &entries {
+        map_set_next(e.clone(), None);
+    }
+
+    resize_in_place(&mut loc, vec_size(NUM_32_LEN * 2, PTR_LEN, size));
+
+    write_field(loc.clone(), 0, size);
+
+    for e in entries {
+        let offset = hashvec_bucket(loc.clone(), ref_get(e.clone()).unwrap());
+        map_set_next(e.clone(), read_ptr(loc.clone(), offset));
+        map_write_ptr(loc.clone(), offset, Some(e));
+    }
+
+    loc
+}
+
+/// Moves every entry of a hash map to the bucket its key now hashes
+/// to, and recounts the entries; needed once keys have changed their
+/// hashes, as symbols do on entering another symbol table
+pub fn hash_map_rehash(loc: SlHndl) -> SlHndl {
+    let size = hashvec_get_size(loc.clone());
+    let loc = hash_map_resize(loc, size);
+
+    let fill = (0..size)
+        .map(|i| {
+            let mut entries = vec![];
+            map_chain_entries(loc.clone(), 2 * NUM_32_LEN + i * PTR_LEN, &mut entries);
+            entries.len() as u32
+        })
+        .sum();
+    hashvec_set_fill(loc.clone(), fill);
+
+    loc
+}
+
+/// Inserts a key and value into a hash map, replacing any existing
+/// value for the key; the map grows as it fills, so it may move.
+/// Like a vector, the map refers to the value rather than copying it
+pub fn hash_map_insert(reg: *mut Region, mut loc: SlHndl, key: SlHndl, val: SlHndl) {
+    let (size, fill) = (hashvec_get_size(loc.clone()), hashvec_get_fill(loc.clone()));
+
+    if (fill + 1) * HASH_MAP_LOAD.1 > size * HASH_MAP_LOAD.0
+        && !hash_map_contains(loc.clone(), key.clone())
+    {
+        loc = hash_map_resize(loc, (size * 2).max(2));
+    }
+
+    let offset = hashvec_bucket(loc.clone(), key.clone());
+    if map_chain_insert(reg, loc.clone(), offset, key, ref_init(reg, val)) {
+        hashvec_set_fill(loc, fill + 1);
+    }
+}
+
+/// Gets the value for a key in a hash map, if present
+pub fn hash_map_lookup(loc: SlHndl, key: SlHndl) -> Option<SlHndl> {
+    if hashvec_get_size(loc.clone()) == 0 {
+        return None;
+    }
+
+    let offset = hashvec_bucket(loc.clone(), key.clone());
+    map_chain_find(loc, offset, key)
+        .map(|e| ref_get(get_next_list_elt(ref_get(e).unwrap()).unwrap()).unwrap())
+}
+
+#[inline(always)]
+pub fn hash_map_contains(loc: SlHndl, key: SlHndl) -> bool {
+    hash_map_lookup(loc, key).is_some()
+}
+
+/// Removes the entry for a key from a hash map; returns true if the
+/// key was present
+pub fn hash_map_remove(loc: SlHndl, key: SlHndl) -> bool {
+    if hashvec_get_size(loc.clone()) == 0 {
+        return false;
+    }
+
+    let offset = hashvec_bucket(loc.clone(), key.clone());
+    let found = map_chain_remove(loc.clone(), offset, key);
+
+    if found {
+        let fill = hashvec_get_fill(loc.clone());
+        hashvec_set_fill(loc, fill - 1);
+    }
+
+    found
+}
+
+/// Gets every key and value in a hash map, in bucket order
+pub fn hash_map_entries(loc: SlHndl) -> Vec<(SlHndl, SlHndl)> {
+    let mut acc = vec![];
+    for i in 0..hashvec_get_size(loc.clone()) {
+        map_chain_entries(loc.clone(), 2 * NUM_32_LEN + i * PTR_LEN, &mut acc);
+    }
+    map_entry_pairs(acc)
+        .into_iter()
+        .map(|(key, cell)| (key, ref_get(cell).unwrap()))
+        .collect()
+}
+
+#[inline(always)]
+pub fn hash_map_len(loc: SlHndl) -> u32 {
+    hashvec_get_fill(loc)
+}
+
+/// Checks that a list is shaped as an association list map: each of
+/// its elements a list of exactly a key and a value
+pub fn alist_map_p(mut loc: SlHndl) -> bool {
+    if !coretypp!(loc ; Ref) {
+        return false;
+    }
+
+    let mut pos = ref_get(loc);
+    while let Some(mut entry) = pos {
+        pos = get_next_list_elt(entry.clone());
+
+        if !coretypp!(entry ; Ref) {
+            return false;
+        }
+        let pair = ref_get(entry)
+            .and_then(get_next_list_elt)
+            .is_some_and(|val| get_next_list_elt(val).is_none());
+        if !pair {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Inserts a key and value into an association list map, replacing
+/// any existing value for the key; new keys go to the front. The
+/// value is copied in as an element of the entry's list
+///
+/// # Safety
+///
+/// `reg` must point to a live region.
+pub unsafe fn alist_map_insert(reg: *mut Region, mut loc: SlHndl, key: SlHndl, val: SlHndl) {
+    coretypck!(loc ; Ref);
+    map_chain_insert(reg, loc, 0, key, unsafe { list_elt_copy(reg, val) });
+}
+
+/// Gets the value for a key in an association list map, if present
+pub fn alist_map_lookup(mut loc: SlHndl, key: SlHndl) -> Option<SlHndl> {
+    coretypck!(loc ; Ref);
+    map_chain_find(loc, 0, key).map(|e| get_next_list_elt(ref_get(e).unwrap()).unwrap())
+}
+
+#[inline(always)]
+pub fn alist_map_contains(loc: SlHndl, key: SlHndl) -> bool {
+    alist_map_lookup(loc, key).is_some()
+}
+
+/// Removes the entry for a key from an association list map; returns
+/// true if the key was present
+pub fn alist_map_remove(mut loc: SlHndl, key: SlHndl) -> bool {
+    coretypck!(loc ; Ref);
+    map_chain_remove(loc, 0, key)
+}
+
+/// Gets every key and value in an association list map, most recently
+/// added first
+pub fn alist_map_entries(mut loc: SlHndl) -> Vec<(SlHndl, SlHndl)> {
+    coretypck!(loc ; Ref);
+    let mut acc = vec![];
+    map_chain_entries(loc, 0, &mut acc);
+    map_entry_pairs(acc)
+}
+
+/// Marks a macro whose last argument collects the remaining forms
+const PROC_REST_FLAG: u16 = 0x8000;
 
 #[inline(always)]
 pub fn proc_get_argct(mut loc: SlHndl) -> u16 {
-    assert!(loc.proc_p());
-    read_field(loc, 0)
+    assert!(loc.proc_p() || loc.lambda_p());
+    read_field::<u16>(loc, 0) & !PROC_REST_FLAG
+}
+
+#[inline(always)]
+pub fn proc_macro_rest_p(mut loc: SlHndl) -> bool {
+    coretypck!(loc ; ProcMacro);
+    read_field::<u16>(loc, 0) & PROC_REST_FLAG != 0
 }
 
 #[inline(always)]
 pub fn proc_lambda_set_arg(mut loc: SlHndl, idx: u16, arg: u32) {
-    coretypck!(loc ; ProcLambda);
-    write_field(loc, (NUM_16_LEN + PTR_LEN) + (idx as u32 * SYMBOL_LEN), arg)
+    assert!(loc.lambda_p());
+    write_field(
+        loc,
+        (NUM_16_LEN + 2 * PTR_LEN) + (idx as u32 * SYMBOL_LEN),
+        arg,
+    )
 }
 
 #[inline(always)]
@@ -2029,13 +2806,13 @@ pub fn proc_lambda_get_arg(reg: *mut Region, loc: SlHndl, idx: u16) -> SlHndl {
 
 #[inline(always)]
 pub fn proc_lambda_get_arg_id(mut loc: SlHndl, idx: u16) -> u32 {
-    coretypck!(loc ; ProcLambda);
-    read_field(loc, (NUM_16_LEN + PTR_LEN) + (idx as u32 * SYMBOL_LEN))
+    assert!(loc.lambda_p());
+    read_field(loc, (NUM_16_LEN + 2 * PTR_LEN) + (idx as u32 * SYMBOL_LEN))
 }
 
 #[inline(always)]
 pub fn proc_lambda_set_body(mut loc: SlHndl, body: SlHndl) {
-    coretypck!(loc ; ProcLambda);
+    assert!(loc.lambda_p());
     unsafe {
         inc_refc(body.get_raw());
         write_ptr_unsafe_unchecked(loc, NUM_16_LEN, body);
@@ -2044,10 +2821,42 @@ pub fn proc_lambda_set_body(mut loc: SlHndl, body: SlHndl) {
 
 #[inline(always)]
 pub fn proc_lambda_get_body(mut loc: SlHndl) -> Option<SlHndl> {
-    coretypck!(loc ; ProcLambda);
+    assert!(loc.lambda_p());
     read_ptr(loc, NUM_16_LEN)
 }
 
+/// Sets the environment a lambda procedure or macro closes over; its
+/// body will be evaluated in a new scope within that environment
+#[inline(always)]
+pub fn proc_lambda_set_env(mut loc: SlHndl, scope: Option<SlHndl>) {
+    assert!(loc.lambda_p());
+    unsafe {
+        let dst = loc.value_ptr().add((NUM_16_LEN + PTR_LEN) as usize) as *mut *mut SlHead;
+        let cur = ptr::read_unaligned(dst);
+
+        let tgt = match scope {
+            Some(s) => {
+                inc_refc(s.get_raw());
+                s.get_raw()
+            }
+            None => nil(),
+        };
+        ptr::write_unaligned(dst, tgt);
+
+        if !nil_p(cur) && dec_refc(cur) {
+            destroy_obj_core(cur)
+        }
+    }
+}
+
+/// Gets the environment a lambda procedure or macro closes over, if
+/// any; without one, the body is evaluated within the caller's
+#[inline(always)]
+pub fn proc_lambda_get_env(mut loc: SlHndl) -> Option<SlHndl> {
+    assert!(loc.lambda_p());
+    read_ptr(loc, NUM_16_LEN + PTR_LEN)
+}
+
 #[inline(always)]
 pub fn proc_native_set_body(mut loc: SlHndl, fun: NativeFn) {
     coretypck!(loc ; ProcNative);
@@ -2082,19 +2891,47 @@ pub fn core_copy_val(reg: *mut Region, mut src: SlHndl) -> SlHndl {
     }
 }
 
-/// Copies the values from a pair of Sail objects of core types into a
-/// two element list structure
+/// Copies a Sail object so that the copy may be linked into another
+/// list; objects it references are shared rather than copied
+///
+/// # Safety
+///
+/// `reg` must point to a live region.
+pub unsafe fn list_elt_copy(reg: *mut Region, mut src: SlHndl) -> SlHndl {
+    let siz = src.size();
+    let cap = if src.type_fld_p() {
+        src.type_id()
+    } else {
+        memmgt::cap(src.cfg_byte().try_into().unwrap())
+    };
+
+    unsafe {
+        let mut dst = SlHndl::from_raw_unchecked(memmgt::alloc(reg, siz, cap));
+        ptr::copy_nonoverlapping(src.value_ptr(), dst.value_ptr(), siz as usize);
+
+        for ofs in discern_ref_offsets_core(dst.get_raw()) {
+            let nest = ptr::read_unaligned(dst.value_ptr().add(ofs as usize) as *mut *mut SlHead);
+            if !nil_p(nest) {
+                inc_refc(nest);
+            }
+        }
+
+        dst
+    }
+}
+
+/// Makes a map entry, a two element list of a copy of the key
+/// followed by the element holding the value
 #[inline(always)]
-fn core_cons_copy(reg: *mut Region, car: SlHndl, cdr: SlHndl) -> SlHndl {
-    let new_cdr = core_copy_val(reg, cdr);
-    let new_car = core_copy_val(reg, car);
+fn map_entry_init(reg: *mut Region, key: SlHndl, cell: SlHndl) -> SlHndl {
+    let new_key = unsafe { list_elt_copy(reg, key) };
 
     unsafe {
-        inc_refc(new_cdr.get_raw());
-        set_next_list_elt_unsafe_unchecked(new_car.clone(), new_cdr);
+        inc_refc(cell.get_raw());
+        set_next_list_elt_unsafe_unchecked(new_key.clone(), cell);
     }
 
-    ref_init(reg, new_car)
+    ref_init(reg, new_key)
 }
 
 // **********************************************************
@@ -2125,7 +2962,6 @@ fn core_cons_copy(reg: *mut Region, car: SlHndl, cdr: SlHndl) -> SlHndl {
 
 // TODO: deal somewhere with dynamic bindings, lexical bindings, argument bindings
 
-
 // TODO: should there be a small version (maybe an array with length 6
 // to 12) for procedure arguments? note need for modules and types
 
@@ -2246,11 +3082,11 @@ pub fn env_scope_ins(reg: *mut Region, env: SlHndl, mut sym: SlHndl, obj: SlHndl
     env_scope_ins_by_id(reg, env, sym_get_id(sym), obj)
 }
 
-// TODO: shadowing behavior within a scope is complete nonsense;
-// characterize and fix
-
 /// Inserts a symbol with the given ID into the environment, bound to
 /// the given object
+///
+/// A symbol already bound in this scope is rebound; bindings in
+/// parent scopes are shadowed, not changed.
 pub fn env_scope_ins_by_id(reg: *mut Region, mut env: SlHndl, sym_id: u32, obj: SlHndl) {
     coretypck!(env ; EnvScope);
 
@@ -2290,6 +3126,12 @@ pub fn env_scope_ins_by_id(reg: *mut Region, mut env: SlHndl, sym_id: u32, obj:
             let slot_id: u32 =
                 unsafe { read_field_unchecked(layer_ptr.clone().unwrap(), byte_offset) };
 
+            // lookups would find this earlier entry first
+            if slot_id == sym_id {
+                write_ptr(env, layer_ptr.unwrap(), byte_offset + SYMBOL_LEN, obj);
+                break 'layer;
+            }
+
             // there needs to be a way to discern an empty slot in all
             // three environment sections
             if slot_id >> 30 != SymbolMode::Keyword as u32 {
@@ -2310,6 +3152,64 @@ pub fn env_scope_ins_by_id(reg: *mut Region, mut env: SlHndl, sym_id: u32, obj:
     ()
 }
 
+// no symbol ever gets this ID, so lookups probe past a removed entry
+const ENV_SLOT_REMOVED: u32 = GENSYM_TOP + 1;
+
+/// Removes the given symbol's binding from this scope alone; parent
+/// scopes are left as they are
+///
+/// Returns false if there was no binding to remove, or true if there
+/// was
+pub fn env_scope_del(mut env: SlHndl, mut sym: SlHndl) -> bool {
+    coretypck!(env ; EnvScope);
+    coretypck!(sym ; Symbol);
+
+    let sym_id = sym_get_id(sym);
+    let layer_offset = match mode_of_sym(sym_id) {
+        SymbolMode::Basic => 0,
+        SymbolMode::Module => 8,
+        SymbolMode::Type => 16,
+        SymbolMode::Keyword => return false,
+    };
+
+    let entry_offset = sym_id % ENV_LAYER_SLOTS;
+    let mut layer_ptr = unsafe { read_ptr_unchecked(env.clone(), layer_offset) };
+
+    while let Some(layer) = layer_ptr {
+        for o in 0..ENV_MAX_PROBES {
+            let slot_offset = (entry_offset + o) % ENV_LAYER_SLOTS;
+            let byte_offset = slot_offset * (SYMBOL_LEN + PTR_LEN);
+
+            let slot_id: u32 = unsafe { read_field_unchecked(layer.clone(), byte_offset) };
+
+            if slot_id != sym_id {
+                if slot_id >> 30 == SymbolMode::Keyword as u32 {
+                    break;
+                }
+                continue;
+            }
+
+            unsafe {
+                write_field_unchecked(layer.clone(), byte_offset, ENV_SLOT_REMOVED);
+
+                let loc = get_ptr_ptr_unchecked(layer, byte_offset + SYMBOL_LEN);
+                let old = ptr::read_unaligned(loc);
+                ptr::write_unaligned(loc, ptr::null_mut());
+
+                if !nil_p(old) && dec_refc(old) {
+                    destroy_obj(env, old)
+                }
+            }
+
+            return true;
+        }
+
+        layer_ptr = get_next_list_elt(layer);
+    }
+
+    false
+}
+
 /// Looks up the given symbol ID in the given environment, returning
 /// the location in the environment of the object it refers to; None
 /// if no binding
@@ -2387,13 +3287,13 @@ pub fn u8_init(reg: *mut Region, val: u8) -> SlHndl {
 }
 
 #[inline(always)]
-fn _u8_set(mut loc: SlHndl, val: u8) {
+pub fn u8_set(mut loc: SlHndl, val: u8) {
     coretypck!(loc ; U8);
     write_field(loc, 0, val)
 }
 
 #[inline(always)]
-fn _u8_get(mut loc: SlHndl) -> u8 {
+pub fn u8_get(mut loc: SlHndl) -> u8 {
     coretypck!(loc ; U8);
     read_field(loc, 0)
 }
@@ -2404,20 +3304,20 @@ fn u16_make(reg: *mut Region) -> SlHndl {
 }
 
 #[inline(always)]
-fn u16_init(reg: *mut Region, val: u16) -> SlHndl {
+pub fn u16_init(reg: *mut Region, val: u16) -> SlHndl {
     let ptr = u16_make(reg);
     unsafe { write_field_unchecked(ptr.clone(), 0, val) };
     ptr
 }
 
 #[inline(always)]
-fn _u16_set(mut loc: SlHndl, val: u16) {
+pub fn u16_set(mut loc: SlHndl, val: u16) {
     coretypck!(loc ; U16);
     write_field(loc, 0, val)
 }
 
 #[inline(always)]
-fn _u16_get(mut loc: SlHndl) -> u16 {
+pub fn u16_get(mut loc: SlHndl) -> u16 {
     coretypck!(loc ; U16);
     read_field(loc, 0)
 }
@@ -2452,14 +3352,14 @@ fn u64_make(reg: *mut Region) -> SlHndl {
 }
 
 #[inline(always)]
-fn u64_init(reg: *mut Region, val: u64) -> SlHndl {
+pub fn u64_init(reg: *mut Region, val: u64) -> SlHndl {
     let ptr = u64_make(reg);
     unsafe { write_field_unchecked(ptr.clone(), 0, val) };
     ptr
 }
 
 #[inline(always)]
-fn _u64_set(mut loc: SlHndl, val: u64) {
+pub fn u64_set(mut loc: SlHndl, val: u64) {
     coretypck!(loc ; U64);
     write_field(loc, 0, val)
 }
@@ -2478,20 +3378,20 @@ fn u128_make(reg: *mut Region) -> SlHndl {
 }
 
 #[inline(always)]
-fn u128_init(reg: *mut Region, val: u128) -> SlHndl {
+pub fn u128_init(reg: *mut Region, val: u128) -> SlHndl {
     let ptr = u128_make(reg);
     unsafe { write_field_unchecked(ptr.clone(), 0, val) };
     ptr
 }
 
 #[inline(always)]
-fn _u128_set(mut loc: SlHndl, val: u128) {
+pub fn u128_set(mut loc: SlHndl, val: u128) {
     coretypck!(loc ; U128);
     write_field(loc, 0, val)
 }
 
 #[inline(always)]
-fn _u128_get(mut loc: SlHndl) -> u128 {
+pub fn u128_get(mut loc: SlHndl) -> u128 {
     coretypck!(loc ; U128);
     read_field(loc, 0)
 }
@@ -2502,20 +3402,20 @@ fn i8_make(reg: *mut Region) -> SlHndl {
 }
 
 #[inline(always)]
-fn i8_init(reg: *mut Region, val: i8) -> SlHndl {
+pub fn i8_init(reg: *mut Region, val: i8) -> SlHndl {
     let ptr = i8_make(reg);
     unsafe { write_field_unchecked(ptr.clone(), 0, val) };
     ptr
 }
 
 #[inline(always)]
-fn _i8_set(mut loc: SlHndl, val: i8) {
+pub fn i8_set(mut loc: SlHndl, val: i8) {
     coretypck!(loc ; I8);
     write_field(loc, 0, val)
 }
 
 #[inline(always)]
-fn _i8_get(mut loc: SlHndl) -> i8 {
+pub fn i8_get(mut loc: SlHndl) -> i8 {
     coretypck!(loc ; I8);
     read_field(loc, 0)
 }
@@ -2526,20 +3426,20 @@ fn i16_make(reg: *mut Region) -> SlHndl {
 }
 
 #[inline(always)]
-fn i16_init(reg: *mut Region, val: i16) -> SlHndl {
+pub fn i16_init(reg: *mut Region, val: i16) -> SlHndl {
     let ptr = i16_make(reg);
     unsafe { write_field_unchecked(ptr.clone(), 0, val) };
     ptr
 }
 
 #[inline(always)]
-fn _i16_set(mut loc: SlHndl, val: i16) {
+pub fn i16_set(mut loc: SlHndl, val: i16) {
     coretypck!(loc ; I16);
     write_field(loc, 0, val)
 }
 
 #[inline(always)]
-fn _i16_get(mut loc: SlHndl) -> i16 {
+pub fn i16_get(mut loc: SlHndl) -> i16 {
     coretypck!(loc ; I16);
     read_field(loc, 0)
 }
@@ -2550,20 +3450,20 @@ fn i32_make(reg: *mut Region) -> SlHndl {
 }
 
 #[inline(always)]
-fn i32_init(reg: *mut Region, val: i32) -> SlHndl {
+pub fn i32_init(reg: *mut Region, val: i32) -> SlHndl {
     let ptr = i32_make(reg);
     unsafe { write_field_unchecked(ptr.clone(), 0, val) };
     ptr
 }
 
 #[inline(always)]
-fn _i32_set(mut loc: SlHndl, val: i32) {
+pub fn i32_set(mut loc: SlHndl, val: i32) {
     coretypck!(loc ; I32);
     write_field(loc, 0, val)
 }
 
 #[inline(always)]
-fn _i32_get(mut loc: SlHndl) -> i32 {
+pub fn i32_get(mut loc: SlHndl) -> i32 {
     coretypck!(loc ; I32);
     read_field(loc, 0)
 }
@@ -2600,20 +3500,20 @@ fn i128_make(reg: *mut Region) -> SlHndl {
 }
 
 #[inline(always)]
-fn i128_init(reg: *mut Region, val: i128) -> SlHndl {
+pub fn i128_init(reg: *mut Region, val: i128) -> SlHndl {
     let ptr = i128_make(reg);
     unsafe { write_field_unchecked(ptr.clone(), 0, val) };
     ptr
 }
 
 #[inline(always)]
-fn _i128_set(mut loc: SlHndl, val: i128) {
+pub fn i128_set(mut loc: SlHndl, val: i128) {
     coretypck!(loc ; I128);
     write_field(loc, 0, val)
 }
 
 #[inline(always)]
-fn _i128_get(mut loc: SlHndl) -> i128 {
+pub fn i128_get(mut loc: SlHndl) -> i128 {
     coretypck!(loc ; I128);
     read_field(loc, 0)
 }
@@ -0,0 +1,532 @@
+// STARK, a system for computer augmented design.
+
+// SPDX-FileCopyrightText: © 2021 Matthew Rothlisberger
+// SPDX-License-Identifier: AGPL-3.0-only
+
+// STARK is licensed under the terms of the GNU Affero General Public
+// License version 3. See the top-level LICENSES directory for the
+// license text.
+
+// Find full copyright information in the top-level COPYRIGHT file.
+
+// <>
+
+// src/sail/doc.rs
+
+// The drawing document: everything drawn in a session, as the records
+// that drew it, each with its window and lines, and the styles the
+// lines are drawn in. Scripts hold a document as plain Sail maps and
+// vectors; on disk it is a short Sail program, written one item to a
+// line in a fixed order, so that saved drawings read well and diff
+// cleanly.
+
+// <>
+
+use super::core::*;
+use super::num::{Num, NumType};
+use super::{SlErrCode, Stab, arr, memmgt, parser};
+
+/// Reason a document could not be taken in, with a message for the
+/// error
+pub type DocErr = (SlErrCode, &'static str);
+
+/// Version of the document format; raised whenever the format changes
+pub const VERSION: i64 = 1;
+
+/// A named line color
+#[derive(Debug, Clone, PartialEq)]
+pub struct Style {
+    pub name: String,
+    pub color: [f32; 3],
+}
+
+/// A line between two points, in a window's coordinates
+#[derive(Debug, Clone, PartialEq)]
+pub struct Line {
+    pub style: String,
+    pub points: [f32; 4],
+}
+
+/// The part of a drawing made by one record: its window, as the top
+/// left and bottom right corners in frame coordinates, and its lines
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub window: [f32; 4],
+    pub lines: Vec<Line>,
+}
+
+/// A whole drawing
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Document {
+    pub styles: Vec<Style>,
+    pub records: Vec<Record>,
+}
+
+fn invalid<T>(msg: &'static str) -> Result<T, DocErr> {
+    Err((SlErrCode::TypeInvalid, msg))
+}
+
+/// Reads an array of finite f32 values of a given length
+fn f32s<const N: usize>(mut obj: SlHndl) -> Option<[f32; N]> {
+    if !coretypp!(obj ; VecArr) {
+        return None;
+    }
+
+    let (vals, typ) = arr::nums(obj).ok()?;
+    if typ != NumType::F32 {
+        return None;
+    }
+
+    let out: [f32; N] = vals
+        .into_iter()
+        .map(|n| match n {
+            Num::F32(v) => v,
+            _ => f32::NAN,
+        })
+        .collect::<Vec<_>>()
+        .try_into()
+        .ok()?;
+
+    out.iter().all(|v| v.is_finite()).then_some(out)
+}
+
+/// Writes f32 values as an array literal
+fn f32s_lit(vals: &[f32]) -> String {
+    let vals: Vec<String> = vals.iter().map(|v| Num::F32(*v).bare()).collect();
+    format!("#f32[{}]", vals.join(" "))
+}
+
+/// Gets the name of a symbol in the given mode
+fn sym_name(tbl: &Stab, mut obj: SlHndl, mode: SymbolMode) -> Option<String> {
+    if !coretypp!(obj ; Symbol) {
+        return None;
+    }
+
+    let id = sym_get_id(obj);
+    if mode_of_sym(id) != mode {
+        return None;
+    }
+
+    tbl.lookup_by_id(demodes_sym(id))
+        .map(|n| String::from_utf8_lossy(n).into_owned())
+}
+
+fn keyword(reg: *mut memmgt::Region, tbl: &mut Stab, name: &str) -> SlHndl {
+    sym_init(
+        reg,
+        modeize_sym(tbl.get_id(name.as_bytes()), SymbolMode::Keyword),
+    )
+}
+
+/// Gets the value under a keyword in a map
+fn map_field(tbl: &Stab, mut map: SlHndl, name: &str) -> Option<SlHndl> {
+    if !coretypp!(map ; VecHash) {
+        return None;
+    }
+
+    hash_map_entries(map)
+        .into_iter()
+        .find(|(k, _)| sym_name(tbl, k.clone(), SymbolMode::Keyword).as_deref() == Some(name))
+        .map(|(_, v)| v)
+}
+
+/// Gets the items of a vector
+fn vec_items(mut vec: SlHndl) -> Option<Vec<SlHndl>> {
+    if !coretypp!(vec ; VecStd) {
+        return None;
+    }
+
+    Some(
+        (0..stdvec_get_len(vec.clone()))
+            .map(|i| stdvec_idx(vec.clone(), i))
+            .collect(),
+    )
+}
+
+/// Gets the items of a list
+fn list_items(mut list: SlHndl) -> Option<Vec<SlHndl>> {
+    if !coretypp!(list ; Ref) {
+        return None;
+    }
+
+    let mut out = vec![];
+    let mut elt = ref_get(list);
+    while let Some(e) = elt {
+        elt = get_next_list_elt(e.clone());
+        out.push(e);
+    }
+    Some(out)
+}
+
+impl Document {
+    /// Checks that style names are unique and that every line is
+    /// drawn in a known style
+    fn check(&self) -> Result<(), DocErr> {
+        for (i, style) in self.styles.iter().enumerate() {
+            if self.styles[..i].iter().any(|s| s.name == style.name) {
+                return invalid("document names a style twice");
+            }
+        }
+
+        let known = |name: &str| self.styles.iter().any(|s| s.name == name);
+        if !self
+            .records
+            .iter()
+            .flat_map(|r| &r.lines)
+            .all(|l| known(&l.style))
+        {
+            return invalid("document line has an unknown style");
+        }
+
+        Ok(())
+    }
+
+    /// Takes a document from its Sail form: a map with `:styles`, a
+    /// map from style names to colors, and `:records`, a vector of
+    /// maps, each with a `:window` and a vector of `:lines`; each
+    /// line is a vector of its style name and its points
+    pub fn of_sail(tbl: &Stab, doc: SlHndl) -> Result<Self, DocErr> {
+        let (Some(styles), Some(records)) = (
+            map_field(tbl, doc.clone(), "styles"),
+            map_field(tbl, doc, "records"),
+        ) else {
+            return invalid("document must be a map with :styles and :records");
+        };
+
+        let mut out = Document::default();
+
+        let mut styles = styles;
+        if !coretypp!(styles ; VecHash) {
+            return invalid("document styles must be a map");
+        }
+        for (name, color) in hash_map_entries(styles) {
+            match (sym_name(tbl, name, SymbolMode::Keyword), f32s(color)) {
+                (Some(name), Some(color)) => out.styles.push(Style { name, color }),
+                _ => return invalid("style must be a keyword with an f32 color"),
+            }
+        }
+        out.styles.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let Some(records) = vec_items(records) else {
+            return invalid("document records must be a vector");
+        };
+        for rec in records {
+            let window = map_field(tbl, rec.clone(), "window").and_then(f32s);
+            let lines = map_field(tbl, rec, "lines").and_then(vec_items);
+            let (Some(window), Some(lines)) = (window, lines) else {
+                return invalid("record must be a map with :window and :lines");
+            };
+
+            let mut record = Record {
+                window,
+                lines: vec![],
+            };
+            for line in lines {
+                let line = vec_items(line).unwrap_or_default();
+                let [style, points] = &line[..] else {
+                    return invalid("line must be a vector of style and points");
+                };
+                match (
+                    sym_name(tbl, style.clone(), SymbolMode::Keyword),
+                    f32s(points.clone()),
+                ) {
+                    (Some(style), Some(points)) => record.lines.push(Line { style, points }),
+                    _ => return invalid("line must be a vector of style and points"),
+                }
+            }
+            out.records.push(record);
+        }
+
+        out.check()?;
+        Ok(out)
+    }
+
+    /// Gives a document in its Sail form, as taken by `of_sail`
+    pub fn to_sail(&self, reg: *mut memmgt::Region, tbl: &mut Stab) -> SlHndl {
+        let styles = hashvec_make(reg, 4);
+        for style in &self.styles {
+            let color = super::arrvec_init(reg, super::T_F32.0, 3, &style.color);
+            hash_map_insert(reg, styles.clone(), keyword(reg, tbl, &style.name), color);
+        }
+
+        let records = stdvec_make(reg, self.records.len() as u32);
+        for record in &self.records {
+            let lines = stdvec_make(reg, record.lines.len() as u32);
+            for line in &record.lines {
+                let points = super::arrvec_init(reg, super::T_F32.0, 4, &line.points);
+                let style = keyword(reg, tbl, &line.style);
+                stdvec_push(lines.clone(), stdvec_init(reg, &[style, points]));
+            }
+
+            let rec = hashvec_make(reg, 4);
+            let window = super::arrvec_init(reg, super::T_F32.0, 4, &record.window);
+            hash_map_insert(reg, rec.clone(), keyword(reg, tbl, "window"), window);
+            hash_map_insert(reg, rec.clone(), keyword(reg, tbl, "lines"), lines);
+            stdvec_push(records.clone(), rec);
+        }
+
+        let out = hashvec_make(reg, 4);
+        hash_map_insert(reg, out.clone(), keyword(reg, tbl, "styles"), styles);
+        hash_map_insert(reg, out.clone(), keyword(reg, tbl, "records"), records);
+        out
+    }
+
+    /// Writes a document as text: a version form, then a form for each
+    /// style, by name, then one for each record, in order, with each
+    /// of its lines on a line of its own
+    pub fn write(&self) -> String {
+        let mut out = format!("; STARK document\n\n(document {})\n", VERSION);
+
+        if !self.styles.is_empty() {
+            out.push('\n');
+        }
+        for style in &self.styles {
+            out += &format!("(style :{} {})\n", style.name, f32s_lit(&style.color));
+        }
+
+        for record in &self.records {
+            out += &format!("\n(record :window {}", f32s_lit(&record.window));
+            for line in &record.lines {
+                out += &format!("\n  (line :{} {})", line.style, f32s_lit(&line.points));
+            }
+            out += ")\n";
+        }
+
+        out
+    }
+
+    /// Reads a document written by `write`
+    pub fn read(reg: *mut memmgt::Region, tbl: &mut Stab, text: &str) -> Result<Self, DocErr> {
+        let prog = parser::parse(reg, tbl, text, true)
+            .map_err(|code| (code, "could not parse document"))?;
+
+        // the program reads as a `do` form
+        let forms = list_items(prog).unwrap_or_default();
+
+        let mut out = Document::default();
+        let mut version = None;
+
+        for form in forms.into_iter().skip(1) {
+            let items = list_items(form).unwrap_or_default();
+            let Some(head) = items
+                .first()
+                .and_then(|h| sym_name(tbl, h.clone(), SymbolMode::Basic))
+            else {
+                return invalid("document holds something other than a form");
+            };
+
+            match (head.as_str(), &items[1..]) {
+                ("document", [v])
+                    if version.is_none() && v.clone().core_type() == Some(CoreType::I64) =>
+                {
+                    version = Some(i64_get(v.clone()));
+                }
+                _ if version.is_none() => return invalid("document must start with its version"),
+                ("style", [name, color]) => {
+                    match (
+                        sym_name(tbl, name.clone(), SymbolMode::Keyword),
+                        f32s(color.clone()),
+                    ) {
+                        (Some(name), Some(color)) => out.styles.push(Style { name, color }),
+                        _ => return invalid("style must be a keyword with an f32 color"),
+                    }
+                }
+                ("record", [key, window, lines @ ..])
+                    if sym_name(tbl, key.clone(), SymbolMode::Keyword).as_deref()
+                        == Some("window") =>
+                {
+                    let Some(window) = f32s(window.clone()) else {
+                        return invalid("record window must be four f32 values");
+                    };
+
+                    let mut record = Record {
+                        window,
+                        lines: vec![],
+                    };
+                    for line in lines {
+                        let line = list_items(line.clone()).unwrap_or_default();
+                        let [head, style, points] = &line[..] else {
+                            return invalid("line must be a style and points");
+                        };
+                        let head = sym_name(tbl, head.clone(), SymbolMode::Basic);
+                        let style = sym_name(tbl, style.clone(), SymbolMode::Keyword);
+                        match (head.as_deref(), style, f32s(points.clone())) {
+                            (Some("line"), Some(style), Some(points)) => {
+                                record.lines.push(Line { style, points })
+                            }
+                            _ => return invalid("line must be a style and points"),
+                        }
+                    }
+                    out.records.push(record);
+                }
+                _ => return invalid("document holds an unknown form"),
+            }
+        }
+
+        match version {
+            None => return invalid("document must start with its version"),
+            Some(v) if v > VERSION => return invalid("document is from a newer STARK"),
+            _ => (),
+        }
+
+        out.check()?;
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{SYM_ARRAY, interpret};
+    use super::*;
+
+    fn sample() -> Document {
+        Document {
+            styles: vec![
+                Style {
+                    name: "ink".into(),
+                    color: [0.0, 0.0, 0.0],
+                },
+                Style {
+                    name: "red".into(),
+                    color: [1.0, 0.1, 0.0],
+                },
+            ],
+            records: vec![
+                Record {
+                    window: [-1.0, -1.0, 0.0, 1.0],
+                    lines: vec![
+                        Line {
+                            style: "ink".into(),
+                            points: [-0.5, 0.25, 0.5, 0.25],
+                        },
+                        Line {
+                            style: "red".into(),
+                            points: [0.0, 0.0, 1e-7, -0.75],
+                        },
+                    ],
+                },
+                Record {
+                    window: [0.0, -1.0, 1.0, 1.0],
+                    lines: vec![],
+                },
+            ],
+        }
+    }
+
+    fn table() -> Stab {
+        let mut tbl = Stab::new(51);
+        for s in SYM_ARRAY {
+            tbl.get_id(s.as_bytes());
+        }
+        tbl
+    }
+
+    #[test]
+    fn writes_text() {
+        assert_eq!(
+            sample().write(),
+            concat!(
+                "; STARK document\n",
+                "\n",
+                "(document 1)\n",
+                "\n",
+                "(style :ink #f32[0.0 0.0 0.0])\n",
+                "(style :red #f32[1.0 0.1 0.0])\n",
+                "\n",
+                "(record :window #f32[-1.0 -1.0 0.0 1.0]\n",
+                "  (line :ink #f32[-0.5 0.25 0.5 0.25])\n",
+                "  (line :red #f32[0.0 0.0 1e-7 -0.75]))\n",
+                "\n",
+                "(record :window #f32[0.0 -1.0 1.0 1.0])\n",
+            )
+        );
+    }
+
+    #[test]
+    fn round_trips() {
+        let (reg, mut tbl) = (memmgt::Region::acq(1000), table());
+
+        let text = sample().write();
+        let back = Document::read(reg, &mut tbl, &text).unwrap();
+        assert_eq!(back, sample());
+        assert_eq!(back.write(), text);
+
+        let val = sample().to_sail(reg, &mut tbl);
+        assert_eq!(Document::of_sail(&tbl, val).unwrap(), sample());
+
+        assert_eq!(
+            Document::read(reg, &mut tbl, "(document 1)").unwrap(),
+            Document::default()
+        );
+    }
+
+    #[test]
+    fn refuses_bad_documents() {
+        let (reg, mut tbl) = (memmgt::Region::acq(1000), table());
+        let mut read = |text| Document::read(reg, &mut tbl, text).unwrap_err();
+
+        assert_eq!(read("(document 1").0, SlErrCode::ParseUnexpectedEnd);
+        assert_eq!(
+            read("(style :ink #f32[0.0 0.0 0.0])"),
+            (
+                SlErrCode::TypeInvalid,
+                "document must start with its version"
+            )
+        );
+        assert_eq!(
+            read("(document 2)"),
+            (SlErrCode::TypeInvalid, "document is from a newer STARK")
+        );
+        assert_eq!(
+            read(
+                "(document 1) (record :window #f32[0.0 0.0 1.0 1.0] (line :ink #f32[0.0 0.0 1.0 1.0]))"
+            ),
+            (SlErrCode::TypeInvalid, "document line has an unknown style")
+        );
+        assert_eq!(
+            read("(document 1) (style :ink #f64[0.0 0.0 0.0])").0,
+            SlErrCode::TypeInvalid
+        );
+        assert_eq!(
+            read("(document 1) (record :window #f32[0.0 0.0 1.0])").0,
+            SlErrCode::TypeInvalid
+        );
+        assert_eq!(read("(document 1) (circle)").0, SlErrCode::TypeInvalid);
+    }
+
+    #[test]
+    fn saves_and_loads() {
+        let path = std::env::temp_dir().join(format!("stark-doc-{}.sl", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        let exp = format!(
+            "(do (def doc (map-make))
+                 (def styles (map-make))
+                 (map-set styles :ink (arr-vec-make $f32 3 (as-f32 0.5)))
+                 (def rec (map-make))
+                 (map-set rec :window (arr-vec-make $f32 4 (as-f32 1.0)))
+                 (def pts (arr-vec-make $f32 4 (as-f32 0.25)))
+                 (def line `[:ink ,pts])
+                 (map-set rec :lines `[,line])
+                 (map-set doc :styles styles)
+                 (map-set doc :records `[,rec])
+                 (save-doc \"{path}\" doc)
+                 (def back (load-doc \"{path}\"))
+                 (vec-get (vec-get (map-get (vec-get (map-get back :records) 0) :lines) 0) 1))"
+        );
+        assert_eq!("#f32[0.25 0.25 0.25 0.25]", interpret(&exp, false).unwrap());
+
+        let text = std::fs::read_to_string(path).unwrap();
+        assert!(text.contains("(style :ink #f32[0.5 0.5 0.5])"));
+        std::fs::remove_file(path).unwrap();
+
+        let exp = format!("(save-doc \"{path}\" (map-make))");
+        assert!(interpret(&exp, false).unwrap_err().contains("TypeInvalid"));
+        let exp = format!("(load-doc \"{path}\")");
+        assert!(
+            interpret(&exp, false)
+                .unwrap_err()
+                .contains("FileCouldNotRead")
+        );
+    }
+}
@@ -20,11 +20,17 @@
 // <>
 
 use super::core::*;
-use super::thread;
-use super::{SP_COND, SP_DEF, SP_DO, SP_EVAL, SP_FN, SP_IF, SP_QUOTE, SP_SET, SP_WHILE};
+use super::{
+    S_EXPORTS, S_REST, SP_COND, SP_DEF, SP_DEFMACRO, SP_DO, SP_EVAL, SP_EXPORT, SP_FN, SP_IF,
+    SP_IMPORT, SP_MACRO, SP_MODULE, SP_QUASI, SP_QUOTE, SP_SET, SP_SPLICE, SP_TRY, SP_UNQUOTE,
+    SP_WHILE,
+};
+use super::{SlErrCode, memmgt, thread};
 
 use std::alloc;
+use std::collections::HashMap;
 use std::ptr;
+use std::sync::atomic::{AtomicU32, Ordering};
 
 // TODO: make reference counting and garbage collection work with all
 // references kept by the evaluation machinery
@@ -39,8 +45,27 @@ pub struct EvalStack {
     stack_top: *mut usize,
     /// Start of the stack's top frame
     frame_start: *mut usize,
+    /// Count of the frames an error stops unwinding at, those of
+    /// `eval` and `try`; atomic, since a signal handler may read it
+    bounds: AtomicU32,
+    /// Expansions of the macro forms evaluated so far, by the address
+    /// of each form's head
+    expansions: HashMap<usize, Expansion>,
 }
 
+/// A macro form's expansion, with the head which keeps the form alive
+/// (so that its address stays its own) and the macro it came from
+/// (so that redefining the macro expands the form again)
+struct Expansion {
+    _head: SlHndl,
+    mac: SlHndl,
+    expr: SlHndl,
+}
+
+/// Count of expansions kept before they are all forgotten, since code
+/// built at runtime brings new forms without end
+const EXPANSIONS_MAX: usize = 4096;
+
 impl EvalStack {
     /// Create and initialize a new Sail stack and associated memory
     pub fn new(size: usize) -> Self {
@@ -54,6 +79,8 @@ impl EvalStack {
                 stack_max: stack.add(size_bytes) as *mut usize,
                 stack_top: stack as *mut usize,
                 frame_start: stack as *mut usize,
+                bounds: AtomicU32::new(0),
+                expansions: HashMap::new(),
             }
         }
     }
@@ -140,12 +167,16 @@ impl EvalStack {
         }
     }
 
+    /// Pushes an empty slot to the stack, to be filled in later
     fn push_slot(&mut self) {
         unsafe {
             if self.stack_top.add(1) > self.stack_max {
                 self.resize((self.stack_max as usize - self.stack_start as usize) / 4)
             }
 
+            // slots left empty by an unwind must read as nil
+            ptr::write(self.stack_top, 0);
+
             self.stack_top = self.stack_top.add(1)
         }
     }
@@ -202,6 +233,10 @@ impl EvalStack {
             let new_frame_start = self.stack_top;
             self.stack_top = new_frame_start.add(3);
 
+            if matches!(opc, Opcode::Contain | Opcode::Catch) {
+                self.bounds.fetch_add(1, Ordering::Release);
+            }
+
             ptr::write(
                 new_frame_start.add(FrameOffset::LastFrm as usize),
                 self.frame_start as usize,
@@ -237,6 +272,10 @@ impl EvalStack {
 
         let env = self.frame_top().1;
 
+        if matches!(self.frame_opc(), Opcode::Contain | Opcode::Catch) {
+            self.bounds.fetch_sub(1, Ordering::Release);
+        }
+
         unsafe {
             // the frame is entirely between these addresses
             let doomed_frame = self.frame_start;
@@ -276,16 +315,73 @@ impl EvalStack {
     }
 
     // TODO: use more of a condition system than an exception system eventually
-    fn unwind(&mut self, error: SlHndl) {
-        // destroy stack frames until reaching an error catch or the bottom
-        while self.frame_start > self.stack_start {
+    /// Destroys stack frames until reaching an error boundary or the
+    /// bottom, then delivers the error as the result there
+    ///
+    /// `ret` is the return address of the most recently removed
+    /// frame, used if the stack is already empty.
+    fn unwind(
+        &mut self,
+        error: SlHndl,
+        mut ret: *mut *mut SlHead,
+        thread_ref: *mut thread::ThreadHull,
+    ) {
+        while !self.is_empty() {
+            match self.frame_opc() {
+                Opcode::Contain => {
+                    ret = self.frame_addr(0);
+                    self.clear_slot(ret);
+                    break;
+                }
+                Opcode::Catch => {
+                    // the handler runs in place of the catch frame
+                    ret = self.frame_addr(1);
+                    self.clear_slot(ret);
+                    self.set_frame_opc(Opcode::Caught);
+                    self.bounds.fetch_sub(1, Ordering::Release);
+                    break;
+                }
+                Opcode::Source => {
+                    // errors from code loaded out of a file carry its
+                    // name, unless they come from another file within,
+                    // and the place of the form at fault if it is known
+                    let mut err = error.clone();
+                    if coretypp!(err ; ErrObj)
+                        && let (None, line, col) = super::err_get_span(err.clone())
+                    {
+                        let file = self.frame_obj(0);
+                        let pos = unsafe { thread_ref.as_ref() }
+                            .zip(super::err_get_form(err.clone()))
+                            .and_then(|(thr, form)| {
+                                thr.source_pos(string_get(file.clone()), &form)
+                            });
+
+                        match pos {
+                            Some(pos) => super::err_set_span(err, Some(file), pos.line, pos.col),
+                            None => super::err_set_span(err, Some(file), line, col),
+                        }
+                    }
+                }
+                Opcode::Module => {
+                    // an import that failed leaves no placeholder, so
+                    // that the module may be imported again
+                    let module = self.frame_obj(0);
+                    let env = self.frame_top().1;
+                    if env_lookup(env.clone(), module.clone())
+                        .is_some_and(|bound| loading_placeholder_p(&bound, &module))
+                    {
+                        env_scope_del(env, module);
+                    }
+                }
+                _ => {}
+            }
+
+            ret = self.frame_ret();
             self.pop_frame();
         }
 
-        unsafe {
-            let out = error.get_raw();
-            ptr::write(self.frame_ret(), out);
-            inc_refc(out);
+        if ret as *mut usize != self.stack_max {
+            self.write_addr_to(ret, error);
         }
     }
 
@@ -296,7 +392,33 @@ impl EvalStack {
         if !self.is_empty() {
             false
         } else {
-            self.eval_expr(ret, env, expr);
+            // evaluate within the first iteration, so that errors
+            // are handled like any other
+            self.push_frame_head(ret, Opcode::PreEval, env);
+            self.push(expr);
+            true
+        }
+    }
+
+    /// Starts evaluating a Sail expression loaded from the named
+    /// file, with the provided return location
+    ///
+    /// Returns false and does nothing if the stack is already in use
+    pub fn start_from(
+        &mut self,
+        ret: *mut *mut SlHead,
+        env: SlHndl,
+        expr: SlHndl,
+        file: SlHndl,
+    ) -> bool {
+        if !self.is_empty() {
+            false
+        } else {
+            self.push_source(ret, env.clone(), file, None);
+
+            let source_to = self.frame_addr(1);
+            self.push_frame_head(source_to, Opcode::PreEval, env);
+            self.push(expr);
             true
         }
     }
@@ -316,6 +438,29 @@ impl EvalStack {
         }
     }
 
+    /// Unwinds the stack with an error which arrives between steps,
+    /// as though the top frame had raised it
+    pub fn interrupt(&mut self, error: SlHndl) {
+        if !self.is_empty() {
+            let ret = self.frame_ret();
+            self.unwind(error, ret, ptr::null_mut());
+        }
+    }
+
+    /// Determines whether an error raised now would stop at an `eval`
+    /// or `try`, rather than unwind the whole stack
+    #[inline(always)]
+    pub fn bounded_p(&self) -> bool {
+        self.bounds.load(Ordering::Acquire) != 0
+    }
+
+    /// Removes every frame from the stack without evaluating further
+    pub fn abandon(&mut self) {
+        while !self.is_empty() {
+            self.pop_frame();
+        }
+    }
+
     /// Determines whether the stack is currently empty
     #[inline(always)]
     pub fn is_empty(&self) -> bool {
@@ -341,6 +486,27 @@ impl EvalStack {
         }
     }
 
+    /// Replaces the opcode of the current top frame
+    #[inline(always)]
+    fn set_frame_opc(&mut self, opc: Opcode) {
+        unsafe {
+            let env_opc = self.frame_start.add(FrameOffset::EnvOpc as usize);
+            ptr::write(env_opc, (ptr::read(env_opc) & !0xFFFF) + opc as usize);
+        }
+    }
+
+    /// Empties a slot in the current top frame, releasing any object
+    /// it held
+    fn clear_slot(&mut self, slot: *mut *mut SlHead) {
+        unsafe {
+            let old = ptr::read(slot);
+            ptr::write(slot, ptr::null_mut());
+            if !nil_p(old) && dec_refc(old) {
+                destroy_obj(self.frame_top().1, old)
+            }
+        }
+    }
+
     /// Returns all frame head components of the current top frame
     #[inline(always)]
     fn frame_top(&mut self) -> (*mut *mut SlHead, SlHndl, Opcode) {
@@ -377,22 +543,34 @@ impl EvalStack {
     /// Evaluates any Sail expression in object form, using the given
     /// environment and returning to the given location
     #[inline(always)]
-    fn eval_expr(&mut self, ret: *mut *mut SlHead, env: SlHndl, mut expr: SlHndl) {
+    fn eval_expr(
+        &mut self,
+        reg: *mut memmgt::Region,
+        ret: *mut *mut SlHead,
+        env: SlHndl,
+        mut expr: SlHndl,
+    ) -> Result<(), SlHndl> {
         if expr.nnil_ref_p() {
             self.push_frame_head(ret, Opcode::Eval, env.clone());
             self.push(ref_get(expr).unwrap());
-            return;
+            return Ok(());
         }
 
         if ret.is_null() {
-            return;
+            return Ok(());
         }
 
+        let copy;
         let out = if expr.basic_sym_p() {
-            match env_lookup(env.clone(), expr) {
+            match env_lookup(env.clone(), expr.clone()) {
                 Some(obj) => unsafe { obj.get_raw() },
-                None => panic!("symbol not bound in env"),
+                None => return Err(unbound(reg, expr)),
             }
+        } else if coretypp!(expr ; VecStr) {
+            // a string literal gives a new string each time, since
+            // changing one must not change the program
+            copy = string_init(reg, string_get(expr));
+            unsafe { copy.get_raw() }
         } else {
             unsafe { expr.get_raw() }
         };
@@ -405,6 +583,8 @@ impl EvalStack {
 
         // guarantee env stays alive until write-out is done
         drop(env);
+
+        Ok(())
     }
 
     /// Writes the address of a Sail object to an aligned position;
@@ -428,15 +608,30 @@ impl EvalStack {
     /// executing this function repeatedly will evaluate any Sail
     /// expression. It handles all defined opcodes and adds more
     /// frames to the stack as necessary, and never uses recursion.
+    ///
+    /// Errors do not escape this function; they unwind the stack to
+    /// the nearest `eval` boundary, or else become the thread result.
     pub fn iter_once(&mut self, thread_ref: *mut thread::ThreadHull) -> bool {
-        // ***********************************
-        // * Sail stack-based evaluation logic
-        // ***********************************
-
         if self.is_empty() {
             return false;
         }
 
+        let ret = self.frame_ret();
+
+        if let Err(err) = self.step(thread_ref) {
+            self.unwind(err, ret, thread_ref);
+        }
+
+        true
+    }
+
+    /// Executes the top frame of the stack, returning any error that
+    /// occurs without unwinding
+    fn step(&mut self, thread_ref: *mut thread::ThreadHull) -> Result<(), SlHndl> {
+        // ***********************************
+        // * Sail stack-based evaluation logic
+        // ***********************************
+
         let reg = unsafe { (*thread_ref).region() };
 
         let (ret, env, opc) = self.frame_top();
@@ -449,7 +644,7 @@ impl EvalStack {
             Opcode::PreEval => {
                 let expr = self.frame_obj(0);
                 self.pop_frame();
-                self.eval_expr(ret, env, expr);
+                self.eval_expr(reg, ret, env, expr)?;
             }
             Opcode::Eval => {
                 let list = self.frame_obj(0);
@@ -461,121 +656,315 @@ impl EvalStack {
                 if raw_op.basic_sym_p() {
                     match sym_get_id(raw_op.clone()) {
                         id if id == SP_COND.0 => {
+                            let Some(predicate) = raw_args else {
+                                return Err(bad_form(reg, raw_op, "cond called without arguments"));
+                            };
+                            let Some(consequent) = get_next_list_elt(predicate.clone()) else {
+                                return Err(bad_form(reg, raw_op, ODD_COND));
+                            };
+
                             self.push_frame_head(ret, Opcode::CondSeq, env.clone());
                             self.push_slot();
-
-                            let predicate = raw_args.expect("cond called without arguments");
-                            self.push(
-                                get_next_list_elt(predicate.clone())
-                                    .expect("cond must have an even number of arguments"),
-                            );
+                            self.push(consequent);
 
                             let pred_ret = self.frame_addr(0);
-                            self.eval_expr(pred_ret, env, predicate);
-                            return true;
+                            return self.eval_expr(reg, pred_ret, env, predicate);
                         }
-                        id if id == SP_DEF.0 => {
+                        id if id == SP_DEF.0 || id == SP_SET.0 => {
                             // needs: symbol to bind, object to bind to it
-                            self.push_frame_head(ret, Opcode::Bind, env.clone());
-                            self.push(raw_args.clone().unwrap());
+                            let Some(mut symbol) = raw_args else {
+                                return Err(bad_form(reg, raw_op, "no symbol given to bind"));
+                            };
+                            if !symbol.basic_sym_p() {
+                                return Err(bad_form(reg, raw_op, "only symbols may be bound"));
+                            }
+                            let Some(value) = get_next_list_elt(symbol.clone()) else {
+                                return Err(bad_form(reg, raw_op, "no value given to bind"));
+                            };
+
+                            let opc = if id == SP_DEF.0 {
+                                Opcode::Bind
+                            } else {
+                                Opcode::Mutate
+                            };
+
+                            self.push_frame_head(ret, opc, env.clone());
+                            self.push(symbol);
                             self.push_slot();
 
-                            let value = get_next_list_elt(raw_args.unwrap()).unwrap();
                             let return_to = self.frame_addr(1);
+                            return self.eval_expr(reg, return_to, env, value);
+                        }
+                        id if id == SP_DEFMACRO.0 => {
+                            // needs: symbol to bind, nothing else evaluated
+                            let Some(mut symbol) = raw_args else {
+                                return Err(bad_form(reg, raw_op, "no symbol given to bind"));
+                            };
+                            if !symbol.basic_sym_p() {
+                                return Err(bad_form(reg, raw_op, "only symbols may be bound"));
+                            }
 
-                            self.eval_expr(return_to, env, value);
-                            return true;
+                            let form = get_next_list_elt(symbol.clone());
+                            let mac = lambda_from_form(reg, env.clone(), raw_op, form, true)?;
+                            env_scope_ins(reg, env, symbol.clone(), mac);
+
+                            self.write_addr_to(ret, symbol);
+
+                            return Ok(());
                         }
                         id if id == SP_DO.0 => {
                             // needs: current remaining list of expressions
+                            let Some(body) = raw_args else {
+                                return Err(bad_form(reg, raw_op, "do called without arguments"));
+                            };
+
                             self.push_frame_head(ret, Opcode::DoSeq, env);
-                            self.push(raw_args.unwrap());
-                            return true;
+                            self.push(body);
+                            return Ok(());
                         }
                         id if id == SP_EVAL.0 => {
-                            self.push_frame_head(ret, Opcode::PreEval, env.clone());
+                            let Some(expr) = raw_args else {
+                                return Err(bad_form(reg, raw_op, "eval called without arguments"));
+                            };
+
+                            // errors raised within stop at this frame
+                            self.push_frame_head(ret, Opcode::Contain, env.clone());
+                            self.push_slot();
+
+                            let contain_to = self.frame_addr(0);
+
+                            self.push_frame_head(contain_to, Opcode::PreEval, env.clone());
                             self.push_slot();
 
                             let return_to = self.frame_addr(0);
+                            return self.eval_expr(reg, return_to, env, expr);
+                        }
+                        id if id == SP_EXPORT.0 => {
+                            // needs: symbols to export, nothing evaluated
+                            let Some(names) = env_lookup_by_id(env.clone(), S_EXPORTS.0) else {
+                                return Err(bad_form(reg, raw_op, "export used outside a module"));
+                            };
+
+                            let mut arg = raw_args;
+                            while let Some(mut sym) = arg {
+                                if !sym.basic_sym_p() {
+                                    return Err(bad_form(
+                                        reg,
+                                        raw_op,
+                                        "only symbols may be exported",
+                                    ));
+                                }
+                                arg = get_next_list_elt(sym.clone());
+
+                                stdvec_push(names.clone(), sym_init(reg, sym_get_id(sym)));
+                            }
 
-                            self.eval_expr(return_to, env, raw_args.unwrap());
-                            return true;
+                            return Ok(());
                         }
-                        id if id == SP_FN.0 => {
+                        id if id == SP_FN.0 || id == SP_MACRO.0 => {
                             // needs: nothing else evaluated
-                            // TODO: type annotation syntax / handling
-                            let argvec = raw_args.unwrap();
-                            let argct = stdvec_get_len(argvec.clone()) as u16;
-                            let proc = proc_lambda_make(reg, argct);
-                            for i in 0..argct {
-                                proc_lambda_set_arg(
-                                    proc.clone(),
-                                    i,
-                                    sym_get_id(stdvec_idx(argvec.clone(), i as u32)),
-                                );
-                            }
-                            proc_lambda_set_body(proc.clone(), get_next_list_elt(argvec).unwrap());
+                            let mac = id == SP_MACRO.0;
+                            let proc = lambda_from_form(reg, env, raw_op, raw_args, mac)?;
 
                             self.write_addr_to(ret, proc);
 
-                            return true;
+                            return Ok(());
                         }
                         id if id == SP_IF.0 => {
                             // needs: evaluated test and both branches
-                            self.push_frame_head(ret, Opcode::Branch, env.clone());
-                            self.push_slot();
-                            self.push(get_next_list_elt(raw_args.clone().unwrap()).unwrap());
-                            self.push(
-                                get_next_list_elt(
-                                    get_next_list_elt(raw_args.clone().unwrap()).unwrap(),
-                                )
-                                .unwrap(),
-                            );
+                            let branches = raw_args
+                                .clone()
+                                .and_then(get_next_list_elt)
+                                .and_then(|t| Some((t.clone(), get_next_list_elt(t)?)));
+                            let (Some(test), Some((true_body, false_body))) = (raw_args, branches)
+                            else {
+                                return Err(bad_form(
+                                    reg,
+                                    raw_op,
+                                    "if needs a test and two branches",
+                                ));
+                            };
 
                             // TODO: accept if with only one consequent (assume nil following)
 
+                            self.push_frame_head(ret, Opcode::Branch, env.clone());
+                            self.push_slot();
+                            self.push(true_body);
+                            self.push(false_body);
+
                             let return_to = self.frame_addr(0);
+                            return self.eval_expr(reg, return_to, env, test);
+                        }
+                        id if id == SP_IMPORT.0 => {
+                            // needs: module name, symbols to bring in
+                            let Some(module) = raw_args.filter(module_sym_p) else {
+                                return Err(bad_form(reg, raw_op, "import needs a module name"));
+                            };
+                            let names = get_next_list_elt(module.clone());
+
+                            match env_lookup(env.clone(), module.clone()) {
+                                Some(exports) if env_p(&exports) => {
+                                    import_names(reg, env, exports.clone(), names)?;
+                                    self.write_addr_to(ret, exports);
+                                    return Ok(());
+                                }
+                                Some(_) => {
+                                    return Err(bad_form(
+                                        reg,
+                                        raw_op,
+                                        "module imported while it is loading",
+                                    ));
+                                }
+                                None => (),
+                            }
+
+                            // the module is loaded into the top level,
+                            // so that any later import finds it there
+                            let (top, (file, body)) = unsafe {
+                                let thr = &mut *thread_ref;
+                                let source = super::module_source(thr, sym_get_id(module.clone()))?;
+                                (thr.top_env(), source)
+                            };
+
+                            // bound to its own name until it is loaded
+                            env_scope_ins(reg, top.clone(), module.clone(), module.clone());
+
+                            self.push_frame_head(ret, Opcode::Import, env);
+                            match names {
+                                Some(names) => self.push(names),
+                                None => self.push_slot(),
+                            }
+                            self.push_slot();
+
+                            let load_to = self.frame_addr(1);
+                            self.push_source(load_to, top.clone(), file, None);
+
+                            let source_to = self.frame_addr(1);
+                            self.push_module(reg, source_to, top, module, body);
 
-                            self.eval_expr(return_to, env, raw_args.unwrap());
-                            return true;
+                            return Ok(());
+                        }
+                        id if id == SP_MODULE.0 => {
+                            // needs: module name, body to evaluate
+                            let Some(module) = raw_args.filter(module_sym_p) else {
+                                return Err(bad_form(reg, raw_op, "module needs a module name"));
+                            };
+                            let body = get_next_list_elt(module.clone());
+
+                            self.push_module(reg, ret, env, module, body);
+                            return Ok(());
+                        }
+                        id if id == SP_QUASI.0 => {
+                            // needs: every unquoted expression evaluated
+                            let Some(template) = raw_args else {
+                                return Err(bad_form(
+                                    reg,
+                                    raw_op,
+                                    "quasiquote called without arguments",
+                                ));
+                            };
+
+                            let mut unquoted = Vec::new();
+                            quasi_collect(reg, template.clone(), 1, &mut unquoted)?;
+
+                            // construct template frame; slots must come first!
+                            self.push_frame_head(ret, Opcode::Quasi, env.clone());
+                            self.push(template);
+                            for _ in 0..unquoted.len() {
+                                self.push_slot()
+                            }
+
+                            let quasi_start = self.frame_start;
+
+                            for (i, expr) in unquoted.into_iter().enumerate() {
+                                let return_to = unsafe {
+                                    quasi_start.add(FrameOffset::ArgZero as usize + 1 + i)
+                                }
+                                    as *mut *mut SlHead;
+
+                                self.eval_expr(reg, return_to, env.clone(), expr)?;
+                            }
+
+                            return Ok(());
                         }
                         id if id == SP_QUOTE.0 => {
                             // needs: nothing else evaluated
-                            self.write_addr_to(ret, raw_args.unwrap());
+                            let Some(quoted) = raw_args else {
+                                return Err(bad_form(
+                                    reg,
+                                    raw_op,
+                                    "quote called without arguments",
+                                ));
+                            };
+
+                            self.write_addr_to(ret, quoted);
 
-                            return true;
+                            return Ok(());
                         }
-                        id if id == SP_SET.0 => {
-                            // needs: symbol to bind, object to bind to it
-                            self.push_frame_head(ret, Opcode::Mutate, env.clone());
-                            self.push(raw_args.clone().unwrap());
+                        id if id == SP_TRY.0 => {
+                            // needs: body to evaluate, handler for errors
+                            let (Some(body), Some(handler)) =
+                                (raw_args.clone(), raw_args.and_then(get_next_list_elt))
+                            else {
+                                return Err(bad_form(
+                                    reg,
+                                    raw_op,
+                                    "try needs a body and a handler",
+                                ));
+                            };
+
+                            self.push_frame_head(ret, Opcode::Catch, env.clone());
+                            self.push(handler);
                             self.push_slot();
 
-                            let value = get_next_list_elt(raw_args.unwrap()).unwrap();
                             let return_to = self.frame_addr(1);
-
-                            self.eval_expr(return_to, env, value);
-                            return true;
+                            return self.eval_expr(reg, return_to, env, body);
                         }
                         id if id == SP_WHILE.0 => {
+                            let (Some(pred), Some(body)) =
+                                (raw_args.clone(), raw_args.and_then(get_next_list_elt))
+                            else {
+                                return Err(bad_form(reg, raw_op, "while needs a test and a body"));
+                            };
+
                             self.push_frame_head(ret, Opcode::While, env.clone());
-                            self.push(raw_args.clone().unwrap());
+                            self.push(pred.clone());
                             self.push_slot();
-                            self.push(get_next_list_elt(raw_args.clone().unwrap()).unwrap());
+                            self.push(body);
 
                             let return_to = self.frame_addr(1);
-
-                            self.eval_expr(return_to, env, raw_args.unwrap());
-                            return true;
+                            return self.eval_expr(reg, return_to, env, pred);
                         }
                         _ => {}
                     }
                 }
 
+                if module_sym_p(&raw_op) {
+                    // a qualified name, read from @module/name
+                    let Some(member) = raw_args.filter(|a| a.clone().basic_sym_p()) else {
+                        return Err(bad_form(reg, raw_op, "a module name must qualify a symbol"));
+                    };
+
+                    let exports = match env_lookup(env, raw_op.clone()) {
+                        Some(m) if env_p(&m) => m,
+                        _ => return Err(unbound(reg, raw_op)),
+                    };
+
+                    match env_lookup(exports, member.clone()) {
+                        Some(obj) => self.write_addr_to(ret, obj),
+                        None => return Err(unbound(reg, member)),
+                    }
+
+                    return Ok(());
+                }
+
                 if raw_op.nnil_ref_p() {
                     self.push_frame_head(ret, Opcode::PreApp, env.clone());
                     self.push_slot();
-                    self.push(raw_args.unwrap());
+                    match raw_args {
+                        Some(args) => self.push(args),
+                        None => self.push_slot(),
+                    }
 
                     let return_to = self.frame_addr(0);
 
@@ -585,38 +974,71 @@ impl EvalStack {
                     let mut proc = if raw_op.basic_sym_p() {
                         match env_lookup(env.clone(), raw_op.clone()) {
                             Some(obj) => obj,
-                            None => panic!("symbol not bound in env"),
+                            None => return Err(unbound(reg, raw_op)),
                         }
                     } else {
                         raw_op.clone()
                     };
-                    assert!(proc.proc_p());
-                    let arg_ct = proc_get_argct(proc.clone());
+                    if coretypp!(proc ; ProcMacro) {
+                        let key = unsafe { raw_op.get_raw() as usize };
+                        if let Some(known) = self.expansions.get(&key)
+                            && unsafe { known.mac.get_raw() == proc.get_raw() }
+                        {
+                            let expansion = known.expr.clone();
+                            return self.eval_expr(reg, ret, env, expansion);
+                        }
+                        return self.push_expand(reg, ret, env, proc, raw_op, raw_args);
+                    }
+                    if !proc.proc_p() {
+                        return Err(unsafe {
+                            super::err_init(
+                                reg,
+                                SlErrCode::EvalNotProc,
+                                "only procedures may be applied",
+                                Some(ref_init(reg, raw_op)),
+                            )
+                        });
+                    }
 
-                    // construct application frame; slots must come first!
-                    self.push_frame_head(ret, Opcode::Apply, env.clone());
-                    self.push(proc);
-                    for _ in 0..arg_ct {
-                        self.push_slot()
+                    let arg_ct = proc_get_argct(proc.clone());
+                    if count_args(raw_args.clone()) != arg_ct as usize {
+                        return Err(arg_count(reg, arg_ct, ref_init(reg, raw_op)));
                     }
 
-                    let apply_start = self.frame_start;
+                    self.push_apply(reg, ret, env, proc, raw_args)?;
+                }
+            }
+            Opcode::Module => {
+                let module = self.frame_obj(0);
+                let menv = self.frame_obj(1);
+                self.pop_frame();
 
-                    let mut arg = raw_args;
-                    for i in 0..arg_ct {
-                        let return_to = unsafe {
-                            apply_start.add(FrameOffset::ArgZero as usize + 1 + i as usize)
-                        } as *mut *mut SlHead;
+                // only the exported bindings are visible from outside
+                let exports = env_create(reg, None);
+                let names = env_lookup_by_id(menv.clone(), S_EXPORTS.0).unwrap();
 
-                        self.eval_expr(return_to, env.clone(), arg.clone().unwrap());
+                for i in 0..stdvec_get_len(names.clone()) {
+                    let sym = stdvec_idx(names.clone(), i);
+                    let Some(obj) = env_lookup(menv.clone(), sym.clone()) else {
+                        return Err(unbound(reg, sym));
+                    };
 
-                        arg = get_next_list_elt(arg.unwrap())
-                    }
+                    env_scope_ins(reg, exports.clone(), sym, obj);
                 }
+
+                env_scope_ins(reg, env, module, exports.clone());
+                self.write_addr_to(ret, exports);
+            }
+            Opcode::Import => {
+                let names = unsafe { SlHndl::from_raw(ptr::read(self.frame_addr(0))) };
+                let exports = self.frame_obj(1);
+                self.pop_frame();
+
+                import_names(reg, env, exports.clone(), names)?;
+                self.write_addr_to(ret, exports);
             }
             Opcode::Bind => {
-                let mut symbol = self.frame_obj(0);
-                assert!(symbol.basic_sym_p());
+                let symbol = self.frame_obj(0);
                 let value = self.frame_obj(1);
 
                 self.pop_frame();
@@ -625,14 +1047,13 @@ impl EvalStack {
                 self.write_addr_to(ret, symbol);
             }
             Opcode::Mutate => {
-                let mut symbol = self.frame_obj(0);
-                assert!(symbol.basic_sym_p());
+                let symbol = self.frame_obj(0);
                 let value = self.frame_obj(1);
 
-                self.pop_frame();
                 if !env_scope_mut(env, symbol.clone(), value) {
-                    panic!("symbol not in env")
+                    return Err(unbound(reg, symbol));
                 }
+                self.pop_frame();
 
                 self.write_addr_to(ret, symbol);
             }
@@ -646,10 +1067,14 @@ impl EvalStack {
                     if remainder.nnil_ref_p() {
                         self.push_frame_head(ptr::null_mut() as _, Opcode::Eval, env);
                         self.push(ref_get(remainder).unwrap());
+                    } else if remainder.basic_sym_p()
+                        && env_lookup(env, remainder.clone()).is_none()
+                    {
+                        return Err(unbound(reg, remainder));
                     }
                 } else {
                     self.pop_frame();
-                    self.eval_expr(ret, env, remainder);
+                    self.eval_expr(reg, ret, env, remainder)?;
                 }
             }
             Opcode::While => {
@@ -659,15 +1084,17 @@ impl EvalStack {
 
                 if result.truthy() {
                     let return_to = self.frame_addr(1);
-                    self.eval_expr(return_to, env.clone(), pred);
 
                     // TODO: this sort of thing should be a (Rust) macro!
                     unsafe {
+                        ptr::write(return_to, ptr::null_mut());
                         if dec_refc(result.get_raw()) {
                             destroy_obj(env.clone(), result.get_raw())
                         }
                     };
 
+                    self.eval_expr(reg, return_to, env.clone(), pred)?;
+
                     self.push_frame_head(ptr::null_mut() as _, Opcode::DoSeq, env);
                     self.push(body);
                 } else {
@@ -681,16 +1108,30 @@ impl EvalStack {
 
                 if pred_res.truthy() {
                     self.pop_frame();
-                    self.eval_expr(ret, env, consequent);
+                    self.eval_expr(reg, ret, env, consequent)?;
                 } else if let Some(next_pred) = get_next_list_elt(consequent) {
-                    let next_cons = get_next_list_elt(next_pred.clone())
-                        .expect("cond must have an even number of arguments");
+                    let Some(next_cons) = get_next_list_elt(next_pred.clone()) else {
+                        return Err(bad_form(reg, next_pred, ODD_COND));
+                    };
 
                     let pred_ret = self.frame_addr(0);
                     let cons_keep = self.frame_addr(1);
 
-                    self.write_addr_to(cons_keep, next_cons);
-                    self.eval_expr(pred_ret, env, next_pred);
+                    unsafe {
+                        let old = ptr::read(pred_ret);
+                        ptr::write(pred_ret, ptr::null_mut());
+                        if !nil_p(old) && dec_refc(old) {
+                            destroy_obj(env.clone(), old)
+                        }
+
+                        let old = ptr::read(cons_keep);
+                        self.write_addr_to(cons_keep, next_cons);
+                        if !nil_p(old) && dec_refc(old) {
+                            destroy_obj(env.clone(), old)
+                        }
+                    }
+
+                    self.eval_expr(reg, pred_ret, env, next_pred)?;
                 } else {
                     self.pop_frame();
                     self.write_addr_to(ret, pred_res);
@@ -703,50 +1144,64 @@ impl EvalStack {
                 self.pop_frame();
 
                 if pred_res.truthy() {
-                    self.eval_expr(ret, env, true_body);
+                    self.eval_expr(reg, ret, env, true_body)?;
                 } else {
-                    self.eval_expr(ret, env, false_body);
+                    self.eval_expr(reg, ret, env, false_body)?;
                 }
             }
             Opcode::PreApp => {
                 let mut proc = self.frame_obj(0);
-                assert!(proc.proc_p());
-                let raw_args = self.frame_obj(1);
-                self.pop_frame();
-
-                // construct application frame; slots must come first!
-                self.push_frame_head(ret, Opcode::Apply, env.clone());
-                self.push(proc.clone());
-                for _ in 0..proc_get_argct(proc.clone()) {
-                    self.push_slot()
+                if !proc.proc_p() {
+                    return Err(unsafe {
+                        super::err_init(
+                            reg,
+                            SlErrCode::EvalNotProc,
+                            "only procedures may be applied",
+                            Some(proc),
+                        )
+                    });
                 }
 
-                let apply_start = self.frame_start;
+                let raw_args = unsafe { SlHndl::from_raw(ptr::read(self.frame_addr(1))) };
 
-                // NOTE: assume no particular argument evaluation order!
-                let mut arg = Some(raw_args);
-                for i in 0..proc_get_argct(proc) {
-                    let return_to =
-                        unsafe { apply_start.add(FrameOffset::ArgZero as usize + 1 + i as usize) }
-                            as *mut *mut SlHead;
+                let arg_ct = proc_get_argct(proc.clone());
+                if count_args(raw_args.clone()) != arg_ct as usize {
+                    return Err(arg_count(reg, arg_ct, proc));
+                }
 
-                    self.eval_expr(return_to, env.clone(), arg.clone().unwrap());
+                self.pop_frame();
 
-                    arg = get_next_list_elt(arg.unwrap());
-                }
+                self.push_apply(reg, ret, env, proc, raw_args)?;
             }
             Opcode::Apply => {
                 let mut proc = self.frame_obj(0);
                 let typ = match proc.core_type() {
-                    Some(t) if t == CoreType::ProcLambda => true,
+                    Some(t) if t == CoreType::ProcLambda || t == CoreType::ProcMacro => true,
                     Some(t) if t == CoreType::ProcNative => false,
-                    _ => panic!("not a proc"),
+                    _ => {
+                        return Err(unsafe {
+                            super::err_init(
+                                reg,
+                                SlErrCode::EvalNotProc,
+                                "only procedures may be applied",
+                                Some(proc),
+                            )
+                        });
+                    }
                 };
 
                 let argct = proc_get_argct(proc.clone());
 
+                let frame_args = (self.stack_top as usize - self.frame_addr(1) as usize) / 8;
+                if frame_args != argct as usize {
+                    return Err(arg_count(reg, argct, proc));
+                }
+
                 if typ {
-                    let proc_env = env_create(reg, Some(env));
+                    // the body is evaluated within the environment where
+                    // the procedure was created, if it has one
+                    let scope = proc_lambda_get_env(proc.clone()).unwrap_or(env);
+                    let proc_env = env_create(reg, Some(scope));
 
                     for i in 0..argct {
                         env_scope_ins_by_id(
@@ -757,10 +1212,17 @@ impl EvalStack {
                         );
                     }
 
+                    let body = proc_lambda_get_body(proc).unwrap();
                     self.pop_frame();
 
-                    self.push_frame_head(ret, Opcode::DoSeq, proc_env);
-                    self.push(proc_lambda_get_body(proc).unwrap());
+                    // the body takes the place of this frame, so calls
+                    // in tail position never deepen the stack
+                    if get_next_list_elt(body.clone()).is_none() {
+                        self.eval_expr(reg, ret, proc_env, body)?;
+                    } else {
+                        self.push_frame_head(ret, Opcode::DoSeq, proc_env);
+                        self.push(body);
+                    }
                 } else {
                     let args: &[SlHndl] = unsafe {
                         std::slice::from_raw_parts(self.frame_addr(1) as _, argct as usize)
@@ -768,17 +1230,549 @@ impl EvalStack {
 
                     let fn_rslt = proc_native_get_body(proc)(thread_ref, env, args);
 
+                    // the frame stays in place for unwinding
+                    if let Some(err) = unsafe { (*thread_ref).take_fault() } {
+                        return Err(err);
+                    }
+
+                    // a blocked procedure is applied again on waking
+                    if unsafe { (*thread_ref).blocked_p() } {
+                        return Ok(());
+                    }
+
+                    // code handed back runs in the top level, in place
+                    // of the procedure
+                    if let Some(handoff) = unsafe { (*thread_ref).take_handoff() } {
+                        self.pop_frame();
+
+                        let top = unsafe { (*thread_ref).top_env() };
+                        self.push_source(ret, top.clone(), handoff.file, handoff.value);
+
+                        let source_to = self.frame_addr(1);
+                        return self.eval_expr(reg, source_to, top, handoff.expr);
+                    }
+
                     self.write_addr_to(ret, fn_rslt);
 
                     self.pop_frame();
                 }
             }
+            Opcode::Catch => {
+                let result = self.frame_obj(1);
+                self.pop_frame();
+
+                self.write_addr_to(ret, result);
+            }
+            Opcode::Caught => {
+                let handler = self.frame_obj(0);
+                let error = self.frame_obj(1);
+                self.pop_frame();
+
+                // apply the handler to the error, outside the try
+                self.push_frame_head(ret, Opcode::Apply, env.clone());
+                self.push_slot();
+                self.push(error);
+
+                let proc_ret = self.frame_addr(0);
+                self.eval_expr(reg, proc_ret, env, handler)?;
+            }
+            Opcode::Contain => {
+                let result = self.frame_obj(0);
+                self.pop_frame();
+
+                self.write_addr_to(ret, result);
+            }
+            Opcode::Source => {
+                let value = unsafe { SlHndl::from_raw(ptr::read(self.frame_addr(2))) };
+                let result = self.frame_obj(1);
+                self.pop_frame();
+
+                self.write_addr_to(ret, value.unwrap_or(result));
+            }
+            Opcode::Expand => {
+                let expansion = self.frame_obj(0);
+                let head = self.frame_obj(1);
+                let mac = self.frame_obj(2);
+                self.pop_frame();
+
+                // kept aside rather than written into the form, which
+                // may be data the program still holds
+                if self.expansions.len() >= EXPANSIONS_MAX {
+                    self.expansions.clear();
+                }
+                let key = unsafe { head.get_raw() as usize };
+                let expr = expansion.clone();
+                self.expansions.insert(
+                    key,
+                    Expansion {
+                        _head: head,
+                        mac,
+                        expr,
+                    },
+                );
+
+                self.eval_expr(reg, ret, env, expansion)?;
+            }
+            Opcode::Quasi => {
+                let template = self.frame_obj(0);
+
+                let count = (self.stack_top as usize - self.frame_addr(1) as usize) / 8;
+                let mut values = Vec::with_capacity(count);
+                for i in 0..count {
+                    values.push(self.frame_obj(i + 1));
+                }
+
+                let result = quasi_build(reg, template, 1, &mut values.into_iter())?;
+                self.pop_frame();
+
+                self.write_addr_to(ret, result);
+            }
         }
 
-        true
+        Ok(())
+    }
+
+    /// Constructs an application frame for a procedure, then begins
+    /// evaluating its arguments
+    fn push_apply(
+        &mut self,
+        reg: *mut memmgt::Region,
+        ret: *mut *mut SlHead,
+        env: SlHndl,
+        proc: SlHndl,
+        raw_args: Option<SlHndl>,
+    ) -> Result<(), SlHndl> {
+        let arg_ct = proc_get_argct(proc.clone());
+
+        // construct application frame; slots must come first!
+        self.push_frame_head(ret, Opcode::Apply, env.clone());
+        self.push(proc);
+        for _ in 0..arg_ct {
+            self.push_slot()
+        }
+
+        let apply_start = self.frame_start;
+
+        // NOTE: assume no particular argument evaluation order!
+        let mut arg = raw_args;
+        for i in 0..arg_ct {
+            let return_to =
+                unsafe { apply_start.add(FrameOffset::ArgZero as usize + 1 + i as usize) }
+                    as *mut *mut SlHead;
+
+            let this = arg.unwrap();
+            arg = get_next_list_elt(this.clone());
+
+            self.eval_expr(reg, return_to, env.clone(), this)?;
+        }
+
+        Ok(())
+    }
+
+    /// Constructs a frame for code loaded from a file, to be
+    /// evaluated into its second slot; the frame gives either the
+    /// code's result or the value given here
+    fn push_source(
+        &mut self,
+        ret: *mut *mut SlHead,
+        env: SlHndl,
+        file: SlHndl,
+        value: Option<SlHndl>,
+    ) {
+        self.push_frame_head(ret, Opcode::Source, env);
+        self.push(file);
+        self.push_slot();
+        match value {
+            Some(value) => self.push(value),
+            None => self.push_slot(),
+        }
+    }
+
+    /// Constructs a module frame, then begins evaluating the module
+    /// body in a new environment within the given one
+    fn push_module(
+        &mut self,
+        reg: *mut memmgt::Region,
+        ret: *mut *mut SlHead,
+        env: SlHndl,
+        module: SlHndl,
+        body: Option<SlHndl>,
+    ) {
+        let menv = env_create(reg, Some(env.clone()));
+        env_scope_ins_by_id(reg, menv.clone(), S_EXPORTS.0, stdvec_make(reg, 4));
+
+        self.push_frame_head(ret, Opcode::Module, env);
+        self.push(module);
+        self.push(menv.clone());
+
+        if let Some(body) = body {
+            self.push_frame_head(ptr::null_mut() as _, Opcode::DoSeq, menv);
+            self.push(body);
+        }
+    }
+
+    /// Applies a macro to its argument forms, unevaluated, then
+    /// evaluates the expansion in place of the original form, which
+    /// starts with `head`; the expansion is kept for the form's next
+    /// evaluation
+    fn push_expand(
+        &mut self,
+        reg: *mut memmgt::Region,
+        ret: *mut *mut SlHead,
+        env: SlHndl,
+        mac: SlHndl,
+        head: SlHndl,
+        raw_args: Option<SlHndl>,
+    ) -> Result<(), SlHndl> {
+        let arg_ct = proc_get_argct(mac.clone());
+        let rest = proc_macro_rest_p(mac.clone());
+        let fixed = arg_ct - rest as u16;
+
+        let given = count_args(raw_args.clone());
+        if given < fixed as usize || (!rest && given > fixed as usize) {
+            return Err(arg_count(reg, arg_ct, mac));
+        }
+
+        self.push_frame_head(ret, Opcode::Expand, env.clone());
+        self.push_slot();
+        self.push(head);
+        self.push(mac.clone());
+
+        let expand_to = self.frame_addr(0);
+
+        self.push_frame_head(expand_to, Opcode::Apply, env);
+        self.push(mac);
+
+        let mut arg = raw_args;
+        for _ in 0..fixed {
+            let this = arg.unwrap();
+            arg = get_next_list_elt(this.clone());
+            self.push(this);
+        }
+
+        // the remaining forms are passed as a list
+        if rest {
+            match arg {
+                Some(first) => self.push(ref_init(reg, first)),
+                None => self.push(ref_make(reg)),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+const ODD_COND: &str = "cond must have an even number of arguments";
+
+/// Checks whether an object is a module name, as in `@geom`
+fn module_sym_p(obj: &SlHndl) -> bool {
+    let mut obj = obj.clone();
+    coretypp!(obj ; Symbol) && mode_of_sym(sym_get_id(obj)) == SymbolMode::Module
+}
+
+/// Checks whether an object is an environment, as modules are
+fn env_p(obj: &SlHndl) -> bool {
+    let mut obj = obj.clone();
+    coretypp!(obj ; EnvScope)
+}
+
+/// Checks whether a module name is bound to itself, as it is while
+/// the module loads
+fn loading_placeholder_p(bound: &SlHndl, module: &SlHndl) -> bool {
+    module_sym_p(bound) && sym_get_id(bound.clone()) == sym_get_id(module.clone())
+}
+
+/// Binds the named exports of a module in the given environment
+fn import_names(
+    reg: *mut memmgt::Region,
+    env: SlHndl,
+    exports: SlHndl,
+    names: Option<SlHndl>,
+) -> Result<(), SlHndl> {
+    let mut arg = names;
+    while let Some(mut sym) = arg {
+        if !sym.basic_sym_p() {
+            return Err(bad_form(reg, sym, "only symbols may be imported"));
+        }
+        arg = get_next_list_elt(sym.clone());
+
+        match env_lookup(exports.clone(), sym.clone()) {
+            Some(obj) => env_scope_ins(reg, env.clone(), sym, obj),
+            None => return Err(unbound(reg, sym)),
+        }
+    }
+
+    Ok(())
+}
+
+/// Creates an error for a malformed special form
+fn bad_form(reg: *mut memmgt::Region, form: SlHndl, msg: &str) -> SlHndl {
+    unsafe { super::err_init(reg, SlErrCode::EvalBadForm, msg, Some(ref_init(reg, form))) }
+}
+
+/// Creates an error for a symbol which is not bound in the environment
+fn unbound(reg: *mut memmgt::Region, sym: SlHndl) -> SlHndl {
+    unsafe {
+        super::err_init(
+            reg,
+            SlErrCode::EvalUnboundSymbol,
+            "symbol not bound in env",
+            Some(sym),
+        )
+    }
+}
+
+/// Creates an error for a procedure given the wrong number of arguments
+fn arg_count(reg: *mut memmgt::Region, expected: u16, form: SlHndl) -> SlHndl {
+    unsafe {
+        super::err_init(
+            reg,
+            SlErrCode::EvalArgCount,
+            &format!("procedure takes {} arguments", expected),
+            Some(form),
+        )
+    }
+}
+
+/// Creates a lambda procedure or a macro from an argument vector and
+/// a body, closing over the given environment; a macro may collect
+/// its remaining argument forms in the argument following `&`
+fn lambda_from_form(
+    reg: *mut memmgt::Region,
+    env: SlHndl,
+    raw_op: SlHndl,
+    raw_args: Option<SlHndl>,
+    mac: bool,
+) -> Result<SlHndl, SlHndl> {
+    // TODO: type annotation syntax / handling
+    let argvec = match raw_args {
+        Some(v) if v.clone().core_type() == Some(CoreType::VecStd) => v,
+        _ => return Err(bad_form(reg, raw_op, "fn needs an argument vector")),
+    };
+    let Some(body) = get_next_list_elt(argvec.clone()) else {
+        return Err(bad_form(reg, raw_op, "fn needs a body"));
+    };
+
+    let len = stdvec_get_len(argvec.clone());
+    let mut args = Vec::with_capacity(len as usize);
+    let mut rest = false;
+
+    for i in 0..len {
+        let mut arg = stdvec_idx(argvec.clone(), i);
+        if !arg.basic_sym_p() {
+            return Err(bad_form(reg, raw_op, "fn arguments must be symbols"));
+        }
+
+        let id = sym_get_id(arg);
+        if id == S_REST.0 {
+            if !mac || i + 2 != len {
+                return Err(bad_form(
+                    reg,
+                    raw_op,
+                    "& may only precede the last argument of a macro",
+                ));
+            }
+            rest = true;
+        } else {
+            args.push(id);
+        }
+    }
+
+    let argct = args.len() as u16;
+    let proc = if mac {
+        unsafe { proc_macro_make(reg, argct, rest) }
+    } else {
+        proc_lambda_make(reg, argct)
+    };
+
+    for (i, id) in args.into_iter().enumerate() {
+        proc_lambda_set_arg(proc.clone(), i as u16, id);
+    }
+    proc_lambda_set_body(proc.clone(), body);
+    proc_lambda_set_env(proc.clone(), Some(env));
+
+    Ok(proc)
+}
+
+/// Identifies a list beginning with one of the quasiquote operators
+fn quasi_op(mut node: SlHndl) -> Option<u32> {
+    if !node.nnil_ref_p() {
+        return None;
+    }
+
+    let mut first = ref_get(node).unwrap();
+    if !first.basic_sym_p() {
+        return None;
+    }
+
+    match sym_get_id(first) {
+        id if id == SP_QUASI.0 || id == SP_UNQUOTE.0 || id == SP_SPLICE.0 => Some(id),
+        _ => None,
+    }
+}
+
+/// Gathers the expressions unquoted in a quasiquote template, in the
+/// order that their values are consumed while building
+fn quasi_collect(
+    reg: *mut memmgt::Region,
+    mut node: SlHndl,
+    depth: u32,
+    acc: &mut Vec<SlHndl>,
+) -> Result<(), SlHndl> {
+    if node.nnil_ref_p() {
+        let first = ref_get(node.clone()).unwrap();
+
+        let depth = match quasi_op(node.clone()) {
+            Some(op) if op == SP_QUASI.0 => depth + 1,
+            Some(_) if depth == 1 => {
+                let Some(expr) = get_next_list_elt(first) else {
+                    return Err(bad_form(reg, node, "nothing given to unquote"));
+                };
+                acc.push(expr);
+                return Ok(());
+            }
+            Some(_) => depth - 1,
+            None => depth,
+        };
+
+        let mut elt = Some(first);
+        while let Some(e) = elt {
+            elt = get_next_list_elt(e.clone());
+            quasi_collect(reg, e, depth, acc)?;
+        }
+    } else if coretypp!(node ; VecStd) {
+        for i in 0..stdvec_get_len(node.clone()) {
+            quasi_collect(reg, stdvec_idx(node.clone(), i), depth, acc)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Builds a new structure from a quasiquote template, taking the
+/// value of each unquoted expression in turn
+fn quasi_build(
+    reg: *mut memmgt::Region,
+    mut node: SlHndl,
+    depth: u32,
+    values: &mut impl Iterator<Item = SlHndl>,
+) -> Result<SlHndl, SlHndl> {
+    if node.nnil_ref_p() {
+        let depth = match quasi_op(node.clone()) {
+            Some(op) if op == SP_QUASI.0 => depth + 1,
+            Some(op) if depth == 1 => {
+                if op == SP_SPLICE.0 {
+                    return Err(bad_form(
+                        reg,
+                        node,
+                        "splice must be within a list or vector",
+                    ));
+                }
+                return Ok(quasi_value(reg, values.next().unwrap()));
+            }
+            Some(_) => depth - 1,
+            None => depth,
+        };
+
+        let mut items = Vec::new();
+        let mut elt = ref_get(node);
+        while let Some(e) = elt {
+            elt = get_next_list_elt(e.clone());
+            quasi_item(reg, e, depth, values, &mut items)?;
+        }
+
+        let head = ref_make(reg);
+        let mut tail = head.clone();
+
+        for (i, item) in items.into_iter().enumerate() {
+            unsafe {
+                inc_refc(item.get_raw());
+                if i < 1 {
+                    write_ptr_unsafe_unchecked(tail, 0, item.clone())
+                } else {
+                    set_next_list_elt_unsafe_unchecked(tail, item.clone())
+                }
+            }
+            tail = item;
+        }
+
+        Ok(head)
+    } else if coretypp!(node ; VecStd) {
+        let mut items = Vec::new();
+        for i in 0..stdvec_get_len(node.clone()) {
+            quasi_item(reg, stdvec_idx(node.clone(), i), depth, values, &mut items)?;
+        }
+
+        Ok(stdvec_init(reg, &items))
+    } else {
+        Ok(unsafe { list_elt_copy(reg, node) })
     }
 }
 
+/// Adds one element of a quasiquote template to the items of the
+/// list or vector being built, splicing in values where requested
+fn quasi_item(
+    reg: *mut memmgt::Region,
+    elt: SlHndl,
+    depth: u32,
+    values: &mut impl Iterator<Item = SlHndl>,
+    items: &mut Vec<SlHndl>,
+) -> Result<(), SlHndl> {
+    if depth > 1 || quasi_op(elt.clone()) != Some(SP_SPLICE.0) {
+        items.push(quasi_build(reg, elt, depth, values)?);
+        return Ok(());
+    }
+
+    let mut spliced = values.next().unwrap();
+    match spliced.core_type() {
+        Some(CoreType::Nil) => {}
+        Some(CoreType::Ref) => {
+            let mut item = ref_get(spliced);
+            while let Some(i) = item {
+                item = get_next_list_elt(i.clone());
+                items.push(unsafe { list_elt_copy(reg, i) });
+            }
+        }
+        Some(CoreType::VecStd) => {
+            for i in 0..stdvec_get_len(spliced.clone()) {
+                items.push(unsafe { list_elt_copy(reg, stdvec_idx(spliced.clone(), i)) });
+            }
+        }
+        _ => {
+            return Err(unsafe {
+                super::err_init(
+                    reg,
+                    SlErrCode::TypeInvalid,
+                    "only lists and vectors may be spliced",
+                    Some(spliced),
+                )
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Prepares the value of an unquoted expression for its place in a
+/// new structure
+fn quasi_value(reg: *mut memmgt::Region, mut value: SlHndl) -> SlHndl {
+    if coretypp!(value ; Nil) {
+        ref_make(reg)
+    } else {
+        unsafe { list_elt_copy(reg, value) }
+    }
+}
+
+/// Counts the elements of an argument list
+fn count_args(mut arg: Option<SlHndl>) -> usize {
+    let mut count = 0;
+    while let Some(a) = arg {
+        count += 1;
+        arg = get_next_list_elt(a);
+    }
+    count
+}
+
 impl Drop for EvalStack {
     fn drop(&mut self) {
         unsafe {
@@ -846,6 +1840,93 @@ mod tests {
 
         assert_eq!(i64_get(result), 1);
     }
+
+    #[test]
+    fn unwind_check() {
+        let t_tact = thread::Tact::create(251);
+        let mut t_weft = thread::Weft::create(t_tact);
+
+        super::super::global_ctx_setup(t_weft.ctx_mut());
+
+        let t_thr = thread::ThreadHull::summon(&mut t_weft, 10000, 20000, None);
+
+        super::super::thread_env_setup(t_thr);
+
+        let thread_ref = unsafe { &mut *t_thr };
+
+        thread_ref
+            .load_from_text(&"(do (def f (fn [a] (+ a :k))) (+ 1 (f 2)))", false)
+            .unwrap();
+
+        while thread_ref.advance() {}
+
+        assert!(thread_ref.inert_p());
+
+        let mut result = thread_ref.result().unwrap();
+
+        assert!(coretypp!(result ; ErrObj));
+        assert_eq!(
+            super::super::err_get_code(result.clone()),
+            SlErrCode::TypeMismatch
+        );
+
+        // the thread remains usable after an error
+        thread_ref.load_from_text(&"(f (- 4 3))", false).unwrap();
+
+        while thread_ref.advance() {}
+
+        let mut result = thread_ref.result().unwrap();
+
+        assert!(coretypp!(result ; ErrObj));
+
+        thread_ref.load_from_text(&"(+ 1 2)", false).unwrap();
+
+        while thread_ref.advance() {}
+
+        assert_eq!(i64_get(thread_ref.result().unwrap()), 3);
+    }
+
+    #[test]
+    fn tail_calls() {
+        let t_tact = thread::Tact::create(251);
+        let mut t_weft = thread::Weft::create(t_tact);
+
+        super::super::global_ctx_setup(t_weft.ctx_mut());
+
+        let t_thr = thread::ThreadHull::summon(&mut t_weft, 10000, 1 << 20, None);
+
+        super::super::thread_env_setup(t_thr);
+
+        let thread_ref = unsafe { &mut *t_thr };
+
+        let text = "(do (def count (fn [n acc]
+                                     (cond (= n 0) acc
+                                           #T (do (if (= (mod n 2) 0)
+                                                      (count (- n 1) (+ acc 1))
+                                                      (count (- n 1) acc))))))
+                        (count 1000000 0))";
+
+        let expr = super::super::parser::parse(
+            thread_ref.region(),
+            thread_ref.context().symtab(),
+            text,
+            false,
+        )
+        .unwrap();
+
+        let mut stack = EvalStack::new(64);
+        let stack_max = stack.stack_max;
+
+        let mut out: *mut SlHead = ptr::null_mut();
+        stack.start(&mut out, thread_ref.top_env(), expr);
+
+        while stack.iter_once(t_thr) {
+            assert_eq!(stack.stack_max, stack_max);
+        }
+
+        let result = unsafe { SlHndl::from_raw(out).unwrap() };
+        assert_eq!(i64_get(result), 500000);
+    }
 }
 
 enum_and_tryfrom! {
@@ -883,11 +1964,29 @@ enum_and_tryfrom! {
         /// Procedure, all arguments
         Apply,
 
-        // /// Function to run with error
-        // Catch,
+        /// Result of an eval form; errors stop unwinding here
+        Contain,
+
+        /// Handler expression, result
+        Catch,
+
+        /// Handler expression, error caught
+        Caught,
+
+        /// Expansion of a macro form
+        Expand,
+
+        /// Quasiquote template, all unquoted values
+        Quasi,
+
+        /// Module name, module environment
+        Module,
+
+        /// Symbols to bind, exports of the loaded module
+        Import,
 
-        // /// Function to run, error caught
-        // Caught,
+        /// Name of the source file, result, value to give instead
+        Source,
     }
 }
 
@@ -919,8 +2018,7 @@ enum FrameOffset {
 }
 
 // TODO: call lambda functions using the stack?
-// TODO: **Macros**, closures, continuations
+// TODO: continuations
 // TODO: special forms may be examples for creating / using native functions cleanly
 // TODO: just like native functions, special forms should check for type
 // TODO: match the argument structure to the number of arguments needed
-// TODO: tail call optimization
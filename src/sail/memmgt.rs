@@ -19,11 +19,12 @@
 
 // <>
 
-use super::{SlHead, HEAD_LEN, NUM_32_LEN};
+use super::{HEAD_LEN, NUM_32_LEN, SlHead};
 
 use std::alloc;
 use std::mem;
 use std::ptr;
+use std::sync::Mutex;
 
 static mut OBJECT_ID_CTR: u32 = 0;
 
@@ -161,26 +162,34 @@ impl RegionTable {
 
             std::intrinsics::atomic_fence_acqrel();
 
-            let old_len = (*tgt).len;
+            // fill the first gap left by a removal, if there is one
+            let gap = (0..(*tgt).len).find(|i| ptr::read((*tgt).high_array.add(*i)) == 0);
 
-            if old_len >= (*tgt).cap {
-                Self::resize(tgt.as_mut_unchecked(), (*tgt).cap * 2);
-            }
+            let idx = match gap {
+                Some(idx) => idx,
+                None => {
+                    let old_len = (*tgt).len;
+
+                    if old_len >= (*tgt).cap {
+                        Self::resize(tgt.as_mut_unchecked(), (*tgt).cap * 2);
+                    }
 
-            (*tgt).len += 1;
+                    (*tgt).len += 1;
+
+                    old_len
+                }
+            };
 
             // TODO: use atomic_store_rel if needed?
-            ptr::write((*tgt).low_array.add(old_len), start);
-            ptr::write((*tgt).high_array.add(old_len), end);
-            ptr::write((*tgt).zone_array.add(old_len), zone);
-            ptr::write((*tgt).region_array.add(old_len), region);
+            ptr::write((*tgt).low_array.add(idx), start);
+            ptr::write((*tgt).high_array.add(idx), end);
+            ptr::write((*tgt).zone_array.add(idx), zone);
+            ptr::write((*tgt).region_array.add(idx), region);
 
             std::intrinsics::atomic_fence_acqrel();
 
             std::intrinsics::atomic_store_release(lock, false as u8);
 
-            println!("registered {:x} to {:x}", start, end);
-
             // if cfg!(feature = "memdbg") {
 
             // println!("- Zone added -");
@@ -202,6 +211,32 @@ impl RegionTable {
         }
     }
 
+    /// Clear the entry for the span beginning at the given address,
+    /// leaving a gap for a later entry to fill
+    unsafe fn remove(tgt: *mut Self, start: usize) {
+        unsafe {
+            let lock: *mut u8 = &raw mut (*tgt).lock;
+            while !std::intrinsics::atomic_cxchg_acqrel_acquire(lock, false as u8, true as u8).1 {
+                std::hint::spin_loop();
+            }
+
+            std::intrinsics::atomic_fence_acqrel();
+
+            if let Some(idx) =
+                (0..(*tgt).len).find(|i| ptr::read((*tgt).low_array.add(*i)) == start)
+            {
+                ptr::write((*tgt).low_array.add(idx), 0);
+                ptr::write((*tgt).high_array.add(idx), 0);
+                ptr::write((*tgt).zone_array.add(idx), ptr::null_mut());
+                ptr::write((*tgt).region_array.add(idx), ptr::null_mut());
+            }
+
+            std::intrinsics::atomic_fence_acqrel();
+
+            std::intrinsics::atomic_store_release(lock, false as u8);
+        }
+    }
+
     /// Gets a table entry by index
     unsafe fn index(tgt: *mut Self, idx: usize) -> (usize, usize, *mut Zone, *mut Region) {
         unsafe {
@@ -234,7 +269,8 @@ impl Drop for RegionTable {
         unsafe {
             for i in 0..self.len {
                 let cur_reg = ptr::read(self.region_array.add(i));
-                if cur_reg != last_reg {
+                let cur_zone = ptr::read(self.zone_array.add(i));
+                if !cur_zone.is_null() && cur_reg != last_reg {
                     drop(Box::from_raw(cur_reg));
                     last_reg = cur_reg;
                 }
@@ -303,6 +339,10 @@ pub const fn cap(cfg: super::Cfg) -> u32 {
 unsafe fn acquire_raw(reg: *mut Region, sz: u32) -> *mut u8 {
     unsafe {
         let region_ref = reg.as_mut().unwrap();
+
+        if large_p(region_ref, sz) {
+            return large_acquire(reg, sz);
+        }
         let mut zone_ref = region_ref.head.as_mut().unwrap();
 
         let ptr = loop {
@@ -440,6 +480,7 @@ unsafe fn zone_advance<'a>(region_ref: &mut Region, zone_ref: &'a mut Zone) -> &
         match zone_ref.next.as_mut() {
             Some(refer) => refer,
             None => {
+                region_ref.collect_due = true;
                 new_mem_zone(region_ref);
                 region_ref.head.as_mut().unwrap()
             }
@@ -596,7 +637,7 @@ unsafe fn void_seek(zone_ref: &mut Zone, obj_len: u32) -> Option<*mut u8> {
 /// redirect is left if there is more than one reference
 pub unsafe fn realloc(obj: *mut SlHead, size: u32) -> *mut SlHead {
     unsafe {
-        let cur_size = lblk_get_len(obj);
+        let cur_size = super::raw_size(obj);
         if size == cur_size {
             return obj;
         }
@@ -606,6 +647,7 @@ pub unsafe fn realloc(obj: *mut SlHead, size: u32) -> *mut SlHead {
         }
 
         let diff = size - cur_size;
+        let cur_len = lblk_get_len(obj);
 
         let size_fld_p = super::raw_siz_fld_p(obj);
         let type_fld_p = super::raw_typ_fld_p(obj);
@@ -617,9 +659,14 @@ pub unsafe fn realloc(obj: *mut SlHead, size: u32) -> *mut SlHead {
 
         let mut ret_ptr = obj;
 
-        let qp = znlck!(*c_zone => {
+        // large objects, and those that grow too large, always move
+        let qp = !c_zone.is_null()
+            && !large_p(&*c_regn, new_len)
+            && znlck!(*c_zone => {
         // first, check whether we happen to be at the end of the used area; extend if so
-        if super::raw_val_ptr(obj).add(cur_size as _) == (*c_zone).top {
+        if (obj as *mut u8).add(cur_len as _) == (*c_zone).top
+            && (*c_zone).top.add(diff as _) <= (*c_zone).end
+        {
             let ptr = (*c_zone).top;
             (*c_zone).used += diff;
             (*c_zone).top = ptr.add(diff as _);
@@ -637,8 +684,8 @@ pub unsafe fn realloc(obj: *mut SlHead, size: u32) -> *mut SlHead {
             let old_ptr = obj as *mut u8;
             let new_ptr = acquire_raw(c_regn, new_len);
 
-            ptr::copy_nonoverlapping(old_ptr, new_ptr, cur_size as _);
-            ptr::write_bytes(new_ptr.add(cur_size as _), 0, diff as _);
+            ptr::copy_nonoverlapping(old_ptr, new_ptr, cur_len as _);
+            ptr::write_bytes(new_ptr.add(cur_len as _), 0, diff as _);
 
             // set refct of new loc to 2, for the redir and the return
             ptr::write(new_ptr.add(1), 2);
@@ -652,8 +699,11 @@ pub unsafe fn realloc(obj: *mut SlHead, size: u32) -> *mut SlHead {
                 super::Cfg::B0Redir as u64 + ((old_refc as u64) << 8) + ((new_ptr as u64) << 16),
             );
 
-            // free all space not required for redirect (> 8 bytes)
-            reclaim_raw(old_ptr.add(HEAD_LEN as _), cur_size - HEAD_LEN);
+            // free all space not required for redirect (> 8 bytes); a
+            // large object's span is kept whole until the redirect dies
+            if !c_zone.is_null() {
+                reclaim_raw(old_ptr.add(HEAD_LEN as _), cur_len - HEAD_LEN);
+            }
         }
 
         if size_fld_p {
@@ -847,24 +897,27 @@ unsafe fn reclaim_raw(ptr: *mut u8, sz: u32) {
             }
 
             if !ub_parent.is_null() {
+                // the upper block's subtrees, joined, take its place;
+                // every block of the later one follows the earlier one
+                let rest = if ubprev.is_null() {
+                    ubnext
+                } else {
+                    if !ubnext.is_null() {
+                        insert_fblk_at(zone, ubprev, ubnext)
+                    }
+                    ubprev
+                };
+
                 if upper_bound < ub_parent {
                     assert!(newblk < ub_parent);
                     assert_eq!(upper_bound, fblk_get_prev_blk(zone, ub_parent));
 
-                    if !ubnext.is_null() {
-                        fblk_set_prev_blk(zone, ubnext, ubprev)
-                    }
-
-                    fblk_set_prev_blk(zone, ub_parent, ubnext)
+                    fblk_set_prev_blk(zone, ub_parent, rest)
                 } else if upper_bound > ub_parent {
                     assert!(newblk > ub_parent);
                     assert_eq!(upper_bound, fblk_get_next_blk(zone, ub_parent));
 
-                    if !ubprev.is_null() {
-                        fblk_set_next_blk(zone, ubprev, ubnext)
-                    }
-
-                    fblk_set_next_blk(zone, ub_parent, ubprev)
+                    fblk_set_next_blk(zone, ub_parent, rest)
                 }
             } else {
                 if !ubprev.is_null() && ubprev != lower_bound {
@@ -983,9 +1036,13 @@ pub unsafe fn dealloc(val: *mut SlHead) {
             println!("O {obj_id} RECLAIM")
         }
 
-        // TODO: panic if object appears to exceed zone's max obj size
+        let (region, zone) = which_mem_area(val);
 
-        reclaim_raw(val as _, lblk_get_len(val))
+        if zone.is_null() {
+            large_release(region, val as _)
+        } else {
+            reclaim_raw(val as _, lblk_get_len(val))
+        }
     }
 }
 
@@ -1441,8 +1498,109 @@ mod free_tree_tests {
             assert_eq!((*zone).bot, (*zone).top);
         }
     }
-}
 
+    #[test]
+    fn region_stats() {
+        use super::super::Cfg;
+
+        unsafe {
+            let region = Region::acq(1000);
+
+            let objs: Vec<_> = (0..6).map(|_| alloc(region, 8, cap(Cfg::B8U64))).collect();
+            let text = alloc(region, 20, cap(Cfg::VecStr));
+
+            // two separate gaps, one twice the size of the other
+            dealloc(objs[1]);
+            dealloc(objs[3]);
+            dealloc(objs[4]);
+
+            let stats = (*region).stats();
+
+            let obj_len = lblk_get_len(objs[0]) as usize;
+
+            assert_eq!(1, stats.zones);
+            assert_eq!(1000, stats.zone_bytes);
+            assert_eq!(3 * obj_len + lblk_get_len(text) as usize, stats.used_bytes);
+            assert_eq!(2, stats.free_blocks);
+            assert_eq!(3 * obj_len, stats.free_bytes);
+            assert_eq!(2 * obj_len, stats.largest_free);
+            assert!((stats.fragmentation() - 1.0 / 3.0).abs() < 1e-9);
+
+            assert_eq!(4, stats.live_objects());
+            assert_eq!(vec![(Cfg::B8U64, 3), (Cfg::VecStr, 1)], stats.live);
+        }
+    }
+
+    #[test]
+    fn joins_free_blocks() {
+        use super::super::Cfg;
+
+        unsafe {
+            let region = Region::acq(1000);
+
+            let objs: Vec<_> = (0..11).map(|_| alloc(region, 8, cap(Cfg::B8U64))).collect();
+
+            // the free tree runs 1 -> 9 -> 3 -> 7 -> 5; freeing 2 joins
+            // 1, 2 and 3, and 3's place goes to 7 along with all below it
+            for i in [1, 9, 3, 7, 5, 2] {
+                dealloc(objs[i]);
+            }
+
+            let stats = (*region).stats();
+            let obj_len = lblk_get_len(objs[0]) as usize;
+
+            assert_eq!(4, stats.free_blocks);
+            assert_eq!(6 * obj_len, stats.free_bytes);
+            assert_eq!(3 * obj_len, stats.largest_free);
+            assert_eq!(5, region_objects(region).len());
+        }
+    }
+
+    #[test]
+    fn large_objects() {
+        use super::super::Cfg;
+
+        unsafe {
+            let region = Region::acq(1000);
+
+            let zone = (*region).head;
+
+            let big = alloc(region, 4000, cap(Cfg::VecAny));
+            assert_eq!(which_mem_area(big), (region, ptr::null_mut()));
+            assert_eq!(0, (*zone).used);
+
+            ptr::write_bytes(super::super::raw_val_ptr(big), 7, 4000);
+
+            // a zone object which outgrows its zone moves out of it
+            let small = alloc(region, 16, cap(Cfg::VecAny));
+            let moved = realloc(small, 900);
+            assert_eq!(which_mem_area(moved), (region, ptr::null_mut()));
+            assert_eq!(HEAD_LEN, (*zone).used);
+
+            let bigger = realloc(big, 8000);
+            assert_eq!(7, ptr::read(super::super::raw_val_ptr(bigger).add(3999)));
+            assert_eq!(0, ptr::read(super::super::raw_val_ptr(bigger).add(4000)));
+            assert_eq!(3, (*region).large.lock().unwrap().len());
+
+            let stats = (*region).stats();
+            assert_eq!(3, stats.large_objects);
+            assert_eq!(
+                4000 + 900 + 8000 + 3 * (HEAD_LEN + NUM_32_LEN) as usize,
+                stats.large_bytes
+            );
+
+            dealloc(small);
+            dealloc(moved);
+            dealloc(big);
+            dealloc(bigger);
+
+            assert_eq!(0, (*zone).used);
+            assert!((*region).large.lock().unwrap().is_empty());
+
+            drop(Box::from_raw(region));
+        }
+    }
+}
 
 // TODO: store zone location information?
 
@@ -1452,6 +1610,14 @@ mod free_tree_tests {
 pub struct Region {
     pub head: *mut Zone,
     pub zone_size: u32,
+    /// Set when every zone is full and another must be created; the
+    /// owning thread collects cycles at its next opportunity
+    pub collect_due: bool,
+    /// Held while cycles are collected, and while objects are copied
+    /// into the region from elsewhere
+    lock: u8,
+    /// Start and length of every block in the large object space
+    large: Mutex<Vec<(usize, u32)>>,
 }
 
 impl Region {
@@ -1459,6 +1625,9 @@ impl Region {
         Self {
             zone_size,
             head: ptr::null_mut(),
+            collect_due: false,
+            lock: false as u8,
+            large: Mutex::new(Vec::new()),
         }
     }
 
@@ -1471,6 +1640,186 @@ impl Region {
     pub fn init(&mut self) {
         unsafe { new_mem_zone(self) };
     }
+
+    /// Wait for and take the region lock
+    pub fn lock(&mut self) {
+        let lock: *mut u8 = &mut self.lock;
+        while !unsafe {
+            std::intrinsics::atomic_cxchg_acqrel_acquire(lock, false as u8, true as u8)
+        }
+        .1
+        {
+            std::hint::spin_loop();
+        }
+    }
+
+    /// Release the region lock
+    pub fn unlock(&mut self) {
+        unsafe { std::intrinsics::atomic_store_release(&mut self.lock, false as u8) };
+    }
+
+    /// Surveys every zone and the large object space, summarizing how
+    /// the region's memory is being used
+    pub fn stats(&self) -> RegionStats {
+        let mut stats = RegionStats::default();
+        let mut live = Vec::new();
+
+        unsafe {
+            let mut zone = self.head;
+            while !zone.is_null() {
+                let zone_ref = zone.as_mut().unwrap();
+
+                for (_, len) in zone_blocks(zone_ref, &mut live) {
+                    stats.free_blocks += 1;
+                    stats.free_bytes += len;
+                    stats.largest_free = stats.largest_free.max(len);
+                }
+
+                stats.zones += 1;
+                stats.zone_bytes += self.zone_size as usize;
+                stats.used_bytes += zone_ref.used as usize;
+                stats.empty_bytes += zone_ref.end as usize - zone_ref.top as usize;
+
+                zone = zone_ref.next;
+            }
+
+            for (start, len) in self.large.lock().unwrap().iter() {
+                stats.large_objects += 1;
+                stats.large_bytes += *len as usize;
+                live.push(*start as *mut SlHead);
+            }
+        }
+
+        for obj in live {
+            let cfg = super::raw_cfg_spec(obj);
+            match stats.live.iter_mut().find(|(c, _)| *c == cfg) {
+                Some((_, count)) => *count += 1,
+                None => stats.live.push((cfg, 1)),
+            }
+        }
+
+        stats.live.sort_by_key(|(c, _)| *c as u8);
+
+        stats
+    }
+}
+
+/// A summary of a region's memory use, taken by `Region::stats`
+#[derive(Debug, Default)]
+pub struct RegionStats {
+    /// Number of zones in the region
+    pub zones: usize,
+    /// Total capacity of all zones
+    pub zone_bytes: usize,
+    /// Bytes in zones taken by live objects
+    pub used_bytes: usize,
+    /// Bytes in zones never yet allocated (past the top of each)
+    pub empty_bytes: usize,
+    /// Number of blocks in all free trees
+    pub free_blocks: usize,
+    /// Bytes in all free trees
+    pub free_bytes: usize,
+    /// Length of the largest block in any free tree
+    pub largest_free: usize,
+    /// Number of objects in the large object space
+    pub large_objects: usize,
+    /// Bytes in the large object space
+    pub large_bytes: usize,
+    /// Count of live objects for each configuration present
+    pub live: Vec<(super::Cfg, usize)>,
+}
+
+impl RegionStats {
+    /// Share of free tree bytes lying outside the largest free block,
+    /// from 0 (none, or all in one block) toward 1 (finely scattered)
+    pub fn fragmentation(&self) -> f64 {
+        if self.free_bytes == 0 {
+            0.0
+        } else {
+            1.0 - self.largest_free as f64 / self.free_bytes as f64
+        }
+    }
+
+    /// Total number of live objects
+    pub fn live_objects(&self) -> usize {
+        self.live.iter().map(|(_, count)| count).sum()
+    }
+}
+
+/// Returns every live object in a region, in no particular order
+///
+/// # Safety
+///
+/// `region` must point to a live region, which no other thread is
+/// allocating in or freeing from.
+pub unsafe fn region_objects(region: *mut Region) -> Vec<*mut SlHead> {
+    let mut acc = Vec::new();
+
+    unsafe {
+        let mut zone = (*region).head;
+        while !zone.is_null() {
+            zone_blocks(zone.as_mut().unwrap(), &mut acc);
+            zone = (*zone).next;
+        }
+
+        let large = (*region).large.lock().unwrap();
+        acc.extend(large.iter().map(|(start, _)| *start as *mut SlHead));
+    }
+
+    acc
+}
+
+/// Walks the used portion of a zone, collecting the live blocks that
+/// lie between those recorded in its free tree; returns the start and
+/// length of each free block, in address order
+unsafe fn zone_blocks(zone_ref: &mut Zone, acc: &mut Vec<*mut SlHead>) -> Vec<(usize, usize)> {
+    unsafe {
+        znlck!(zone_ref => {
+
+    let mut free_blocks = Vec::new();
+    let mut pending = Vec::new();
+
+    if !zone_ref.free.is_null() {
+        pending.push(zone_ref.free);
+    }
+
+    while let Some(fb) = pending.pop() {
+        let prev = fblk_get_prev_blk(zone_ref, fb);
+        if !prev.is_null() {
+            pending.push(prev)
+        }
+        let next = fblk_get_next_blk(zone_ref, fb);
+        if !next.is_null() {
+            pending.push(next)
+        }
+
+        free_blocks.push((fb as usize, fblk_get_len(fb) as usize));
+    }
+
+    free_blocks.sort_unstable();
+
+    let mut free_iter = free_blocks.iter().peekable();
+    let mut probe_pos = zone_ref.bot as usize;
+
+    while probe_pos < zone_ref.top as usize {
+        match free_iter.peek() {
+            Some(&&(fb, len)) if fb == probe_pos => {
+                probe_pos += len;
+                free_iter.next();
+            }
+            _ => {
+                acc.push(probe_pos as *mut SlHead);
+                probe_pos += lblk_get_len(probe_pos as *mut SlHead) as usize;
+            }
+        }
+    }
+
+    assert_eq!(probe_pos, zone_ref.top as usize);
+
+    free_blocks
+
+    } ENDLCK)
+    }
 }
 
 impl Drop for Region {
@@ -1481,21 +1830,79 @@ impl Drop for Region {
                 1,
             );
             let mut next = self.head;
-            loop {
+            while !next.is_null() {
                 let cur = next;
                 next = (*cur).next;
-                if next == ptr::null_mut() {
-                    break;
-                }
+                RegionTable::remove(&raw mut REGION_TABLE, (*cur).bot as usize);
+                super::refc_spill_forget(cur as usize, cur as usize + z_layout.size());
                 alloc::dealloc(cur as *mut u8, z_layout);
             }
+
+            for (start, len) in self.large.get_mut().unwrap().drain(..) {
+                RegionTable::remove(&raw mut REGION_TABLE, start);
+                super::refc_spill_forget(start, start + len as usize);
+                alloc::dealloc(
+                    start as *mut u8,
+                    alloc::Layout::from_size_align_unchecked(len as usize, 8),
+                );
+            }
         }
     }
 }
 
 // NOTE: Maximum zone size: 2GiB
-// NOTE: Maximum object size: 1/32 of zone size
-// TODO: special allocation scheme for larger objects
+// NOTE: Objects over half the zone size go to the large object space
+
+/// Checks whether a block of the given length is too large to be kept
+/// in one of a region's zones
+fn large_p(region_ref: &Region, len: u32) -> bool {
+    len > region_ref.zone_size / 2
+}
+
+/// Returns a pointer to a fresh span of `sz` bytes in the region's
+/// large object space
+unsafe fn large_acquire(region: *mut Region, sz: u32) -> *mut u8 {
+    unsafe {
+        let layout = alloc::Layout::from_size_align_unchecked(sz as usize, 8);
+        let ptr = alloc::alloc(layout);
+        if ptr.is_null() {
+            alloc::handle_alloc_error(layout)
+        }
+
+        RegionTable::append(
+            &raw mut REGION_TABLE,
+            ptr as usize,
+            ptr as usize + sz as usize,
+            ptr::null_mut(),
+            region,
+        );
+
+        (*region).large.lock().unwrap().push((ptr as usize, sz));
+
+        ptr
+    }
+}
+
+/// Returns a span in the region's large object space to the system
+unsafe fn large_release(region: *mut Region, ptr: *mut u8) {
+    unsafe {
+        let len = {
+            let mut large = (*region).large.lock().unwrap();
+            let idx = large
+                .iter()
+                .position(|(start, _)| *start == ptr as usize)
+                .expect("large object not found in its region");
+            large.swap_remove(idx).1
+        };
+
+        RegionTable::remove(&raw mut REGION_TABLE, ptr as usize);
+
+        alloc::dealloc(
+            ptr,
+            alloc::Layout::from_size_align_unchecked(len as usize, 8),
+        );
+    }
+}
 
 /// A zone is a contiguous chunk of memory in which Sail objects may
 /// be allocated
@@ -1535,7 +1942,8 @@ struct FreeBlock {
 // const MEM_REGION_HEAD_SIZE: usize = mem::size_of::<Region>();
 const MEM_ZONE_HEAD_SIZE: usize = mem::size_of::<Zone>();
 
-/// Returns the region and zone in which a given Sail object is stored
+/// Returns the region and zone in which a given Sail object is stored;
+/// the zone is null for objects in the region's large object space
 unsafe fn which_mem_area(ptr: *mut SlHead) -> (*mut Region, *mut Zone) {
     unsafe {
         assert_ne!(ptr, ptr::null_mut());
@@ -1591,6 +1999,10 @@ unsafe fn new_mem_zone(region: *mut Region) {
             region,
         );
 
+        if cfg!(feature = "memdbg") {
+            println!("registered {:x} to {:x}", start as usize, end as usize);
+        }
+
         let zn_id = std::intrinsics::atomic_xadd_acqrel(&raw mut ZONE_ID_CTR, 1);
 
         let new_head = Zone {
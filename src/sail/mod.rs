@@ -33,6 +33,7 @@
 //!
 //! A custom Lisp dialect for writing STARK
 
+use std::collections::HashMap;
 use std::fmt;
 use std::mem;
 use std::ptr;
@@ -41,10 +42,14 @@ use std::ptr;
 pub mod core;
 pub use self::core::*;
 
+pub mod arr;
+pub mod doc;
 pub mod eval;
 pub mod memmgt;
+pub mod num;
 pub mod parser;
 pub mod queue;
+pub mod serial;
 pub mod stdenv;
 pub mod symtab;
 pub mod thread;
@@ -138,66 +143,176 @@ fn make_val_type_mfst(
 // These should have global type IDs, type symbol bindings in the
 // environment tree, and manifests.
 
-// TODO: probably track how many have handles to a queue
-pub fn warp_hdl_init(reg: *mut memmgt::Region, tgt: *mut queue::Inlet) -> SlHndl {
-    assert!(!tgt.is_null());
+/// Marks a warp handle to a named channel rather than the main queue
+const WARP_CHAN: u64 = 1 << 32;
+
+/// Creates a send-only handle to the main queue of the thread with
+/// the given ID, or to one of its channels; the thread is found anew
+/// for every send, so the handle is harmless once it is gone
+pub fn warp_hdl_init(reg: *mut memmgt::Region, thread_id: usize, chan: Option<u32>) -> SlHndl {
+    let code = ((thread_id as u64) << 33) | chan.map_or(0, |c| WARP_CHAN | c as u64);
+
     unsafe {
         let out = SlHndl::from_raw_unchecked(memmgt::alloc(reg, 8, memmgt::cap(Cfg::B8WarpHdl)));
-        write_field_unchecked(out.clone(), 0, tgt as u64);
+        write_field_unchecked(out.clone(), 0, code);
         out
     }
 }
 
-fn warp_hdl_send(mut loc: SlHndl, msg: SlHndl, from: usize) {
+/// Sends a message from a thread through a warp handle, returning
+/// whether the target queue still exists to receive it
+fn warp_hdl_send(thr: *mut thread::ThreadHull, mut loc: SlHndl, msg: SlHndl) -> bool {
     coretypck!(loc ; WarpHdl);
 
-    let addrs: u64 = read_field(loc, 0);
+    let code: u64 = read_field(loc, 0);
+    let tgt = (code >> 33) as usize;
 
-    unsafe {
-        (addrs as *mut queue::Inlet)
-            .as_mut()
-            .expect("null warp handle")
-    }
-    .transmit(from, msg)
+    let thr_ref = unsafe { thr.as_ref().expect("null thread reference") };
+
+    thr_ref
+        .weft()
+        .with_thread(tgt, |t| {
+            let inlet = if code & WARP_CHAN == 0 {
+                Some(t.queue_inlet())
+            } else {
+                t.find_channel(code as u32)
+            };
+
+            inlet.map(|i| unsafe { (*i).transmit(thr_ref.id, msg) })
+        })
+        .flatten()
+        .is_some()
 }
 
+/// Creates a handle to a Sail thread: its ID, then the result it gave
+/// when first joined through the handle
 fn thread_ref_init(reg: *mut memmgt::Region, tgt: *mut thread::ThreadHull) -> SlHndl {
     assert!(!tgt.is_null());
     unsafe {
-        let out = SlHndl::from_raw_unchecked(memmgt::alloc(reg, 8, T_THR_REF_ID.0));
-        write_field_unchecked(out.clone(), 0, tgt as u64);
+        let out = SlHndl::from_raw_unchecked(memmgt::alloc(reg, 8 + PTR_LEN, T_THR_REF_ID.0));
+        write_field_unchecked(out.clone(), 0, (*tgt).id as u64);
         out
     }
 }
 
-fn thread_ref_join(reg: *mut memmgt::Region, mut loc: SlHndl) -> SlHndl {
+/// Checks whether an object is a handle to a Sail thread
+fn thread_ref_p(loc: &mut SlHndl) -> bool {
+    loc.type_fld_p() && loc.type_id() == T_THR_REF_ID.0
+}
+
+/// Gets the ID of the thread a handle refers to
+fn thread_ref_id(mut loc: SlHndl) -> usize {
     assert_eq!(loc.type_id(), T_THR_REF_ID.0);
+    read_field::<u64>(loc, 0) as usize
+}
 
-    let addrs: u64 = read_field(loc, 0);
+/// Creates a character object, holding one Unicode scalar value
+///
+/// # Safety
+///
+/// `reg` must point to a live region.
+pub unsafe fn char_init(reg: *mut memmgt::Region, val: char) -> SlHndl {
+    unsafe {
+        let out = SlHndl::from_raw_unchecked(memmgt::alloc(reg, 4, T_CHAR_ID.0));
+        write_field_unchecked(out.clone(), 0, val as u32);
+        out
+    }
+}
 
-    let th_ref = unsafe {
-        (addrs as *mut thread::ThreadHull)
-            .as_mut()
-            .expect("null thread reference")
-    };
+/// Checks whether an object is a character
+pub fn char_p(loc: &mut SlHndl) -> bool {
+    loc.type_fld_p() && loc.type_id() == T_CHAR_ID.0
+}
+
+pub fn char_get(mut loc: SlHndl) -> char {
+    assert_eq!(loc.type_id(), T_CHAR_ID.0);
+    char::from_u32(read_field::<u32>(loc, 0)).expect("invalid character")
+}
+
+/// Gives the name a character is read and printed by, for those that
+/// would otherwise be invisible
+pub fn char_name(val: char) -> Option<&'static str> {
+    match val {
+        ' ' => Some("space"),
+        '\n' => Some("newline"),
+        '\t' => Some("tab"),
+        _ => None,
+    }
+}
+
+/// Outcome of an attempt to join a thread
+enum Joined {
+    /// The thread finished, with a copy of its result if it had one
+    Ended(Option<SlHndl>),
+    /// The joining thread is blocked until the thread finishes
+    Waiting,
+    /// No such thread remains
+    Gone,
+}
+
+/// Copies the result of a finished thread into the waiter's region,
+/// keeping it in the handle so that joining again gives it at once; if
+/// the thread is still running, the waiter blocks until it finishes
+fn thread_ref_join(waiter: *mut thread::ThreadHull, loc: SlHndl) -> Joined {
+    if let Some(res) = read_ptr(loc.clone(), 8) {
+        return Joined::Ended(Some(res));
+    }
+
+    let waiter_ref = unsafe { waiter.as_mut().expect("null thread reference") };
+    let (reg, weft) = (waiter_ref.region(), waiter_ref.weft());
+    let id = thread_ref_id(loc.clone());
 
-    // TODO: real thread management without spinloops
-    while !th_ref.done_p() {}
+    // a thread removed since it ended may have left its result behind
+    let joined = weft
+        .with_thread(id, |t| {
+            if t.watch_end(waiter_ref.id) {
+                Joined::Waiting
+            } else {
+                Joined::Ended(t.collect(reg))
+            }
+        })
+        .or_else(|| weft.collect_kept(id, reg).map(Joined::Ended))
+        .unwrap_or(Joined::Gone);
+
+    match &joined {
+        Joined::Ended(Some(res)) => write_ptr(waiter_ref.top_env(), loc, 8, res.clone()),
+        Joined::Waiting => waiter_ref.block(),
+        _ => (),
+    }
 
-    unsafe { SlHndl::from_raw_unchecked(structure_copy(reg, th_ref.result().unwrap())) }
+    joined
 }
 
-/// Basic error codes for Sail faults
-#[derive(Debug)]
+/// Basic error codes for Sail faults; codes are only ever added at
+/// the end, so that each keeps its number
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[repr(u16)]
 pub enum SlErrCode {
     ErrorUnknown = 0,
-    ParseUnexpectedEnd,
-    ParseBadSpecial,
-    ParseInvalidChar,
-    ParseInvalidString,
-    ParseInvalidNum,
-    FileCouldNotRead,
+    ParseUnexpectedEnd = 1,
+    ParseBadSpecial = 2,
+    ParseInvalidChar = 3,
+    ParseInvalidString = 4,
+    ParseInvalidNum = 5,
+    FileCouldNotRead = 6,
+    EvalUnboundSymbol = 7,
+    EvalBadForm = 8,
+    EvalNotProc = 9,
+    EvalArgCount = 10,
+    TypeInvalid = 11,
+    TypeMismatch = 12,
+    IndexOutOfRange = 13,
+    ArithDivByZero = 14,
+    ThreadStartFailed = 15,
+    Raised = 16,
+    ThreadKilled = 17,
+    ThreadNotFound = 18,
+    ThreadInterrupted = 19,
+    ThreadOverBudget = 20,
+    ArithOverflow = 21,
+    ParseUnreadable = 22,
+    ParseBadBlob = 23,
+    FileCouldNotWrite = 24,
 }
 
 impl TryFrom<u16> for SlErrCode {
@@ -214,6 +329,24 @@ impl TryFrom<u16> for SlErrCode {
             x if x == ParseInvalidString as u16 => Ok(ParseInvalidString),
             x if x == ParseInvalidNum as u16 => Ok(ParseInvalidNum),
             x if x == FileCouldNotRead as u16 => Ok(FileCouldNotRead),
+            x if x == EvalUnboundSymbol as u16 => Ok(EvalUnboundSymbol),
+            x if x == EvalBadForm as u16 => Ok(EvalBadForm),
+            x if x == EvalNotProc as u16 => Ok(EvalNotProc),
+            x if x == EvalArgCount as u16 => Ok(EvalArgCount),
+            x if x == TypeInvalid as u16 => Ok(TypeInvalid),
+            x if x == TypeMismatch as u16 => Ok(TypeMismatch),
+            x if x == IndexOutOfRange as u16 => Ok(IndexOutOfRange),
+            x if x == ArithDivByZero as u16 => Ok(ArithDivByZero),
+            x if x == ThreadStartFailed as u16 => Ok(ThreadStartFailed),
+            x if x == Raised as u16 => Ok(Raised),
+            x if x == ThreadKilled as u16 => Ok(ThreadKilled),
+            x if x == ThreadNotFound as u16 => Ok(ThreadNotFound),
+            x if x == ThreadInterrupted as u16 => Ok(ThreadInterrupted),
+            x if x == ThreadOverBudget as u16 => Ok(ThreadOverBudget),
+            x if x == ArithOverflow as u16 => Ok(ArithOverflow),
+            x if x == ParseUnreadable as u16 => Ok(ParseUnreadable),
+            x if x == ParseBadBlob as u16 => Ok(ParseBadBlob),
+            x if x == FileCouldNotWrite as u16 => Ok(FileCouldNotWrite),
             _ => Err(()),
         }
     }
@@ -249,28 +382,175 @@ fn errcode_get(mut loc: SlHndl) -> SlErrCode {
     SlErrCode::try_from(read_field::<u16>(loc, 0)).unwrap()
 }
 
-fn arrvec_make<T: SizedBase + Copy>(
+// error objects: message, form, and file pointers, then line,
+// column, and error code
+const ERR_LINE_OFS: u32 = 3 * PTR_LEN;
+const ERR_COL_OFS: u32 = ERR_LINE_OFS + NUM_32_LEN;
+const ERR_CODE_OFS: u32 = ERR_COL_OFS + NUM_32_LEN;
+const ERR_SIZE: u32 = ERR_CODE_OFS + NUM_16_LEN;
+
+/// Creates a full Sail error object, carrying an error code, a
+/// message, and optionally the form which caused the error
+///
+/// The source span is left unknown (line and column zero); fill it
+/// in with `err_set_span` where the location is known.
+///
+/// # Safety
+///
+/// `reg` must point to a live region.
+pub unsafe fn err_init(
     reg: *mut memmgt::Region,
-    typ: u32,
-    len: u32,
-    fill: T,
+    code: SlErrCode,
+    msg: &str,
+    form: Option<SlHndl>,
 ) -> SlHndl {
-    assert_eq!(temp_get_size(typ) as usize, mem::size_of::<T>());
     unsafe {
-        let size = vec_size(8, temp_get_size(typ), len);
-        let out = SlHndl::from_raw_unchecked(memmgt::alloc(reg, size, memmgt::cap(Cfg::VecArr)));
+        let out =
+            SlHndl::from_raw_unchecked(memmgt::alloc(reg, ERR_SIZE, memmgt::cap(Cfg::VecErr)));
 
-        write_field_unchecked::<u32>(out.clone(), 0, typ);
-        write_field_unchecked::<u32>(out.clone(), 4, len);
+        let msg = string_init(reg, msg);
+        inc_refc(msg.get_raw());
+        write_ptr_unsafe_unchecked(out.clone(), 0, msg);
 
-        for i in 0..len {
-            write_field_unchecked(out.clone(), 8 + (temp_get_size(typ) * i), fill)
+        if let Some(form) = form {
+            inc_refc(form.get_raw());
+            write_ptr_unsafe_unchecked(out.clone(), PTR_LEN, form);
         }
 
+        write_field_unchecked::<u16>(out.clone(), ERR_CODE_OFS, code as u16);
+
         out
     }
 }
 
+/// Records the source location of an error; a file of None means the
+/// error came from text without a file
+pub fn err_set_span(mut loc: SlHndl, file: Option<SlHndl>, line: u32, col: u32) {
+    coretypck!(loc ; ErrObj);
+
+    unsafe {
+        let old =
+            ptr::read_unaligned(loc.value_ptr().add(2 * PTR_LEN as usize) as *mut *mut SlHead);
+
+        match file {
+            Some(file) => {
+                inc_refc(file.get_raw());
+                write_ptr_unsafe_unchecked(loc.clone(), 2 * PTR_LEN, file);
+            }
+            None => ptr::write_unaligned(
+                loc.value_ptr().add(2 * PTR_LEN as usize) as *mut *mut SlHead,
+                ptr::null_mut(),
+            ),
+        }
+
+        if !nil_p(old) && dec_refc(old) {
+            destroy_obj_core(old)
+        }
+
+        write_field_unchecked::<u32>(loc.clone(), ERR_LINE_OFS, line);
+        write_field_unchecked::<u32>(loc, ERR_COL_OFS, col);
+    }
+}
+
+#[inline(always)]
+pub fn err_get_code(mut loc: SlHndl) -> SlErrCode {
+    coretypck!(loc ; ErrObj);
+    SlErrCode::try_from(read_field::<u16>(loc, ERR_CODE_OFS)).unwrap_or(SlErrCode::ErrorUnknown)
+}
+
+#[inline(always)]
+pub fn err_get_msg(mut loc: SlHndl) -> &'static str {
+    coretypck!(loc ; ErrObj);
+    match read_ptr(loc, 0) {
+        Some(msg) => string_get(msg),
+        None => "",
+    }
+}
+
+#[inline(always)]
+pub fn err_get_form(mut loc: SlHndl) -> Option<SlHndl> {
+    coretypck!(loc ; ErrObj);
+    read_ptr(loc, PTR_LEN)
+}
+
+/// Returns the file, line, and column at which an error occurred;
+/// a line of zero means the location is unknown
+#[inline(always)]
+pub fn err_get_span(mut loc: SlHndl) -> (Option<SlHndl>, u32, u32) {
+    coretypck!(loc ; ErrObj);
+    (
+        read_ptr(loc.clone(), 2 * PTR_LEN),
+        read_field(loc.clone(), ERR_LINE_OFS),
+        read_field(loc, ERR_COL_OFS),
+    )
+}
+
+/// Creates a keyword from the debug name of a Rust value, splitting
+/// words with dashes, as in `:vec-std` for `Cfg::VecStd` or
+/// `:type-mismatch` for an error code
+pub fn debug_keyword(reg: *mut memmgt::Region, tbl: &mut Stab, value: impl fmt::Debug) -> SlHndl {
+    sym_init(
+        reg,
+        modeize_sym(tbl.get_id(dashed(value).as_bytes()), SymbolMode::Keyword),
+    )
+}
+
+/// Gives the debug name of a Rust value in lower case, splitting words
+/// with dashes
+fn dashed(value: impl fmt::Debug) -> String {
+    let mut name = String::new();
+    for c in format!("{:?}", value).chars() {
+        if c.is_ascii_uppercase() && !name.is_empty() {
+            name.push('-');
+        }
+        name.push(c.to_ascii_lowercase());
+    }
+    name
+}
+
+/// Finds the file for a module on the search path, by the module's
+/// name, and parses it into the body of the module
+///
+/// Gives the path of the file along with the body; failure to find,
+/// read or parse the file is returned as a Sail error object.
+pub fn module_source(
+    thr: &mut thread::ThreadHull,
+    module: u32,
+) -> Result<(SlHndl, Option<SlHndl>), SlHndl> {
+    let name = match thr.context().symtab().lookup_by_id(demodes_sym(module)) {
+        Some(n) => String::from_utf8_lossy(n).into_owned(),
+        None => String::new(),
+    };
+
+    let (path, prog) = thr.source_file(&(name + ".sl"))?;
+    let file = string_init(thr.region(), &path.to_string_lossy());
+
+    // the module body is everything after the wrapping do
+    Ok((file, ref_get(prog).and_then(get_next_list_elt)))
+}
+
+/// Raises an error on a Sail thread from inside a native procedure
+///
+/// The evaluator unwinds the thread's stack once the procedure
+/// returns, so the returned object is never seen as a result.
+///
+/// # Safety
+///
+/// `thr` must point to a live thread, which the caller is running.
+pub unsafe fn raise_err(
+    thr: *mut thread::ThreadHull,
+    code: SlErrCode,
+    msg: &str,
+    form: Option<SlHndl>,
+) -> SlHndl {
+    let thr_ref = unsafe { thr.as_mut().expect("null thread reference") };
+
+    let err = unsafe { err_init(thr_ref.region(), code, msg, form) };
+    thr_ref.raise(err.clone());
+
+    err
+}
+
 pub fn arrvec_init<T: SizedBase + Copy>(
     reg: *mut memmgt::Region,
     typ: u32,
@@ -366,16 +646,26 @@ incl_symbols! {
     T_ENV         "env"     Type;
     T_ENV_LYR     "env-lyr" Type;
     T_THR_REF     "thr-hdl" Type;
+    T_CHAR        "char"    Type;
     SP_AND        "and"     Basic;
     SP_COND       "cond"    Basic;
     SP_DEF        "def"     Basic;
+    SP_DEFMACRO   "defmacro" Basic;
     SP_DO         "do"      Basic;
     SP_EVAL       "eval"    Basic;
+    SP_EXPORT     "export"  Basic;
     SP_FN         "fn"      Basic;
     SP_IF         "if"      Basic;
+    SP_IMPORT     "import"  Basic;
+    SP_MACRO      "macro"   Basic;
+    SP_MODULE     "module"  Basic;
     SP_OR         "or"      Basic;
+    SP_QUASI      "quasiquote" Basic;
     SP_QUOTE      "quote"   Basic;
     SP_SET        "set"     Basic;
+    SP_TRY        "try"     Basic;
+    SP_UNQUOTE    "unquote" Basic;
+    SP_SPLICE     "unquote-splice" Basic;
     SP_WHILE      "while"   Basic;
     S_MAIN        "main"    Basic;
     S_MGR_TGT     "mgr-tgt" Basic;
@@ -387,8 +677,11 @@ incl_symbols! {
     S_ENGINE      "engine"  Basic;
     S_T_INTERN    "%true"   Basic;
     S_F_INTERN    "%false"  Basic;
+    S_EXPORTS     "%exports" Basic;
     S_FR_DIMS     "fr-dims" Basic;
     S_CUR_POS     "cur-pos" Basic;
+    S_DOC_PATH    "doc-path" Basic;
+    S_REST        "&"       Basic;
     K_CX_DESTR    "cx-dstr" Keyword;
     K_CX_RESIZ    "cx-resz" Keyword;
     K_CX_RECRD    "cx-rcrd" Keyword;
@@ -417,6 +710,9 @@ macro_rules! incl_types {
             pub const $name: (u32, u32) = (${index()}, $sym.0);
         )+
             const $ctvar: usize = ${count($name)};
+
+        /// Every type beyond the core types, by ID and type symbol
+        const TYPE_IDS: [(u32, u32); $ctvar] = [$($name),+];
     }
 }
 
@@ -424,189 +720,216 @@ incl_types! {
     TID_COUNT:
     T_FRM_HDL_ID  T_FRM_HDL;
     T_ENG_HDL_ID  T_ENG_HDL;
-    T_THR_REF_ID  T_THR_REF
+    T_THR_REF_ID  T_THR_REF;
+    T_CHAR_ID     T_CHAR
 }
 
-pub fn structure_copy(tgt: *mut memmgt::Region, root: SlHndl) -> *mut SlHead {
-    // collect handles to all objects referenced by msg, with
-    // appropriate reference counts (1 for msg, 1+ for all others
-    // according to the structure) (panic if too many layers)
+/// Every object reachable from a root, numbered in the order found
+/// (the root first), with the links between them by number
+pub struct StructureGraph {
+    pub ptrs: Vec<*mut SlHead>,
+    /// Number of each object's next list element, if any
+    pub nexts: Vec<Option<u32>>,
+    /// Offset and target number of each reference inside each object
+    pub refs: Vec<Vec<(u32, u32)>>,
+    /// Count of links to each object, plus one for the root
+    pub rcs: Vec<u32>,
+}
 
-    fn index_of(vec: &Vec<*mut SlHead>, qry: *mut SlHead) -> Option<usize> {
-        vec.iter()
-            .enumerate()
-            .find_map(|(i, x)| if *x == qry { Some(i) } else { None })
+/// Follows redirects from an object to the object proper
+fn redir_resolve(mut loc: *mut SlHead) -> *mut SlHead {
+    while !nil_p(loc) && raw_cfg_spec(loc) == Cfg::B0Redir {
+        loc = (unsafe { ptr::read_unaligned(loc as *mut usize) } >> 16) as *mut SlHead;
     }
+    loc
+}
 
-    let mut src_ptrs: Vec<*mut SlHead> = vec![unsafe { root.get_raw() }];
-    let mut last_cycle_ptr_count = 0;
-    let mut total_ptr_count = 1;
-
-    let mut src_nxt_elts: Vec<Option<u32>> = vec![];
-    let mut src_int_refs: Vec<Vec<(u32, u32)>> = vec![];
+/// Walks the structure under a root object, recording every object and
+/// link once, however many times an object is shared or a cycle passes
+/// through it
+pub fn structure_graph(root: *mut SlHead) -> StructureGraph {
+    // numbers an object the first time it is seen, and counts one
+    // link to it every time
+    fn visit(
+        graph: &mut StructureGraph,
+        index: &mut HashMap<*mut SlHead, u32>,
+        loc: *mut SlHead,
+    ) -> u32 {
+        let id = *index.entry(loc).or_insert_with(|| {
+            graph.ptrs.push(loc);
+            graph.rcs.push(0);
+            graph.ptrs.len() as u32 - 1
+        });
+        graph.rcs[id as usize] += 1;
+        id
+    }
 
-    let mut new_rcs: Vec<u8> = vec![1];
+    let mut graph = StructureGraph {
+        ptrs: vec![],
+        nexts: vec![],
+        refs: vec![],
+        rcs: vec![],
+    };
+    let mut index = HashMap::new();
 
-    let mut ptrs_to_push: Vec<*mut SlHead> = Vec::new();
+    visit(&mut graph, &mut index, redir_resolve(root));
 
-    loop {
-        for this_src_ptr in &src_ptrs[last_cycle_ptr_count..] {
-            let nxt_elt =
-                (unsafe { ptr::read_unaligned(*this_src_ptr as *mut usize) } >> 16) as *mut SlHead;
+    let mut i = 0;
+    while i < graph.ptrs.len() {
+        let this = graph.ptrs[i];
 
-            src_nxt_elts.push(if nil_p(nxt_elt) {
-                None
-            } else {
-                // condition: pointer found in existing list or in
-                // current (to push) list
-
-                // increment appropriate reference count slot
-                // (index for src_ptrs, index + last_end for ptp)
-
-                if let Some(cycle_idx) = index_of(&ptrs_to_push, nxt_elt) {
-                    new_rcs[cycle_idx + last_cycle_ptr_count] += 1;
-                    Some((cycle_idx + last_cycle_ptr_count) as u32)
-                } else if let Some(existing_id) = index_of(&src_ptrs, nxt_elt) {
-                    new_rcs[existing_id] += 1;
-                    Some(existing_id as u32)
-                } else {
-                    ptrs_to_push.push(nxt_elt);
-                    new_rcs.push(1);
-                    total_ptr_count += 1;
-                    Some(total_ptr_count - 1)
-                }
+        let next = redir_resolve(
+            (unsafe { ptr::read_unaligned(this as *mut usize) } >> 16) as *mut SlHead,
+        );
+        let next_id = (!nil_p(next)).then(|| visit(&mut graph, &mut index, next));
+        graph.nexts.push(next_id);
+
+        // TODO: permit arbitrary objects, beyond core objects
+        let mut ofs_tgt = vec![];
+        for ofs in discern_ref_offsets_core(this) {
+            let nest = redir_resolve(unsafe {
+                ptr::read_unaligned(raw_val_ptr(this).add(ofs as usize) as *mut *mut SlHead)
             });
-
-            // we must also track the offsets of every reference within
-            // every object, and identify which object each reference
-            // pointed to, so that we can reconstitute the structure
-
-            let mut ofs_tgt = Vec::new();
-            // TODO: permit arbitrary objects, beyond core objects
-            for ofs in discern_ref_offsets_core(*this_src_ptr) {
-                let this_int_ptr = unsafe {
-                    ptr::read_unaligned(
-                        raw_val_ptr(*this_src_ptr).add(ofs as usize) as *mut *mut SlHead
-                    )
-                };
-                if !nil_p(this_int_ptr) {
-                    let tgt_id = if let Some(cycle_idx) = index_of(&ptrs_to_push, this_int_ptr) {
-                        new_rcs[cycle_idx + last_cycle_ptr_count] += 1;
-                        (cycle_idx + last_cycle_ptr_count) as u32
-                    } else if let Some(existing_id) = index_of(&src_ptrs, this_int_ptr) {
-                        new_rcs[existing_id] += 1;
-                        existing_id as u32
-                    } else {
-                        ptrs_to_push.push(this_int_ptr);
-                        new_rcs.push(1);
-                        total_ptr_count += 1;
-                        total_ptr_count - 1
-                    };
-
-                    ofs_tgt.push((ofs, tgt_id));
-                }
+            if !nil_p(nest) {
+                ofs_tgt.push((ofs, visit(&mut graph, &mut index, nest)));
             }
-
-            src_int_refs.push(ofs_tgt);
         }
+        graph.refs.push(ofs_tgt);
 
-        assert_eq!(src_ptrs.len(), src_nxt_elts.len());
-        assert_eq!(src_ptrs.len(), src_int_refs.len());
-
-        if total_ptr_count as usize == last_cycle_ptr_count {
-            // TODO: check for excess passes
-            assert!(ptrs_to_push.is_empty());
-            break;
-        }
-
-        last_cycle_ptr_count = src_ptrs.len();
-
-        src_ptrs.append(&mut ptrs_to_push);
-
-        assert_eq!(src_ptrs.len(), total_ptr_count as usize);
-        assert_eq!(src_ptrs.len(), new_rcs.len());
+        i += 1;
     }
 
-    // copy all objects to the new region, in reverse reference
-    // dependency order, tracking the new pointers and populating
-    // them into the correct reference slots as we go
-
-    // we can insert them in reverse order of the source pointer
-    // vector, because it was constructed by probing stepwise from
-    // the root object
-
-    let mut new_ptrs: Vec<*mut SlHead> = vec![ptr::null_mut(); src_ptrs.len()];
-
-    // for p in &src_ptrs {
-    //     println!("{:?}", raw_core_type(*p).unwrap());
-    // }
-
-    // println!("{src_ptrs:?}");
-    // println!("{new_rcs:?}");
-    // println!("{src_nxt_elts:?}");
-    // println!("{src_int_refs:?}");
-
-    for (i, p) in src_ptrs.into_iter().enumerate().rev() {
-        let this_new_obj = memmgt::copy_direct(p, tgt, new_rcs[i]);
-        new_ptrs[i] = this_new_obj;
+    graph
+}
 
-        // insert correct new addresses in next element slot and
-        // at reference offset points (the first item processed
-        // here is guaranteed not to have any references out)
+/// Copies every object of a structure graph into a region and links
+/// the copies up as the originals were, returning the copies in graph
+/// order
+fn structure_build(tgt: *mut memmgt::Region, graph: &StructureGraph) -> Vec<*mut SlHead> {
+    // hold off cycle collection in the target until the copies are
+    // all linked up with their final reference counts
+    unsafe { (*tgt).lock() };
+
+    let new_ptrs: Vec<*mut SlHead> = graph
+        .ptrs
+        .iter()
+        .zip(&graph.rcs)
+        .map(|(p, rc)| {
+            let obj = memmgt::copy_direct(*p, tgt, (*rc).min(255) as u8);
+            if *rc > 255 {
+                refc_spill(obj, rc - 255);
+            }
+            obj
+        })
+        .collect();
 
+    // every copy exists before any is linked, so references may run
+    // in any direction, including back around a cycle
+    for (i, obj) in new_ptrs.iter().enumerate() {
         unsafe {
-            if let Some(nxt_id) = src_nxt_elts[i] {
-                // set next list element raw
-                ptr::write_unaligned(
-                    this_new_obj as *mut u64,
-                    ((new_ptrs[nxt_id as usize] as u64) << 16)
-                        + ptr::read_unaligned(this_new_obj as *mut u16) as u64,
-                );
-            }
+            let next = graph.nexts[i].map_or(ptr::null_mut(), |id| new_ptrs[id as usize]);
+            ptr::write_unaligned(
+                *obj as *mut u64,
+                ((next as u64) << 16) + ptr::read_unaligned(*obj as *mut u16) as u64,
+            );
 
-            for (ofs, id) in &src_int_refs[i] {
-                // write internal pointer raw
+            for (ofs, id) in &graph.refs[i] {
                 ptr::write_unaligned(
-                    raw_val_ptr(this_new_obj).add(*ofs as usize) as *mut _,
+                    raw_val_ptr(*obj).add(*ofs as usize) as *mut _,
                     new_ptrs[*id as usize],
                 );
             }
         }
     }
 
-    new_ptrs[0]
+    unsafe { (*tgt).unlock() };
+
+    new_ptrs
+}
+
+/// Copies an object and everything it references into another region,
+/// preserving shared substructure and cycles; the copy of the root
+/// carries one reference, for the caller
+pub fn structure_copy(tgt: *mut memmgt::Region, root: SlHndl) -> *mut SlHead {
+    structure_build(tgt, &structure_graph(unsafe { root.get_raw() }))[0]
 }
 
 /// Bundles together an object and associated symbol table for display
 pub struct SlContextVal<'a> {
     tbl: &'a Stab,
     obj: SlHndl,
+    /// addresses of the containers being written around the object
+    within: &'a [usize],
 }
 
 /// Create a SlContextVal for display
 pub fn context(tbl: &Stab, obj: SlHndl) -> SlContextVal {
-    SlContextVal { tbl, obj }
+    SlContextVal {
+        tbl,
+        obj,
+        within: &[],
+    }
 }
 
-// TODO: this is a mess; write Sail native display functions
+/// Writes an object in a form which the parser reads back as an equal
+/// object; objects which cannot be read are written in angle brackets,
+/// as is a container found again within itself
 // TODO: just push characters into a byte vector (string) for display
 impl fmt::Display for SlContextVal<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut value = self.obj.clone();
 
         use CoreType::*;
+        let addr = redir_resolve(unsafe { value.get_raw() }) as usize;
+        let container = matches!(value.core_type(), Some(Ref | VecStd | VecHash | ErrObj));
+        if container && self.within.contains(&addr) {
+            return write!(f, "#<cycle>");
+        }
+
+        let within = if container {
+            [self.within, &[addr]].concat()
+        } else {
+            vec![]
+        };
+        let nest = |obj| SlContextVal {
+            tbl: self.tbl,
+            obj,
+            within: &within,
+        };
+
         match value.core_type() {
             Some(t) => match t {
                 Nil => write!(f, "()"),
                 Bool => write!(f, "{}", if bool_get(value) { "#T" } else { "#F" }),
-                I64 => write!(f, "{}", i64_get(value)),
-                U32 => write!(f, "{}", u32_get(value)),
-                F64 => write!(f, "{}", f64_get(value)),
-                F32 => write!(f, "{}", f32_get(value)),
-                ErrCode => write!(f, "<err: {:?}>", errcode_get(value)),
+                U8 | I8 | U16 | I16 | U32 | I32 | U64 | I64 | U128 | I128 | F32 | F64 => {
+                    write!(f, "{}", num::Num::of(&value).unwrap())
+                }
+                ErrCode => write!(f, "#err({:?})", errcode_get(value)),
+                ErrObj => {
+                    write!(f, "#err({:?} ", err_get_code(value.clone())).unwrap();
+                    write_str_lit(f, err_get_msg(value.clone())).unwrap();
+                    if let Some(form) = err_get_form(value.clone()) {
+                        write!(f, " :form {}", nest(form)).unwrap();
+                    }
+                    let (file, line, col) = err_get_span(value);
+                    if file.is_some() || line != 0 {
+                        write!(f, " :at").unwrap();
+                    }
+                    if let Some(file) = file {
+                        write!(f, " ").unwrap();
+                        write_str_lit(f, string_get(file)).unwrap();
+                    }
+                    if line != 0 {
+                        write!(f, " {} {}", line, col).unwrap();
+                    }
+                    write!(f, ")")
+                }
                 Symbol => {
                     let full_id = sym_get_id(value);
                     match mode_of_sym(full_id) {
+                        SymbolMode::Basic if gensym_id_p(full_id) => {
+                            write!(f, "%g{}", GENSYM_TOP - full_id)
+                        }
                         SymbolMode::Basic => {
                             write!(
                                 f,
@@ -653,7 +976,7 @@ impl fmt::Display for SlContextVal<'_> {
                     write!(f, "(").unwrap();
                     let mut elt = ref_get(value);
                     while let Some(next) = elt {
-                        write!(f, "{}", context(self.tbl, next.clone()).to_string()).unwrap();
+                        write!(f, "{}", nest(next.clone()).to_string()).unwrap();
                         elt = get_next_list_elt(next);
                         if elt.is_some() {
                             write!(f, " ").unwrap();
@@ -665,87 +988,86 @@ impl fmt::Display for SlContextVal<'_> {
                     write!(f, "[").unwrap();
                     let len = stdvec_get_len(value.clone());
                     for idx in 0..len {
-                        write!(
-                            f,
-                            "{}",
-                            context(self.tbl, stdvec_idx(value.clone(), idx)).to_string()
-                        )
-                        .unwrap();
+                        write!(f, "{}", nest(stdvec_idx(value.clone(), idx)).to_string()).unwrap();
                         if idx < len - 1 {
                             write!(f, " ").unwrap();
                         }
                     }
                     write!(f, "]")
                 }
-                VecStr => write!(f, "\"{}\"", string_get(value)),
+                VecStr => write_str_lit(f, string_get(value)),
                 VecHash => {
-                    // TODO: function to access all map pairs somehow
                     write!(f, "{{").unwrap();
-                    // visit every position and traverse each entry list as below
-                    let size = hashvec_get_size(value.clone());
-                    let mut fst = true;
-                    for idx in 0..size {
-                        let mut pos = read_ptr(value.clone(), 4 + 4 + (PTR_LEN * idx));
-                        while let Some(next) = pos {
-                            if !fst {
-                                write!(f, " ").unwrap()
-                                // TODO: keep commas / parse them in maps?
-                                // write!(f, ", ").unwrap()
-                            }
-                            write!(
-                                f,
-                                "{} ",
-                                context(self.tbl, ref_get(next.clone()).unwrap()).to_string()
-                            )
-                            .unwrap();
-                            write!(
-                                f,
-                                "{}",
-                                context(
-                                    self.tbl,
-                                    get_next_list_elt(ref_get(next.clone()).unwrap()).unwrap()
-                                )
-                                .to_string()
-                            )
-                            .unwrap();
-                            pos = get_next_list_elt(next);
-                            fst = false;
+                    for (idx, (key, val)) in hash_map_entries(value).into_iter().enumerate() {
+                        if idx > 0 {
+                            write!(f, " ").unwrap()
+                            // TODO: keep commas / parse them in maps?
+                            // write!(f, ", ").unwrap()
                         }
+                        write!(f, "{} {}", nest(key), nest(val)).unwrap();
                     }
                     write!(f, "}}")
                 }
-                VecArr => {
-                    let typ = arrvec_get_typ(value.clone());
-                    if typ == T_F32.0 {
-                        write!(f, "#[").unwrap();
-                        let len = arrvec_get_len(value.clone());
-                        for idx in 0..len {
-                            let val = read_field::<f32>(value.clone(), 8 + (4 * idx));
-                            write!(f, "{}", val).unwrap();
-                            if idx < len - 1 {
+                VecArr => match arr::nums(value) {
+                    Ok((vals, typ)) => {
+                        write!(f, "#{}[", typ.suffix()).unwrap();
+                        for (idx, val) in vals.iter().enumerate() {
+                            if idx > 0 {
                                 write!(f, " ").unwrap();
                             }
+                            write!(f, "{}", val.bare()).unwrap();
                         }
                         write!(f, "]")
-                    } else {
-                        write!(f, "<$arr-vec>")
                     }
-                }
-                ProcLambda | ProcNative => write!(f, "<$proc>"),
-                _ => write!(f, "<@core/$other>"),
+                    Err(_) => write!(f, "#<arr-vec>"),
+                },
+                // the rest cannot be read back, so are written in angle
+                // brackets, which the parser refuses
+                ProcLambda | ProcNative => write!(f, "#<proc>"),
+                ProcMacro => write!(f, "#<macro>"),
+                other => write!(f, "#<{}>", dashed(other)),
             },
+            None if char_p(&mut value) => {
+                let c = char_get(value);
+                match char_name(c) {
+                    Some(name) => write!(f, "#\\{}", name),
+                    None => write!(f, "#\\{}", c),
+                }
+            }
             None => {
-                println!("{:?}", value.cfg_spec());
-                write!(f, "<$other>")
+                let name = TYPE_IDS
+                    .iter()
+                    .find(|(id, _)| value.type_fld_p() && *id == value.type_id())
+                    .and_then(|(_, sym)| self.tbl.lookup_by_id(demodes_sym(*sym)));
+                match name {
+                    Some(n) => write!(f, "#<{}>", String::from_utf8_lossy(n)),
+                    None => write!(f, "#<other>"),
+                }
             }
         }
     }
 }
 
+/// Writes a string literal, escaping the characters the parser would
+/// otherwise take as its end or as escapes, and control characters
+fn write_str_lit(f: &mut fmt::Formatter<'_>, text: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in text.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\t' => write!(f, "\\t")?,
+            c if c.is_control() => write!(f, "\\u{{{:x}}}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
 /// Accepts an input stream and runs a read - evaluate - print loop perpetually
 pub fn repl(stream_in: std::io::Stdin) {
     // TODO: consider stack-like environment per function
-    // TODO: design module / namespace mechanisms
 
     let tact = thread::Tact::create(251);
     let mut weft = thread::Weft::create(tact);
@@ -768,10 +1090,14 @@ pub fn repl(stream_in: std::io::Stdin) {
         let mut input = String::new();
         stream_in.read_line(&mut input).expect("Failure");
 
-        let expr = match parser::parse(region, weft.ctx_mut().symtab(), &input, false) {
-            Ok(out) => out,
-            Err(err) => {
-                println!("{:?}\n", err);
+        let expr = match parser::parse_spanned(region, weft.ctx_mut().symtab(), &input, false, true)
+        {
+            Ok((out, _)) => out,
+            Err(errs) => {
+                for err in errs {
+                    println!("{}", err);
+                }
+                println!();
                 continue;
             }
         };
@@ -788,17 +1114,27 @@ pub fn repl(stream_in: std::io::Stdin) {
 }
 
 /// Runs a Sail file in its own context
-pub fn run_file(filename: &str) -> Result<String, SlErrCode> {
+pub fn run_file(filename: &str) -> Result<String, String> {
     let file = match std::fs::read_to_string(filename) {
         Ok(s) => s,
-        Err(_) => return Err(SlErrCode::FileCouldNotRead),
+        Err(err) => {
+            return Err(format!(
+                "#err({:?} \"{}: {}\")",
+                SlErrCode::FileCouldNotRead,
+                filename,
+                err
+            ));
+        }
     };
 
     interpret(&file, true)
 }
 
 /// Interprets a Sail expression, returning the formatted result
-pub fn interpret(code: &str, dolist: bool) -> Result<String, SlErrCode> {
+///
+/// A Sail error, whether from parsing or evaluation, is formatted and
+/// returned as the error variant.
+pub fn interpret(code: &str, dolist: bool) -> Result<String, String> {
     let mut ctx = thread::Tact::create(251);
     global_ctx_setup(&mut ctx);
 
@@ -811,13 +1147,19 @@ pub fn interpret(code: &str, dolist: bool) -> Result<String, SlErrCode> {
     weft.assign_special(thread_ref.id);
     weft.add_worker();
 
-    thread_ref.load_from_text(code, dolist)?;
+    if let Err(err) = thread_ref.load_from_text(code, dolist) {
+        return Err(format!("#err({:?})", err));
+    }
 
     while thread_ref.advance() {}
 
-    let result = thread_ref.result().unwrap();
+    let mut result = thread_ref.result().unwrap();
 
-    Ok(context(weft.ctx_mut().symtab(), result).to_string())
+    if coretypp!(result ; ErrObj) {
+        Err(context(weft.ctx_mut().symtab(), result).to_string())
+    } else {
+        Ok(context(weft.ctx_mut().symtab(), result).to_string())
+    }
 }
 
 pub fn global_ctx_setup(tact: &mut thread::Tact) {
@@ -843,6 +1185,49 @@ pub fn thread_env_setup(thr: *mut thread::ThreadHull) {
     env_scope_ins_by_id(reg, env.clone(), S_F_INTERN.0, false_intern);
 
     insert_native_procs(reg, thr_ref.context().symtab(), env, stdenv::ENVFNS);
+
+    thr_ref
+        .load_from_text(include_str!("prelude.sl"), true)
+        .expect("prelude must parse");
+
+    while thr_ref.advance() {}
+
+    if let Some(mut res) = thr_ref.result() {
+        let failed = coretypp!(res ; ErrObj);
+        assert!(
+            !failed,
+            "prelude failed: {}",
+            context(thr_ref.context().symtab(), res)
+        );
+    }
+
+    thr_ref.clr_res();
+}
+
+/// Creates a weft with the given number of workers and one thread in
+/// it, set up and assigned to be driven by the caller; the context
+/// may be adjusted before the weft takes it
+///
+/// The weft is boxed because its threads hold pointers into it.
+#[cfg(test)]
+pub(crate) fn test_weft(
+    adjust: impl FnOnce(&mut thread::Tact),
+    workers: usize,
+) -> (Box<thread::Weft>, *mut thread::ThreadHull) {
+    let mut tact = thread::Tact::create(251);
+    global_ctx_setup(&mut tact);
+    adjust(&mut tact);
+
+    let mut weft = Box::new(thread::Weft::create(tact));
+    let thr = thread::ThreadHull::summon(&mut weft, 10000, 100000, None);
+    thread_env_setup(thr);
+
+    weft.assign_special(unsafe { (*thr).id });
+    for _ in 0..workers {
+        weft.add_worker();
+    }
+
+    (weft, thr)
 }
 
 /// Insert a slice of native procedures into the symbol table and environment
@@ -865,6 +1250,12 @@ pub fn insert_native_procs(
 mod tests {
     use super::*;
 
+    fn run(thr: &mut thread::ThreadHull, code: &str) -> String {
+        thr.load_from_text(code, false).unwrap();
+        while thr.advance() {}
+        context(thr.context().symtab(), thr.result().unwrap()).to_string()
+    }
+
     #[test]
     fn returns() {
         let exp = String::from("42");
@@ -883,6 +1274,75 @@ mod tests {
         assert_eq!("6", interpret(&exp, false).unwrap());
     }
 
+    #[test]
+    fn closures() {
+        let exp = "(do (def x 1) (def f (fn [] x)) (def g (fn [x] (f))) (g 2))";
+        assert_eq!("1", interpret(exp, false).unwrap());
+
+        let exp = "(do (def counter (fn [] (def n 0) (fn [] (set n (+ n 1)) n)))
+                       (def c (counter)) (def d (counter))
+                       (c) (c) (d) (c))";
+        assert_eq!("3", interpret(exp, false).unwrap());
+
+        let exp = "(do (def adder (fn [a] (fn [b] (+ a b)))) ((adder 2) 5))";
+        assert_eq!("7", interpret(exp, false).unwrap());
+
+        // a procedure's definitions stay within it
+        let exp = "(do (def f (fn [] (def y 1) y)) (f) y)";
+        assert!(
+            interpret(exp, false)
+                .unwrap_err()
+                .contains("EvalUnboundSymbol")
+        );
+    }
+
+    #[test]
+    fn collects_cycles() {
+        // the closure and the scope it was defined in hold each other,
+        // so only a collection keeps the live count from climbing with
+        // every call; it is taken once the loop has settled
+        let run = |collect| {
+            let exp = format!(
+                "(do (def f (fn [] ((fn [] (def g (fn [] g)) 0))))
+                     (def base 0) (def live 0) (def i 0)
+                     (while (not (= i 6))
+                       (f) {collect}
+                       (set live (vec-get (heap-stats) 21))
+                       (if (= i 1) (set base live) ())
+                       (set i (+ i 1)))
+                     (- live base))"
+            );
+            interpret(&exp, false).unwrap()
+        };
+        assert_eq!("0", run("(gc)"));
+        assert_ne!("0", run("()"));
+    }
+
+    #[test]
+    fn heap_stats() {
+        assert_eq!("24", interpret("(vec-len (heap-stats))", false).unwrap());
+        assert_eq!(
+            ":zones",
+            interpret("(vec-get (heap-stats) 0)", false).unwrap()
+        );
+        assert_eq!(
+            ":live",
+            interpret("(vec-get (heap-stats) 22)", false).unwrap()
+        );
+
+        let exp = "(do (def live (vec-get (heap-stats) 23)) (vec-get live 0))";
+        assert_eq!(":b0-bool-f", interpret(exp, false).unwrap());
+    }
+
+    #[test]
+    fn rebinds() {
+        let exp = "(do (def i 0) (while (not (= i 3)) (def last i) (set i (+ i 1))) last)";
+        assert_eq!("2", interpret(exp, false).unwrap());
+
+        let exp = "(do (def x 1) (def f (fn [] (def x 2) x)) (f) x)";
+        assert_eq!("1", interpret(exp, false).unwrap());
+    }
+
     #[test]
     fn parses() {
         let (reg, mut tbl) = (memmgt::Region::acq(1000), Stab::new(51));
@@ -904,6 +1364,969 @@ mod tests {
         assert_eq!(gnd, out);
     }
 
+    #[test]
+    fn parses_qualified() {
+        let (reg, mut tbl) = (memmgt::Region::acq(1000), Stab::new(51));
+
+        // qualified names read as lists of module and member
+        let val = parser::parse(reg, &mut tbl, "(@geom/line 1)", false).unwrap();
+        let out = context(&tbl, val).to_string();
+        assert_eq!("((@geom line) 1)", out);
+    }
+
+    #[test]
+    fn parses_with_spans() {
+        let (reg, mut tbl) = (memmgt::Region::acq(1000), Stab::new(51));
+        let pos = |line, col| parser::Pos { line, col };
+
+        let (val, spans) =
+            parser::parse_spanned(reg, &mut tbl, "(a\n  \"bb\" 3)", false, false).unwrap();
+        let second = get_next_list_elt(ref_get(val.clone()).unwrap()).unwrap();
+
+        assert_eq!(spans.len(), 4);
+        assert_eq!(spans.locate(&val).unwrap().start, pos(1, 1));
+        assert_eq!(spans.locate(&val).unwrap().end, pos(2, 10));
+        assert_eq!(
+            spans.locate(&second),
+            Some(parser::Span {
+                start: pos(2, 3),
+                end: pos(2, 7)
+            })
+        );
+
+        // a program is read straight into a do form
+        let val = parser::parse(reg, &mut tbl, "1 2 ; three\n4", true).unwrap();
+        assert_eq!("(do 1 2 4)", context(&tbl, val).to_string());
+
+        let errs = parser::parse_spanned(reg, &mut tbl, "(a b%\n [1 )]\n #Q 1.2.3)", false, true)
+            .unwrap_err();
+        let found: Vec<_> = errs.iter().map(|e| (e.code, e.pos)).collect();
+        assert_eq!(
+            found,
+            [
+                (SlErrCode::ParseInvalidChar, pos(1, 5)),
+                (SlErrCode::ParseInvalidChar, pos(2, 5)),
+                (SlErrCode::ParseBadSpecial, pos(3, 2)),
+                (SlErrCode::ParseInvalidNum, pos(3, 5)),
+            ]
+        );
+
+        // without recovery, reading stops at the first problem
+        let errs = parser::parse_spanned(reg, &mut tbl, "(a b%\n [1 )]", false, false).unwrap_err();
+        assert_eq!(errs.len(), 1);
+
+        let errs = parser::parse_spanned(reg, &mut tbl, "(1 (2\n", true, true).unwrap_err();
+        assert_eq!(errs[0].code, SlErrCode::ParseUnexpectedEnd);
+        assert_eq!(errs[0].pos, pos(2, 1));
+    }
+
+    #[test]
+    fn copies_shared() {
+        let (reg, dst) = (memmgt::Region::acq(1000), memmgt::Region::acq(1000));
+
+        let item = i64_init(reg, 3);
+        let vec = stdvec_make(reg, 300);
+        for _ in 0..300 {
+            stdvec_push(vec.clone(), item.clone());
+        }
+
+        let copy = unsafe { SlHndl::from_raw_unchecked(structure_copy(dst, vec)) };
+        let shared = stdvec_idx(copy.clone(), 299);
+
+        assert_eq!(refc_total(unsafe { shared.get_raw() }), 301);
+
+        drop(copy);
+        assert_eq!(refc_total(unsafe { shared.get_raw() }), 1);
+        assert_eq!(i64_get(shared), 3);
+    }
+
+    #[test]
+    fn numeric_tower() {
+        assert_eq!("3.0", interpret("(+ 1 2.0)", false).unwrap());
+        assert_eq!("300i16", interpret("(+ 200u8 100i16)", false).unwrap());
+        assert_eq!("255u8", interpret("(* 0xFu8 17u8)", false).unwrap());
+        assert_eq!("0.001", interpret("(/ 1e-3 1)", false).unwrap());
+        assert_eq!("#T", interpret("(= 1.5f32 (/ 3 2.0))", false).unwrap());
+        assert_eq!("-1", interpret("(mod -7 2)", false).unwrap());
+
+        let err = interpret("(+ 255u8 1u8)", false).unwrap_err();
+        assert!(err.contains("ArithOverflow"));
+
+        let err = interpret("(neg 1u32)", false).unwrap_err();
+        assert!(err.contains("ArithOverflow"));
+
+        let exp = "(try (* 9223372036854775807 2) (fn [e] (err-code e)))";
+        assert_eq!(":arith-overflow", interpret(exp, false).unwrap());
+
+        assert_eq!("-3i8", interpret("(as-i8 -3.9)", false).unwrap());
+        assert_eq!(
+            "200u128",
+            interpret("(as-u128 (as-u8 200))", false).unwrap()
+        );
+        assert_eq!("0.5f32", interpret("(as-f32 0.5)", false).unwrap());
+        assert!(
+            interpret("(as-u8 256)", false)
+                .unwrap_err()
+                .contains("ArithOverflow")
+        );
+        assert!(
+            interpret("(as-i64 :k)", false)
+                .unwrap_err()
+                .contains("TypeInvalid")
+        );
+
+        let err = interpret("256u8", false).unwrap_err();
+        assert!(err.contains("ParseInvalidNum"));
+    }
+
+    #[test]
+    fn maps() {
+        assert_eq!("2", interpret("(map-get {:a 1 :b 2} :b)", false).unwrap());
+        assert_eq!("#F", interpret("(map-get {:a 1} :c)", false).unwrap());
+
+        let set = "(def m (map-make)) (map-set m 1 :one) (map-set m \"two\" 2) (map-set m 1 :uno)";
+        let get = |exp| interpret(&format!("(do {set} {exp})"), false).unwrap();
+        assert_eq!(":uno", get("(map-get m 1)"));
+        assert_eq!("2", get("(map-get m \"two\")"));
+        assert_eq!("2", get("(map-len m)"));
+
+        // enough entries to grow the map several times over
+        let set = "(def m (map-make)) (def i 0)
+                   (while (not (= i 100)) (map-set m i (* i i)) (set i (+ i 1)))
+                   (map-del m 3)";
+        let get = |exp| interpret(&format!("(do {set} {exp})"), false).unwrap();
+        assert_eq!("9801", get("(map-get m 99)"));
+        assert_eq!("#F", get("(map-has m 3)"));
+        assert_eq!("#F", get("(map-del m 3)"));
+        assert_eq!("99", get("(map-len m)"));
+
+        // a hash map refers to its values, as a vector does
+        let exp = "(do (def o (map-make)) (def v `[]) (map-set o :x v) (vec-push v 1)
+                       (vec-len (map-get o :x)))";
+        assert_eq!("1", interpret(exp, false).unwrap());
+
+        let exp = "(do (def o (map-make)) (def n (map-make)) (map-set o :n n)
+                       (map-set n :a 1) (map-set n :b 2) (map-get (map-get o :n) :b))";
+        assert_eq!("2", interpret(exp, false).unwrap());
+
+        let set = "(def m (alist-make)) (map-set m :a 1) (map-set m :b 2) (map-set m :a 3)
+                   (map-del m :b)";
+        let get = |exp| interpret(&format!("(do {set} {exp})"), false).unwrap();
+        assert_eq!("((:a 3))", get("m"));
+        assert_eq!("[:a]", get("(map-keys m)"));
+        assert_eq!("[3]", get("(map-vals m)"));
+
+        let err = interpret("(map-get [] :a)", false).unwrap_err();
+        assert!(err.contains("TypeInvalid"));
+
+        // a list is only a map if each element is a key and value
+        for exp in [
+            "(map-get '(1 2) 1)",
+            "(map-set '(1 2) 1 2)",
+            "(map-keys '(1 2))",
+            "(map-get '(()) 1)",
+            "(map-has '((1)) 1)",
+            "(map-vals '((1 2 3)))",
+        ] {
+            assert!(interpret(exp, false).unwrap_err().contains("TypeInvalid"));
+        }
+        assert_eq!("2", interpret("(map-get '((1 2)) 1)", false).unwrap());
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(
+            "\"héllo wörld\"",
+            interpret("(str-cat \"héllo\" \" wörld\")", false).unwrap()
+        );
+        assert_eq!("5", interpret("(str-len \"héllo\")", false).unwrap());
+        assert_eq!(
+            "\"éll\"",
+            interpret("(str-sub \"héllo\" 1 4)", false).unwrap()
+        );
+        assert_eq!("#\\l", interpret("(str-ref \"héllo\" 2)", false).unwrap());
+        assert_eq!("2", interpret("(str-find \"héllo\" #\\l)", false).unwrap());
+        assert_eq!(
+            "#F",
+            interpret("(str-find \"héllo\" \"z\")", false).unwrap()
+        );
+        assert_eq!(
+            "[\"a\" \"b\" \"\" \"c\"]",
+            interpret("(str-split \"a,b,,c\" \",\")", false).unwrap()
+        );
+        assert_eq!("\"-12.5\"", interpret("(str-of -12.5)", false).unwrap());
+        assert_eq!("\"(1 :a)\"", interpret("(str-of '(1 :a))", false).unwrap());
+        assert_eq!("\"kw\"", interpret("(sym-str :kw)", false).unwrap());
+        assert_eq!(
+            "new-sym",
+            interpret("(str-sym \"new-sym\")", false).unwrap()
+        );
+        assert_eq!("-x", interpret("(str-sym \"-x\")", false).unwrap());
+        for name in ["a b", "1", "-1", "", ":a", "a.b"] {
+            let exp = format!("(str-sym \"{name}\")");
+            assert!(interpret(&exp, false).unwrap_err().contains("TypeInvalid"));
+        }
+
+        // appending grows the string well past its first capacity
+        let exp = "(do (def s \"ab\") (def i 0)
+                       (while (not (= i 50)) (str-push s \"cd\") (set i (+ i 1)))
+                       (str-len s))";
+        assert_eq!("102", interpret(exp, false).unwrap());
+
+        let exp = "(str-push \"a\" \"bbbbbbbbbbbbbbbbbbbb\")";
+        assert_eq!("\"abbbbbbbbbbbbbbbbbbbb\"", interpret(exp, false).unwrap());
+
+        // a string literal in the program is never changed
+        let exp = "(do (def f (fn [] (do (def s \"a\") (str-push s \"x\") s))) (f) (f))";
+        assert_eq!("\"ax\"", interpret(exp, false).unwrap());
+
+        assert_eq!("#\\space", interpret("#\\space", false).unwrap());
+        assert_eq!(
+            "#\\λ",
+            interpret("(code-char (char-code #\\λ))", false).unwrap()
+        );
+        assert!(
+            interpret("#\\bad", false)
+                .unwrap_err()
+                .contains("ParseBadSpecial")
+        );
+        assert!(
+            interpret("(str-cat \"a\" 1)", false)
+                .unwrap_err()
+                .contains("TypeInvalid")
+        );
+        assert!(
+            interpret("(str-sub \"abc\" 2 4)", false)
+                .unwrap_err()
+                .contains("IndexOutOfRange")
+        );
+    }
+
+    #[test]
+    fn pops_vectors() {
+        let exp = "(do (def v `[1 2]) (vec-push v 3) (def x (vec-pop v)) `[,x ,(vec-pop v) ,v])";
+        assert_eq!("[3 2 [1]]", interpret(exp, false).unwrap());
+        assert!(
+            interpret("(vec-pop `[])", false)
+                .unwrap_err()
+                .contains("IndexOutOfRange")
+        );
+    }
+
+    #[test]
+    fn arrays() {
+        let run = |set: &str, exp: &str| interpret(&format!("(do {set} {exp})"), false);
+        let set = "(def a (arr-vec-make $f64 3 0.0)) (arr-vec-rplc a [1.0 2.0 3.0])
+                   (def b (arr-vec-make $f64 3 0.0)) (arr-vec-rplc b [4.0 5.0 6.0])";
+
+        assert_eq!("#f64[5.0 7.0 9.0]", run(set, "(arr-vec-add a b)").unwrap());
+        assert_eq!(
+            "#f64[-3.0 -3.0 -3.0]",
+            run(set, "(arr-vec-sub a b)").unwrap()
+        );
+        assert_eq!(
+            "#f64[2.0 4.0 6.0]",
+            run(set, "(arr-vec-scale a 2)").unwrap()
+        );
+        assert_eq!("32.0", run(set, "(arr-vec-dot a b)").unwrap());
+        assert_eq!(
+            "#f64[-3.0 6.0 -3.0]",
+            run(set, "(arr-vec-cross a b)").unwrap()
+        );
+        let exp = "(arr-vec-norm (arr-vec-cat (arr-vec-slice a 2 3) (arr-vec-slice b 0 1)))";
+        assert_eq!("5.0", run(set, exp).unwrap());
+        assert_eq!(
+            "#f64[2.5 3.5 4.5]",
+            run(set, "(arr-vec-lerp a b 0.5)").unwrap()
+        );
+        assert_eq!("#f64[1.0 2.0 3.0]", run(set, "(arr-vec-min a b)").unwrap());
+        assert_eq!(
+            "#f64[1.0 2.0 3.0 4.0 5.0 6.0]",
+            run(set, "(arr-vec-cat a b)").unwrap()
+        );
+        assert_eq!("#f64[2.0 3.0]", run(set, "(arr-vec-slice a 1 3)").unwrap());
+        assert_eq!("6", run(set, "(arr-vec-len (arr-vec-cat a b))").unwrap());
+
+        let exp = "(arr-vec-get (arr-vec-as (arr-vec-scale a 10) $u8) 2)";
+        assert_eq!("30u8", run(set, exp).unwrap());
+        let err = run(set, "(arr-vec-as (arr-vec-scale a 100) $u8)").unwrap_err();
+        assert!(err.contains("ArithOverflow"));
+
+        let err = run(set, "(arr-vec-add a (arr-vec-slice b 0 2))").unwrap_err();
+        assert!(err.contains("TypeMismatch"));
+        let err = run(set, "(arr-vec-cross a (arr-vec-cat a b))").unwrap_err();
+        assert!(err.contains("TypeMismatch"));
+        let err = run(set, "(arr-vec-slice a 2 5)").unwrap_err();
+        assert!(err.contains("IndexOutOfRange"));
+    }
+
+    #[test]
+    fn writes_readably() {
+        let (reg, mut tbl) = (memmgt::Region::acq(1000), Stab::new(51));
+
+        for exp in [
+            "(1 -2i8 3u128 4.0 -0.5f32 #inf #-inff32 1e-7)",
+            "[:kw $f32 @geom sym \"q\\\"b\\\\s\\n\" #\\space #\\λ]",
+            "#u16[0 1 65535]",
+            "#f32[]",
+            "#err(TypeMismatch)",
+            "#err(Raised \"bad \\\"thing\\\"\" :form (f x) :at \"a.sl\" 2 6)",
+            "#err(EvalBadForm \"\" :at 3 1)",
+            "\"bell\\u{7} cr\\u{d}\"",
+        ] {
+            let val = parser::parse(reg, &mut tbl, exp, false).unwrap();
+            assert_eq!(exp, context(&tbl, val).to_string());
+        }
+
+        let val = parser::parse(reg, &mut tbl, "#nanf32", false).unwrap();
+        assert!(f32_get(val).is_nan());
+
+        let err = parser::parse(reg, &mut tbl, "#u8[1 256]", false).unwrap_err();
+        assert_eq!(SlErrCode::ParseInvalidNum, err);
+        let err = parser::parse(reg, &mut tbl, "#err(NoSuchCode)", false).unwrap_err();
+        assert_eq!(SlErrCode::ParseBadSpecial, err);
+
+        // procedures and handles have a form that is refused on reading
+        assert_eq!("\"#<proc>\"", interpret("(write +)", false).unwrap());
+        let err = interpret("(parse (write (fn [x] x)))", false).unwrap_err();
+        assert!(err.contains("ParseUnreadable"));
+
+        let exp =
+            "(eq (parse (write '(1 [2u8 {:a #f64[0.5]}] \"s\"))) '(1 [2u8 {:a #f64[0.5]}] \"s\"))";
+        assert_eq!("#T", interpret(exp, false).unwrap());
+
+        // a container within itself is marked rather than written again
+        let exp = "(do (def v `[]) (vec-push v v) (write v))";
+        assert_eq!("\"[#<cycle>]\"", interpret(exp, false).unwrap());
+
+        let exp = "(do (def m (map-make)) (map-set m :m m) (write m))";
+        assert_eq!("\"{:m #<cycle>}\"", interpret(exp, false).unwrap());
+
+        // but one only shared, not within itself, is written in full
+        let exp = "(do (def v `[1]) (def w `[]) (vec-push w v) (vec-push w v) (write w))";
+        assert_eq!("\"[[1] [1]]\"", interpret(exp, false).unwrap());
+
+        let err = parser::parse(reg, &mut tbl, "\"\\u{110000}\"", false).unwrap_err();
+        assert_eq!(SlErrCode::ParseInvalidString, err);
+    }
+
+    #[test]
+    fn serializes() {
+        let exp = "(write (deserialize (serialize [1 \"a\" :k #\\b {:m [2.5]}])))";
+        assert_eq!(
+            "\"[1 \\\"a\\\" :k #\\\\b {:m [2.5]}]\"",
+            interpret(exp, false).unwrap()
+        );
+        assert!(
+            interpret("(serialize +)", false)
+                .unwrap_err()
+                .contains("TypeInvalid")
+        );
+    }
+
+    /// Builds random objects of every readable type, to check that each
+    /// is written in a form which reads back as an equal object
+    #[test]
+    fn write_read_round_trip() {
+        struct Gen(u64);
+
+        impl Gen {
+            // xorshift, so runs are repeatable
+            fn next(&mut self) -> u64 {
+                self.0 ^= self.0 << 13;
+                self.0 ^= self.0 >> 7;
+                self.0 ^= self.0 << 17;
+                self.0
+            }
+
+            fn below(&mut self, n: u64) -> u64 {
+                self.next() % n
+            }
+
+            fn pick<T: Copy>(&mut self, of: &[T]) -> T {
+                of[self.below(of.len() as u64) as usize]
+            }
+
+            // any error code, however many there are
+            fn code(&mut self) -> SlErrCode {
+                let count = (0..).map_while(|c| SlErrCode::try_from(c).ok()).count();
+                SlErrCode::try_from(self.below(count as u64) as u16).unwrap()
+            }
+
+            fn num(&mut self, typ: num::NumType) -> num::Num {
+                use num::{Num, NumType::*};
+
+                let bits = self.next();
+                let wide = (bits as u128) << 64 | self.next() as u128;
+                match typ {
+                    U8 => Num::U8(bits as _),
+                    I8 => Num::I8(bits as _),
+                    U16 => Num::U16(bits as _),
+                    I16 => Num::I16(bits as _),
+                    U32 => Num::U32(bits as _),
+                    I32 => Num::I32(bits as _),
+                    U64 => Num::U64(bits as _),
+                    I64 => Num::I64(bits as _),
+                    U128 => Num::U128(wide),
+                    I128 => Num::I128(wide as _),
+                    // NaN is not equal to itself, so finite values only
+                    F32 => Num::F32(
+                        Some(f32::from_bits(bits as _))
+                            .filter(|n| n.is_finite())
+                            .unwrap_or(0.25),
+                    ),
+                    F64 => Num::F64(
+                        Some(f64::from_bits(bits))
+                            .filter(|n| n.is_finite())
+                            .unwrap_or(-1e300),
+                    ),
+                }
+            }
+
+            fn text(&mut self) -> String {
+                let len = self.below(6);
+                (0..len)
+                    .map(|_| {
+                        self.pick(&['a', 'é', 'λ', '"', '\\', '\n', '\t', '\u{7}', ' ', '(', ';'])
+                    })
+                    .collect()
+            }
+
+            fn obj(&mut self, reg: *mut memmgt::Region, tbl: &mut Stab, depth: u32) -> SlHndl {
+                use num::NumType::*;
+
+                let types = [U8, I8, U16, I16, U32, I32, U64, I64, U128, I128, F32, F64];
+                let names = ["a", "foo-bar", "x1", "set-pos", "b_2"];
+                let kind = self.below(if depth == 0 { 9 } else { 13 });
+
+                match kind {
+                    0 => ref_make(reg),
+                    1 => bool_init(reg, self.below(2) == 0),
+                    2 | 3 => {
+                        let typ = self.pick(&types);
+                        self.num(typ).init(reg)
+                    }
+                    4 => {
+                        let mode = match self.below(4) {
+                            0 => SymbolMode::Basic,
+                            1 => SymbolMode::Keyword,
+                            2 => SymbolMode::Type,
+                            _ => SymbolMode::Module,
+                        };
+                        let id = tbl.get_id(self.pick(&names).as_bytes());
+                        sym_init(reg, modeize_sym(id, mode))
+                    }
+                    5 => string_init(reg, &self.text()),
+                    6 => unsafe {
+                        char_init(
+                            reg,
+                            self.pick(&['a', 'λ', '(', ';', '"', '#', ' ', '\n', '\t']),
+                        )
+                    },
+                    7 => {
+                        let typ = self.pick(&types);
+                        let vals: Vec<_> = (0..self.below(5)).map(|_| self.num(typ)).collect();
+                        arr::init(reg, typ, &vals).unwrap()
+                    }
+                    8 => {
+                        let code = self.code();
+                        errcode_init(reg, code)
+                    }
+                    9 => {
+                        let head = ref_make(reg);
+                        let mut tail = head.clone();
+                        for i in 0..self.below(4) {
+                            let elt = self.obj(reg, tbl, depth - 1);
+                            unsafe {
+                                inc_refc(elt.get_raw());
+                                if i == 0 {
+                                    write_ptr_unsafe_unchecked(tail, 0, elt.clone());
+                                } else {
+                                    set_next_list_elt_unsafe_unchecked(tail, elt.clone());
+                                }
+                            }
+                            tail = elt;
+                        }
+                        head
+                    }
+                    10 => {
+                        let elts: Vec<_> = (0..self.below(4))
+                            .map(|_| self.obj(reg, tbl, depth - 1))
+                            .collect();
+                        stdvec_init(reg, &elts)
+                    }
+                    11 => {
+                        let map = hashvec_make(reg, 4);
+                        for _ in 0..self.below(4) {
+                            let key = self.obj(reg, tbl, 0);
+                            let val = self.obj(reg, tbl, depth - 1);
+                            hash_map_insert(reg, map.clone(), key, val);
+                        }
+                        map
+                    }
+                    _ => {
+                        let code = self.code();
+                        let form = (self.below(2) == 0).then(|| self.obj(reg, tbl, depth - 1));
+                        let err = unsafe { err_init(reg, code, &self.text(), form) };
+                        let file = (self.below(2) == 0).then(|| string_init(reg, &self.text()));
+                        let line = self.below(3) as u32;
+                        err_set_span(
+                            err.clone(),
+                            file,
+                            line,
+                            if line == 0 {
+                                0
+                            } else {
+                                1 + self.below(80) as u32
+                            },
+                        );
+                        err
+                    }
+                }
+            }
+        }
+
+        let (reg, mut tbl) = (memmgt::Region::acq(1000), Stab::new(51));
+        let mut r#gen = Gen(0x5eed_2024_c0ff_ee11);
+
+        for _ in 0..500 {
+            let obj = r#gen.obj(reg, &mut tbl, 3);
+            let text = context(&tbl, obj.clone()).to_string();
+
+            let back = parser::parse(reg, &mut tbl, &text, false)
+                .unwrap_or_else(|err| panic!("{err:?} reading {text}"));
+            assert!(
+                core_eq(obj, back.clone()),
+                "{text} read as {}",
+                context(&tbl, back)
+            );
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let err = interpret("(+ 1 undefined-sym)", false).unwrap_err();
+        assert!(err.contains("EvalUnboundSymbol"));
+        assert!(err.contains("undefined-sym"));
+
+        let err = interpret("(+ 1 \"two\")", false).unwrap_err();
+        assert!(err.contains("TypeMismatch"));
+
+        let err = interpret("((fn [a b] a) 1)", false).unwrap_err();
+        assert!(err.contains("EvalArgCount"));
+
+        let err = interpret("(if #T 1)", false).unwrap_err();
+        assert!(err.contains("EvalBadForm"));
+
+        let err = interpret(
+            "(do (def x 2) (arr-vec-get (arr-vec-make $f32 2 (as-f32 0.0)) x))",
+            false,
+        )
+        .unwrap_err();
+        assert!(err.contains("IndexOutOfRange"));
+    }
+
+    #[test]
+    fn eval_contains_errors() {
+        let exp = "(do (def e (eval (quote (/ 1 0)))) (print e) 42)";
+        assert_eq!("42", interpret(exp, false).unwrap());
+
+        let exp = "(eval (parse \"(1 2\"))";
+        assert!(
+            interpret(exp, false)
+                .unwrap_err()
+                .contains("ParseUnexpectedEnd")
+        );
+
+        // the position is kept, though no spans are
+        let err = interpret("(parse \"(1\n  2 #?)\")", false).unwrap_err();
+        assert!(err.ends_with(":at 2 6)"));
+    }
+
+    #[test]
+    fn modules() {
+        let exp = "(do (module @geom (export twice) (def twice (fn [x] (* 2 x))) (def b 1))
+                       (@geom/twice 4))";
+        assert_eq!("8", interpret(exp, false).unwrap());
+
+        let exp = "(do (module @m (export a) (def a 5)) (import @m a) a)";
+        assert_eq!("5", interpret(exp, false).unwrap());
+
+        // only exported names may be reached from outside
+        let exp = "(do (module @m (export a) (def a 1) (def b 2)) @m/b)";
+        assert!(
+            interpret(exp, false)
+                .unwrap_err()
+                .contains("EvalUnboundSymbol")
+        );
+
+        let exp = "(do (module @m (export a) (def b 2)) 1)";
+        assert!(
+            interpret(exp, false)
+                .unwrap_err()
+                .contains("EvalUnboundSymbol")
+        );
+
+        assert!(
+            interpret("(export a)", false)
+                .unwrap_err()
+                .contains("EvalBadForm")
+        );
+    }
+
+    #[test]
+    fn loads_modules() {
+        let root = std::env::temp_dir().join(format!("sail-modules-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(
+            root.join("geom.sl"),
+            "(export twice)\n(def twice (fn [x] (* 2 x)))\n",
+        )
+        .unwrap();
+        std::fs::write(root.join("again.sl"), "(import @again)").unwrap();
+        std::fs::write(root.join("fails.sl"), "(export a)\n(def a (+ 1 :k))\n").unwrap();
+
+        let (_weft, thr) = test_weft(|tact| tact.set_search_path([root.clone()]), 0);
+        let thread_ref = unsafe { &mut *thr };
+
+        let exp = "(do (import @geom twice) (+ (twice 2) (@geom/twice 3)))";
+        assert_eq!("10", run(thread_ref, exp));
+
+        // loaded once, then found in the top level
+        std::fs::remove_file(root.join("geom.sl")).unwrap();
+        assert_eq!("2", run(thread_ref, "(do (import @geom) (@geom/twice 1))"));
+
+        assert!(run(thread_ref, "(import @again)").contains("EvalBadForm"));
+
+        // a module that failed to load fails the same way again
+        for _ in 0..2 {
+            let err = run(thread_ref, "(import @fails a)");
+            assert!(err.contains("TypeMismatch") && !err.contains("while it is loading"));
+        }
+        assert!(run(thread_ref, "(import @nowhere)").contains("FileCouldNotRead"));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn loads_files() {
+        let root = std::env::temp_dir().join(format!("sail-files-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("count.sl"), "(set n (+ n 1))\nn\n").unwrap();
+        std::fs::write(root.join("broken.sl"), "(+ 1 2").unwrap();
+        std::fs::write(root.join("wrong.sl"), "(def x 1)\n(+ x :k)\n").unwrap();
+
+        let (_weft, thr) = test_weft(
+            |tact| tact.set_search_path([std::env::temp_dir(), root.clone()]),
+            0,
+        );
+        let thread_ref = unsafe { &mut *thr };
+
+        // loaded every time, required once
+        assert_eq!("1", run(thread_ref, "(do (def n 0) (load \"count.sl\"))"));
+        assert_eq!("2", run(thread_ref, "(load \"count.sl\")"));
+        assert_eq!("#T", run(thread_ref, "(require \"count.sl\")"));
+        assert_eq!("#F", run(thread_ref, "(require \"count.sl\")"));
+        assert_eq!("3", run(thread_ref, "n"));
+
+        // parsed once, unless the file changes
+        std::fs::write(root.join("count.sl"), "(* n 10)\n").unwrap();
+        let stamp = std::time::SystemTime::now() + std::time::Duration::from_secs(5);
+        std::fs::File::options()
+            .write(true)
+            .open(root.join("count.sl"))
+            .and_then(|f| f.set_modified(stamp))
+            .unwrap();
+        assert_eq!("30", run(thread_ref, "(load \"count.sl\")"));
+
+        let path = root.join("broken.sl").canonicalize().unwrap();
+        let out = run(thread_ref, "(load \"broken.sl\")");
+        assert!(out.contains("could not parse file"));
+        assert!(out.ends_with(&format!(":at \"{}\" 1 7)", path.display())));
+
+        let path = root.join("wrong.sl").canonicalize().unwrap();
+        let out = run(thread_ref, "(load \"wrong.sl\")");
+        assert!(out.contains("TypeMismatch"));
+        assert!(out.ends_with(&format!(":at \"{}\" 2 6)", path.display())));
+
+        let out = run(
+            thread_ref,
+            "(try (load \"nowhere.sl\") (fn [e] (err-code e)))",
+        );
+        assert_eq!(":file-could-not-read", out);
+
+        // a file loaded from Rust tags its errors the same way
+        thread_ref.load_from_file("wrong.sl").unwrap();
+        while thread_ref.advance() {}
+        let out = context(thread_ref.context().symtab(), thread_ref.result().unwrap());
+        assert!(out.to_string().contains(&path.display().to_string()));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn try_catch() {
+        let exp = "(try (+ 1 2) (fn [e] 0))";
+        assert_eq!("3", interpret(exp, false).unwrap());
+
+        let exp = "(try (/ 1 0) (fn [e] (err-code e)))";
+        assert_eq!(":arith-div-by-zero", interpret(exp, false).unwrap());
+
+        let exp = "(try (do (raise \"bad message\") 1) (fn [e] (err-msg e)))";
+        assert_eq!("\"bad message\"", interpret(exp, false).unwrap());
+
+        let exp = "(try (raise 5) (fn [e] (err-form e)))";
+        assert_eq!("5", interpret(exp, false).unwrap());
+
+        let exp = "(try (try (raise 1) (fn [e] (raise e))) (fn [e] (err-code e)))";
+        assert_eq!(":raised", interpret(exp, false).unwrap());
+
+        let exp = "(do (def n 0) (while (not (= n 3)) (set n (try (raise n) (fn [e] (+ (err-form e) 1))))) n)";
+        assert_eq!("3", interpret(exp, false).unwrap());
+
+        let exp = "(try (raise 1) (fn [e] (/ 1 0)))";
+        assert!(
+            interpret(exp, false)
+                .unwrap_err()
+                .contains("ArithDivByZero")
+        );
+    }
+
+    #[test]
+    fn quasiquote() {
+        let exp = "(do (def x 2) (def l '(3 4)) `(1 ,x ,@l [,x ,@l] (quote ,x)))";
+        assert_eq!(
+            "(1 2 3 4 [2 3 4] (quote 2))",
+            interpret(exp, false).unwrap()
+        );
+
+        let exp = "(do (def l '(1 2)) `(a ,@l b) l)";
+        assert_eq!("(1 2)", interpret(exp, false).unwrap());
+
+        let exp = "`(1 `(2 ,(3 ,(+ 2 2))))";
+        assert_eq!(
+            "(1 (quasiquote (2 (unquote (3 4)))))",
+            interpret(exp, false).unwrap()
+        );
+
+        let exp = "`(1 ,@2)";
+        assert!(interpret(exp, false).unwrap_err().contains("TypeInvalid"));
+    }
+
+    #[test]
+    fn macros() {
+        let exp = "(do (defmacro swap [a b] `(,b ,a)) (swap 3 neg))";
+        assert_eq!("-3", interpret(exp, false).unwrap());
+
+        let exp = "(do (def sq (macro [x] `(* ,x ,x))) (sq (+ 1 2)))";
+        assert_eq!("9", interpret(exp, false).unwrap());
+
+        let exp = "(do (defmacro body [& forms] `(do ,@forms)) (body 1 2 3))";
+        assert_eq!("3", interpret(exp, false).unwrap());
+
+        let exp = "(do (defmacro two [a b] a) (two 1))";
+        assert!(interpret(exp, false).unwrap_err().contains("EvalArgCount"));
+
+        // a form is expanded the first time it is evaluated, then kept
+        let exp = "(do (def n 0) (defmacro m [] (do (set n (+ n 1)) n))
+                       (def f (fn [] (m))) (f) (f) (f) n)";
+        assert_eq!("1", interpret(exp, false).unwrap());
+
+        // the form itself is left as it was
+        let exp = "(do (defmacro m [x] `(+ ,x 1)) (def code '(m 1)) (eval code) code)";
+        assert_eq!("(m 1)", interpret(exp, false).unwrap());
+
+        // and is expanded again once the macro is redefined
+        let exp = "(do (defmacro m [] 1) (def f (fn [] (m))) (f)
+                       (defmacro m [] 2) (f))";
+        assert_eq!("2", interpret(exp, false).unwrap());
+
+        assert_eq!("#F", interpret("(eq (gensym) (gensym))", false).unwrap());
+
+        let exp = "(fn [a & b] a)";
+        assert!(interpret(exp, false).unwrap_err().contains("EvalBadForm"));
+    }
+
+    #[test]
+    fn prelude_macros() {
+        assert_eq!("#T", interpret("(and)", false).unwrap());
+        assert_eq!("3", interpret("(and 1 2 3)", false).unwrap());
+        assert_eq!("#F", interpret("(and 1 #F (/ 1 0))", false).unwrap());
+
+        assert_eq!("#F", interpret("(or)", false).unwrap());
+        assert_eq!("2", interpret("(or #F 2 (/ 1 0))", false).unwrap());
+
+        assert_eq!("2", interpret("(when #T 1 2)", false).unwrap());
+        assert_eq!("()", interpret("(when #F 1 2)", false).unwrap());
+        assert_eq!("()", interpret("(unless #T 1 2)", false).unwrap());
+        assert_eq!("2", interpret("(unless #F 1 2)", false).unwrap());
+
+        let exp = "(let [x 1 y (+ x 1)] (+ x y))";
+        assert_eq!("3", interpret(exp, false).unwrap());
+
+        // the name a macro binds is generated, so it cannot clash with the caller's
+        let exp = "(do (def val 5) (or #F val))";
+        assert_eq!("5", interpret(exp, false).unwrap());
+
+        let exp = "(match :b :a 1 :b 2 _ 3)";
+        assert_eq!("2", interpret(exp, false).unwrap());
+
+        let exp = "(match :z :a 1 :b 2 _ 3)";
+        assert_eq!("3", interpret(exp, false).unwrap());
+
+        let exp = "(match :z :a 1)";
+        assert_eq!("()", interpret(exp, false).unwrap());
+    }
+
+    #[test]
+    fn thread_join() {
+        let exp = "(th-join (th-spawn (fn [] (+ 1 2))))";
+        assert_eq!("3", interpret(exp, false).unwrap());
+
+        // the joining thread sleeps until a longer computation is done
+        let exp = "(do (def t (th-spawn (fn [] (do (def n 0)
+                                                   (while (not (= n 5000)) (set n (+ n 1)))
+                                                   n))))
+                       (+ (th-join t) (th-join t)))";
+        assert_eq!("10000", interpret(exp, false).unwrap());
+    }
+
+    #[test]
+    fn blocking_receive() {
+        let exp = "(do (qtx (own-tx-hdl) 5) (rest (qrx-wait)))";
+        assert_eq!("5", interpret(exp, false).unwrap());
+
+        let start = std::time::Instant::now();
+        assert_eq!("#F", interpret("(qrx-timeout 50)", false).unwrap());
+        assert!(start.elapsed() >= std::time::Duration::from_millis(50));
+
+        // a worker runs the spawned thread, which waits without it
+        let exp = "(th-join (th-spawn (fn [] (qrx-timeout 20))))";
+        assert_eq!("#F", interpret(exp, false).unwrap());
+
+        let exp = "(do (qtx (own-tx-hdl) 6) (rest (qrx-timeout 1000)))";
+        assert_eq!("6", interpret(exp, false).unwrap());
+
+        // a thread still waiting when its weft goes is not woken later
+        let exp = "(do (th-spawn (fn [] (qrx-timeout 300))) (qrx-timeout 50) 1)";
+        assert_eq!("1", interpret(exp, false).unwrap());
+        std::thread::sleep(std::time::Duration::from_millis(400));
+
+        let exp = "(qrx-timeout -1)";
+        assert!(
+            interpret(exp, false)
+                .unwrap_err()
+                .contains("IndexOutOfRange")
+        );
+    }
+
+    #[test]
+    fn channels() {
+        let exp = "(do (qtx (chan-open :b) 7) (select [:a :b]))";
+        assert_eq!(":b", interpret(exp, false).unwrap());
+
+        let exp = "(do (qtx (chan-open :b) 7) (rest (rest (select [:a :b]))))";
+        assert_eq!("7", interpret(exp, false).unwrap());
+
+        let exp = "(do (qtx (chan-open :a) 1) (rest (chan-rx :a)))";
+        assert_eq!("1", interpret(exp, false).unwrap());
+        assert_eq!("#F", interpret("(chan-rx :a)", false).unwrap());
+
+        // the child hands out a send-only handle, then waits on it
+        let exp = "(do (def tgt (own-tx-hdl))
+                       (def t (th-spawn (fn [] (do (qtx tgt (chan-open :work))
+                                                   (rest (rest (select [:ctl :work])))))))
+                       (qtx (rest (qrx-wait)) 9)
+                       (th-join t))";
+        assert_eq!("9", interpret(exp, false).unwrap());
+
+        assert!(
+            interpret("(select [])", false)
+                .unwrap_err()
+                .contains("IndexOutOfRange")
+        );
+        assert!(
+            interpret("(select [1])", false)
+                .unwrap_err()
+                .contains("TypeInvalid")
+        );
+    }
+
+    #[test]
+    fn thread_control() {
+        // how an ended thread ended is kept until it is joined, and
+        // its handle keeps the result for joining again
+        let exp = "(do (def t (th-spawn (fn [] 1))) (th-monitor t) (qrx-wait) (th-status t))";
+        assert_eq!(":done", interpret(exp, false).unwrap());
+
+        let exp = "(do (def t (th-spawn (fn [] 1))) (th-join t)
+                       (while (not (eq (th-status t) :gone)) ())
+                       (th-join t))";
+        assert_eq!("1", interpret(exp, false).unwrap());
+
+        // monitors hear how a thread ended, along with its result
+        let exp = "(do (def t (th-spawn (fn [] (+ 2 3)))) (th-monitor t) (rest (qrx-wait)))";
+        assert_eq!(":done", interpret(exp, false).unwrap());
+
+        let exp = "(do (def t (th-spawn (fn [] (+ 2 3)))) (th-monitor t) (rest (rest (qrx-wait))))";
+        assert_eq!("5", interpret(exp, false).unwrap());
+
+        let exp = "(do (def t (th-spawn (fn [] (/ 1 0)))) (th-monitor t) (rest (qrx-wait)))";
+        assert_eq!(":failed", interpret(exp, false).unwrap());
+
+        let exp = "(do (def t (th-spawn (fn [] (qrx-wait)))) (th-monitor t) (th-kill t) (rest (qrx-wait)))";
+        assert_eq!(":killed", interpret(exp, false).unwrap());
+
+        // a killed thread is removed, and its queue with it
+        let exp = "(do (def t (th-spawn (fn [] (qrx-wait))))
+                       (while (not (eq (th-status t) :blocked)) ())
+                       (th-kill t)
+                       (th-join t)
+                       (while (not (eq (th-status t) :gone)) ())
+                       (qtx (rest t) 1))";
+        assert_eq!("#F", interpret(exp, false).unwrap());
+
+        // joining gives the error the thread was killed with
+        let exp = "(do (def t (th-spawn (fn [] (qrx-wait)))) (th-kill t) (err-code (th-join t)))";
+        assert_eq!(":thread-killed", interpret(exp, false).unwrap());
+
+        // an interrupt wakes a blocked thread to raise its error
+        let exp = "(do (def t (th-spawn (fn [] (qrx-wait))))
+                       (while (not (eq (th-status t) :blocked)) ())
+                       (th-interrupt t)
+                       (err-code (th-join t)))";
+        assert_eq!(":thread-interrupted", interpret(exp, false).unwrap());
+
+        // a spawned thread sees its parent's bindings as they were at
+        // the spawn, and nothing defined or changed there afterwards
+        let exp = "(do (def q (th-spawn (fn [] (do (qrx-wait) x))))
+                       (def x 5)
+                       (qtx (rest q) 0)
+                       (th-join q))";
+        assert!(
+            interpret(exp, false)
+                .unwrap_err()
+                .contains("EvalUnboundSymbol")
+        );
+
+        let exp = "(do (def x 1)
+                       (def q (th-spawn (fn [] (do (qrx-wait) x))))
+                       (set x 2)
+                       (qtx (rest q) 0)
+                       (+ x (th-join q)))";
+        assert_eq!("3", interpret(exp, false).unwrap());
+
+        // a thread outlives the one which spawned it, and still sees
+        // the bindings it was spawned with
+        let exp = "(do (def k 41)
+                       (def p (th-spawn (fn [] (th-spawn (fn [] (do (qrx-wait) (+ k 1)))))))
+                       (def c (th-join p))
+                       (while (not (eq (th-status p) :gone)) ())
+                       (qtx (rest c) 0)
+                       (th-join c))";
+        assert_eq!("42", interpret(exp, false).unwrap());
+    }
+
     #[test]
     fn mult_while_direct() {
         let input = include_str!("../../examples/mult-while.sl");
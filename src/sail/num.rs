@@ -0,0 +1,756 @@
+// STARK, a system for computer augmented design.
+
+// SPDX-FileCopyrightText: © 2021 Matthew Rothlisberger
+// SPDX-License-Identifier: AGPL-3.0-only
+
+// STARK is licensed under the terms of the GNU Affero General Public
+// License version 3. See the top-level LICENSES directory for the
+// license text.
+
+// Find full copyright information in the top-level COPYRIGHT file.
+
+// <>
+
+// src/sail/num.rs
+
+// Numeric tower for Sail. Reads numbers of every core numeric type
+// out of objects and text, promotes mixed operands to a common type,
+// and performs checked arithmetic and conversion between types.
+
+// <>
+
+use super::core::*;
+use super::{SlErrCode, memmgt};
+
+use std::cmp::Ordering;
+use std::fmt;
+
+/// Number taken out of a Sail object, of any core numeric type
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Num {
+    U8(u8),
+    I8(i8),
+    U16(u16),
+    I16(i16),
+    U32(u32),
+    I32(i32),
+    U64(u64),
+    I64(i64),
+    U128(u128),
+    I128(i128),
+    F32(f32),
+    F64(f64),
+}
+
+/// Type of a number; one for each core numeric type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumType {
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+    U64,
+    I64,
+    U128,
+    I128,
+    F32,
+    F64,
+}
+
+/// Arithmetic operation on two numbers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+/// Calls the given macro with the variant, Rust type, getter and
+/// constructor of every integer type, after any arguments given
+macro_rules! each_int {
+    ($mac:ident $(, $arg:expr)*) => {
+        $mac!($($arg,)*
+              U8 u8 u8_get u8_init, I8 i8 i8_get i8_init,
+              U16 u16 u16_get u16_init, I16 i16 i16_get i16_init,
+              U32 u32 u32_get u32_init, I32 i32 i32_get i32_init,
+              U64 u64 u64_get u64_init, I64 i64 i64_get i64_init,
+              U128 u128 u128_get u128_init, I128 i128 i128_get i128_init)
+    };
+}
+
+impl NumType {
+    /// Gives the numeric type matching a core type, if there is one
+    pub fn of_core(typ: CoreType) -> Option<Self> {
+        macro_rules! arms {
+            ($typ:expr, $($v:ident $t:ident $get:ident $init:ident),*) => {
+                match $typ {
+                    $(CoreType::$v => Some(Self::$v),)*
+                    CoreType::F32 => Some(Self::F32),
+                    CoreType::F64 => Some(Self::F64),
+                    _ => None,
+                }
+            };
+        }
+        each_int!(arms, typ)
+    }
+
+    /// Reads a literal suffix, as in `3u8` or `1.5f32`
+    pub fn of_suffix(suffix: &str) -> Option<Self> {
+        Some(match suffix {
+            "u8" => Self::U8,
+            "i8" => Self::I8,
+            "u16" => Self::U16,
+            "i16" => Self::I16,
+            "u32" => Self::U32,
+            "i32" => Self::I32,
+            "u64" => Self::U64,
+            "i64" => Self::I64,
+            "u128" => Self::U128,
+            "i128" => Self::I128,
+            "f32" => Self::F32,
+            "f64" => Self::F64,
+            _ => return None,
+        })
+    }
+
+    /// Gives the suffix which marks a literal of the type
+    pub fn suffix(self) -> &'static str {
+        match self {
+            Self::U8 => "u8",
+            Self::I8 => "i8",
+            Self::U16 => "u16",
+            Self::I16 => "i16",
+            Self::U32 => "u32",
+            Self::I32 => "i32",
+            Self::U64 => "u64",
+            Self::I64 => "i64",
+            Self::U128 => "u128",
+            Self::I128 => "i128",
+            Self::F32 => "f32",
+            Self::F64 => "f64",
+        }
+    }
+
+    /// Gives the numeric type named by a type symbol, as in `$f32`
+    pub fn of_sym(id: u32) -> Option<Self> {
+        [
+            Self::U8,
+            Self::I8,
+            Self::U16,
+            Self::I16,
+            Self::U32,
+            Self::I32,
+            Self::U64,
+            Self::I64,
+            Self::U128,
+            Self::I128,
+            Self::F32,
+            Self::F64,
+        ]
+        .into_iter()
+        .find(|t| t.sym() == id)
+    }
+
+    /// Gives the type symbol naming the numeric type
+    pub fn sym(self) -> u32 {
+        match self {
+            Self::U8 => super::T_U8.0,
+            Self::I8 => super::T_I8.0,
+            Self::U16 => super::T_U16.0,
+            Self::I16 => super::T_I16.0,
+            Self::U32 => super::T_U32.0,
+            Self::I32 => super::T_I32.0,
+            Self::U64 => super::T_U64.0,
+            Self::I64 => super::T_I64.0,
+            Self::U128 => super::T_U128.0,
+            Self::I128 => super::T_I128.0,
+            Self::F32 => super::T_F32.0,
+            Self::F64 => super::T_F64.0,
+        }
+    }
+
+    pub fn bits(self) -> u32 {
+        match self {
+            Self::U8 | Self::I8 => 8,
+            Self::U16 | Self::I16 => 16,
+            Self::U32 | Self::I32 | Self::F32 => 32,
+            Self::U64 | Self::I64 | Self::F64 => 64,
+            Self::U128 | Self::I128 => 128,
+        }
+    }
+
+    pub fn float_p(self) -> bool {
+        matches!(self, Self::F32 | Self::F64)
+    }
+
+    pub fn signed_p(self) -> bool {
+        matches!(
+            self,
+            Self::I8 | Self::I16 | Self::I32 | Self::I64 | Self::I128 | Self::F32 | Self::F64
+        )
+    }
+
+    /// Gives the integer type of the given width and signedness
+    fn int(bits: u32, signed: bool) -> Self {
+        match (bits, signed) {
+            (8, false) => Self::U8,
+            (8, true) => Self::I8,
+            (16, false) => Self::U16,
+            (16, true) => Self::I16,
+            (32, false) => Self::U32,
+            (32, true) => Self::I32,
+            (64, false) => Self::U64,
+            (64, true) => Self::I64,
+            (_, false) => Self::U128,
+            (_, true) => Self::I128,
+        }
+    }
+
+    /// Gives the type to which operands of two types are promoted
+    ///
+    /// A float beats an integer and the wider float beats the
+    /// narrower; the wider of two integers wins if they agree in sign.
+    /// Otherwise the signed type wins if it is wider, and if not, the
+    /// result is the signed type twice the width of the unsigned one,
+    /// up to 128 bits.
+    pub fn common(a: Self, b: Self) -> Self {
+        if a == b {
+            return a;
+        }
+
+        match (a.float_p(), b.float_p()) {
+            (true, true) => Self::F64,
+            (true, false) => a,
+            (false, true) => b,
+            _ if a.signed_p() == b.signed_p() => {
+                if a.bits() >= b.bits() {
+                    a
+                } else {
+                    b
+                }
+            }
+            _ => {
+                let (s, u) = if a.signed_p() { (a, b) } else { (b, a) };
+                if s.bits() > u.bits() {
+                    s
+                } else {
+                    Self::int((u.bits() * 2).min(128), true)
+                }
+            }
+        }
+    }
+}
+
+/// Number widened to the largest type of its kind, for conversion
+#[derive(Debug, Clone, Copy)]
+enum Wide {
+    Int(i128),
+    UInt(u128),
+    Float(f64),
+}
+
+impl Num {
+    /// Takes the number out of a Sail object, if it holds one
+    pub fn of(obj: &SlHndl) -> Option<Self> {
+        macro_rules! arms {
+            ($obj:expr, $($v:ident $t:ident $get:ident $init:ident),*) => {
+                match NumType::of_core($obj.clone().core_type()?)? {
+                    $(NumType::$v => Self::$v($get($obj.clone())),)*
+                    NumType::F32 => Self::F32(f32_get($obj.clone())),
+                    NumType::F64 => Self::F64(f64_get($obj.clone())),
+                }
+            };
+        }
+        Some(each_int!(arms, obj))
+    }
+
+    /// Creates a Sail object holding the number
+    pub fn init(self, reg: *mut memmgt::Region) -> SlHndl {
+        macro_rules! arms {
+            ($num:expr, $reg:expr, $($v:ident $t:ident $get:ident $init:ident),*) => {
+                match $num {
+                    $(Self::$v(n) => $init($reg, n),)*
+                    Self::F32(n) => f32_init($reg, n),
+                    Self::F64(n) => f64_init($reg, n),
+                }
+            };
+        }
+        each_int!(arms, self, reg)
+    }
+
+    /// Reads a number of the given type out of its bytes in memory
+    pub fn read(typ: NumType, bytes: &[u8]) -> Self {
+        macro_rules! arms {
+            ($typ:expr, $bytes:expr, $($v:ident $t:ident $get:ident $init:ident),*) => {
+                match $typ {
+                    $(NumType::$v => Self::$v(<$t>::from_ne_bytes($bytes.try_into().unwrap())),)*
+                    NumType::F32 => Self::F32(f32::from_ne_bytes($bytes.try_into().unwrap())),
+                    NumType::F64 => Self::F64(f64::from_ne_bytes($bytes.try_into().unwrap())),
+                }
+            };
+        }
+        each_int!(arms, typ, bytes)
+    }
+
+    /// Writes the number's bytes into memory
+    pub fn write(self, bytes: &mut [u8]) {
+        macro_rules! arms {
+            ($num:expr, $bytes:expr, $($v:ident $t:ident $get:ident $init:ident),*) => {
+                match $num {
+                    $(Self::$v(n) => $bytes.copy_from_slice(&n.to_ne_bytes()),)*
+                    Self::F32(n) => $bytes.copy_from_slice(&n.to_ne_bytes()),
+                    Self::F64(n) => $bytes.copy_from_slice(&n.to_ne_bytes()),
+                }
+            };
+        }
+        each_int!(arms, self, bytes)
+    }
+
+    pub fn typ(self) -> NumType {
+        macro_rules! arms {
+            ($num:expr, $($v:ident $t:ident $get:ident $init:ident),*) => {
+                match $num {
+                    $(Self::$v(_) => NumType::$v,)*
+                    Self::F32(_) => NumType::F32,
+                    Self::F64(_) => NumType::F64,
+                }
+            };
+        }
+        each_int!(arms, self)
+    }
+
+    fn wide(self) -> Wide {
+        match self {
+            Self::U8(n) => Wide::UInt(n as u128),
+            Self::U16(n) => Wide::UInt(n as u128),
+            Self::U32(n) => Wide::UInt(n as u128),
+            Self::U64(n) => Wide::UInt(n as u128),
+            Self::U128(n) => Wide::UInt(n),
+            Self::I8(n) => Wide::Int(n as i128),
+            Self::I16(n) => Wide::Int(n as i128),
+            Self::I32(n) => Wide::Int(n as i128),
+            Self::I64(n) => Wide::Int(n as i128),
+            Self::I128(n) => Wide::Int(n),
+            Self::F32(n) => Wide::Float(n as f64),
+            Self::F64(n) => Wide::Float(n),
+        }
+    }
+
+    /// Converts the number to another type, if it fits
+    ///
+    /// A float converted to an integer is truncated toward zero; a
+    /// float that is not finite fits no integer type. Integers may
+    /// lose precision as floats, but must not overflow.
+    pub fn to(self, typ: NumType) -> Option<Self> {
+        macro_rules! arms {
+            ($wide:expr, $typ:expr, $($v:ident $t:ident $get:ident $init:ident),*) => {
+                match $typ {
+                    $(NumType::$v => match $wide {
+                        Wide::Int(n) => <$t>::try_from(n).ok().map(Self::$v),
+                        Wide::UInt(n) => <$t>::try_from(n).ok().map(Self::$v),
+                        Wide::Float(n) => {
+                            float_to_int(n, $typ).map(|n| Self::$v(n as $t))
+                        }
+                    },)*
+                    NumType::F32 => {
+                        let n = match $wide {
+                            Wide::Int(n) => n as f32,
+                            Wide::UInt(n) => n as f32,
+                            Wide::Float(n) => n as f32,
+                        };
+                        // finite values must stay finite
+                        match $wide {
+                            Wide::Float(w) if !w.is_finite() => Some(Self::F32(n)),
+                            _ if !n.is_finite() => None,
+                            _ => Some(Self::F32(n)),
+                        }
+                    }
+                    NumType::F64 => Some(Self::F64(match $wide {
+                        Wide::Int(n) => n as f64,
+                        Wide::UInt(n) => n as f64,
+                        Wide::Float(n) => n,
+                    })),
+                }
+            };
+        }
+
+        if self.typ() == typ {
+            return Some(self);
+        }
+
+        each_int!(arms, self.wide(), typ)
+    }
+}
+
+/// Writes the number as a literal which reads back as the same number
+///
+/// Only `i64` and `f64`, the types of literals without a suffix, are
+/// written without one.
+impl fmt::Display for Num {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let suffix = match self.typ() {
+            NumType::I64 | NumType::F64 => "",
+            typ => typ.suffix(),
+        };
+
+        write!(f, "{}{}", self.bare(), suffix)
+    }
+}
+
+impl Num {
+    /// Gives the number as a literal without a type suffix, as written
+    /// among the elements of an array of known type
+    pub fn bare(self) -> String {
+        macro_rules! arms {
+            ($num:expr, $($v:ident $t:ident $get:ident $init:ident),*) => {
+                match $num {
+                    $(Self::$v(n) => n.to_string(),)*
+                    Self::F32(n) => float_lit(n as f64, format!("{:?}", n)),
+                    Self::F64(n) => float_lit(n, format!("{:?}", n)),
+                }
+            };
+        }
+        each_int!(arms, self)
+    }
+}
+
+/// Gives the literal for a float, given its shortest decimal form;
+/// values which are not finite have names read after `#`
+fn float_lit(n: f64, decimal: String) -> String {
+    if n.is_nan() {
+        "#nan".to_owned()
+    } else if n.is_infinite() {
+        if n > 0.0 { "#inf" } else { "#-inf" }.to_owned()
+    } else {
+        decimal
+    }
+}
+
+/// Truncates a float for conversion to an integer type, if the result
+/// is within the range of the type
+fn float_to_int(n: f64, typ: NumType) -> Option<f64> {
+    if !n.is_finite() {
+        return None;
+    }
+
+    let n = n.trunc();
+    let bits = typ.bits() as i32;
+
+    // powers of two are exact, unlike the maximum of each type
+    let (min, limit) = if typ.signed_p() {
+        (-(2f64.powi(bits - 1)), 2f64.powi(bits - 1))
+    } else {
+        (0.0, 2f64.powi(bits))
+    };
+
+    (n >= min && n < limit).then_some(n)
+}
+
+/// Applies an arithmetic operation to two numbers, once promoted to
+/// their common type
+///
+/// Integer operations are checked, so overflow raises an error, as
+/// does integer division by zero; float operations follow IEEE 754.
+pub fn arith(op: Op, a: Num, b: Num) -> Result<Num, SlErrCode> {
+    macro_rules! arms {
+        ($op:expr, $a:expr, $b:expr, $($v:ident $t:ident $get:ident $init:ident),*) => {
+            match ($a, $b) {
+                $((Num::$v(x), Num::$v(y)) => {
+                    if y == 0 && matches!($op, Op::Div | Op::Rem) {
+                        return Err(SlErrCode::ArithDivByZero);
+                    }
+
+                    let out = match $op {
+                        Op::Add => x.checked_add(y),
+                        Op::Sub => x.checked_sub(y),
+                        Op::Mul => x.checked_mul(y),
+                        Op::Div => x.checked_div(y),
+                        Op::Rem => x.checked_rem(y),
+                    };
+                    out.map(Num::$v).ok_or(SlErrCode::ArithOverflow)
+                })*
+                (Num::F32(x), Num::F32(y)) => Ok(Num::F32(float_op($op, x as f64, y as f64) as f32)),
+                (Num::F64(x), Num::F64(y)) => Ok(Num::F64(float_op($op, x, y))),
+                _ => unreachable!("operands promoted to differing types"),
+            }
+        };
+    }
+
+    let typ = NumType::common(a.typ(), b.typ());
+    let a = a.to(typ).ok_or(SlErrCode::ArithOverflow)?;
+    let b = b.to(typ).ok_or(SlErrCode::ArithOverflow)?;
+
+    each_int!(arms, op, a, b)
+}
+
+fn float_op(op: Op, x: f64, y: f64) -> f64 {
+    match op {
+        Op::Add => x + y,
+        Op::Sub => x - y,
+        Op::Mul => x * y,
+        Op::Div => x / y,
+        Op::Rem => x % y,
+    }
+}
+
+/// Negates a number; negating a nonzero unsigned number overflows
+pub fn neg(a: Num) -> Result<Num, SlErrCode> {
+    macro_rules! arms {
+        ($a:expr, $($v:ident $t:ident $get:ident $init:ident),*) => {
+            match $a {
+                $(Num::$v(x) => x.checked_neg().map(Num::$v).ok_or(SlErrCode::ArithOverflow),)*
+                Num::F32(x) => Ok(Num::F32(-x)),
+                Num::F64(x) => Ok(Num::F64(-x)),
+            }
+        };
+    }
+    each_int!(arms, a)
+}
+
+/// Compares two numbers by value, whatever their types; gives nothing
+/// if either is not a number (NaN)
+pub fn compare(a: Num, b: Num) -> Option<Ordering> {
+    match (a.wide(), b.wide()) {
+        (Wide::Int(x), Wide::Int(y)) => Some(x.cmp(&y)),
+        (Wide::UInt(x), Wide::UInt(y)) => Some(x.cmp(&y)),
+        (Wide::Int(x), Wide::UInt(y)) => Some(match u128::try_from(x) {
+            Ok(x) => x.cmp(&y),
+            Err(_) => Ordering::Less,
+        }),
+        (Wide::UInt(_), Wide::Int(_)) => compare(b, a).map(Ordering::reverse),
+        (Wide::Float(x), Wide::Float(y)) => x.partial_cmp(&y),
+        (Wide::Float(x), Wide::Int(y)) => x.partial_cmp(&(y as f64)),
+        (Wide::Float(x), Wide::UInt(y)) => x.partial_cmp(&(y as f64)),
+        (_, Wide::Float(_)) => compare(b, a).map(Ordering::reverse),
+    }
+}
+
+/// Reads a numeric literal, as in `42`, `-7i8`, `0xFFu8`, `1.5f32` or
+/// `1e-3`, and underscores may separate digits
+///
+/// Integers without a suffix are 64 bit and signed, and floats 64 bit.
+/// A literal whose value does not fit its type is not read. The names
+/// `inf` and `nan`, which the parser reads after `#`, give floats.
+pub fn read_num(text: &str) -> Option<Num> {
+    read_typed(text, None)
+}
+
+/// Reads a numeric literal as `read_num` does, except that a literal
+/// without a suffix has the given type
+pub fn read_num_as(text: &str, typ: NumType) -> Option<Num> {
+    read_typed(text, Some(typ))
+}
+
+fn read_typed(text: &str, default: Option<NumType>) -> Option<Num> {
+    let text = text.replace('_', "");
+
+    let (negative, body) = match text.as_bytes().first()? {
+        b'-' => (true, &text[1..]),
+        b'+' => (false, &text[1..]),
+        _ => (false, &text[..]),
+    };
+
+    let (radix, body) = match body.get(..2) {
+        Some("0x" | "0X") => (16, &body[2..]),
+        Some("0o" | "0O") => (8, &body[2..]),
+        Some("0b" | "0B") => (2, &body[2..]),
+        _ => (10, body),
+    };
+
+    for (name, n) in [("inf", f64::INFINITY), ("nan", f64::NAN)] {
+        if let Some(suffix) = body.strip_prefix(name) {
+            let typ = match suffix {
+                "" => default.unwrap_or(NumType::F64),
+                _ => NumType::of_suffix(suffix)?,
+            };
+
+            let n = if negative { -n } else { n };
+            return if typ.float_p() {
+                Num::F64(n).to(typ)
+            } else {
+                None
+            };
+        }
+    }
+
+    // a hexadecimal f is a digit, so floats are never hexadecimal
+    let split = body
+        .find(|c| c == 'u' || c == 'i' || (c == 'f' && radix == 10))
+        .unwrap_or(body.len());
+    let (digits, typ) = match body.split_at(split) {
+        (digits, "") => (digits, default),
+        (digits, suffix) => (digits, Some(NumType::of_suffix(suffix)?)),
+    };
+
+    if digits.is_empty() || !digits.starts_with(|c: char| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+
+    let float_syntax = radix == 10 && digits.contains(['.', 'e', 'E']);
+
+    if float_syntax || typ.is_some_and(NumType::float_p) {
+        if radix != 10 || typ.is_some_and(|t| !t.float_p()) {
+            return None;
+        }
+
+        // read straight into a 32 bit float, to avoid rounding twice
+        if typ == Some(NumType::F32) {
+            let n: f32 = digits.parse().ok()?;
+            return n
+                .is_finite()
+                .then_some(Num::F32(if negative { -n } else { n }));
+        }
+
+        let n: f64 = digits.parse().ok()?;
+        let n = if negative { -n } else { n };
+        return Num::F64(n).to(typ.unwrap_or(NumType::F64));
+    }
+
+    let magnitude = u128::from_str_radix(digits, radix).ok()?;
+    let wide = if negative {
+        match i128::try_from(magnitude) {
+            Ok(m) => Num::I128(-m),
+            Err(_) if magnitude == 1 << 127 => Num::I128(i128::MIN),
+            Err(_) => return None,
+        }
+    } else {
+        Num::U128(magnitude)
+    };
+
+    wide.to(typ.unwrap_or(NumType::I64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_literals() {
+        assert_eq!(read_num("42"), Some(Num::I64(42)));
+        assert_eq!(read_num("-7i8"), Some(Num::I8(-7)));
+        assert_eq!(read_num("3u8"), Some(Num::U8(3)));
+        assert_eq!(read_num("0xFF"), Some(Num::I64(255)));
+        assert_eq!(read_num("0xFFu8"), Some(Num::U8(255)));
+        assert_eq!(read_num("0b1010_1010u8"), Some(Num::U8(170)));
+        assert_eq!(read_num("1_000_000"), Some(Num::I64(1_000_000)));
+        assert_eq!(read_num("1.5f32"), Some(Num::F32(1.5)));
+        assert_eq!(read_num("2f64"), Some(Num::F64(2.0)));
+        assert_eq!(read_num("1e-3"), Some(Num::F64(1e-3)));
+        assert_eq!(read_num("-128i8"), Some(Num::I8(-128)));
+        assert_eq!(
+            read_num("-170141183460469231731687303715884105728i128"),
+            Some(Num::I128(i128::MIN))
+        );
+
+        assert_eq!(read_num("256u8"), None);
+        assert_eq!(read_num("-1u32"), None);
+        assert_eq!(read_num("9223372036854775808"), None);
+        assert_eq!(read_num("1.5u8"), None);
+        assert_eq!(read_num("0x1.8"), None);
+        assert_eq!(read_num("3u7"), None);
+        assert_eq!(read_num("1.2.3"), None);
+
+        assert_eq!(read_num("-inf"), Some(Num::F64(f64::NEG_INFINITY)));
+        assert_eq!(read_num("inff32"), Some(Num::F32(f32::INFINITY)));
+        assert!(matches!(read_num("nan"), Some(Num::F64(n)) if n.is_nan()));
+        assert_eq!(read_num("infu8"), None);
+        assert_eq!(read_num_as("3", NumType::U8), Some(Num::U8(3)));
+        assert_eq!(read_num_as("0.1", NumType::F32), Some(Num::F32(0.1)));
+        assert_eq!(read_num_as("-1", NumType::U16), None);
+    }
+
+    #[test]
+    fn writes_literals() {
+        assert_eq!(Num::I64(-42).to_string(), "-42");
+        assert_eq!(Num::U8(3).to_string(), "3u8");
+        assert_eq!(
+            Num::I128(i128::MIN).to_string(),
+            format!("{}i128", i128::MIN)
+        );
+        assert_eq!(Num::F64(2.0).to_string(), "2.0");
+        assert_eq!(Num::F64(1e-7).to_string(), "1e-7");
+        assert_eq!(Num::F32(0.1).to_string(), "0.1f32");
+        assert_eq!(Num::F32(f32::NEG_INFINITY).to_string(), "#-inff32");
+        assert_eq!(Num::F64(f64::NAN).bare(), "#nan");
+
+        for n in [
+            Num::U128(u128::MAX),
+            Num::I8(-128),
+            Num::F32(f32::MAX),
+            Num::F32(f32::MIN_POSITIVE),
+            Num::F64(f64::MIN),
+            Num::F64(-0.0),
+        ] {
+            let text = n.to_string();
+            assert_eq!(read_num(text.trim_start_matches('#')), Some(n), "{text}");
+        }
+    }
+
+    #[test]
+    fn promotes() {
+        use NumType::*;
+
+        assert_eq!(NumType::common(U8, U32), U32);
+        assert_eq!(NumType::common(I8, I64), I64);
+        assert_eq!(NumType::common(U8, I16), I16);
+        assert_eq!(NumType::common(U16, I16), I32);
+        assert_eq!(NumType::common(U64, I8), I128);
+        assert_eq!(NumType::common(U128, I128), I128);
+        assert_eq!(NumType::common(I64, F32), F32);
+        assert_eq!(NumType::common(F32, F64), F64);
+
+        assert_eq!(
+            arith(Op::Add, Num::U8(200), Num::I16(100)),
+            Ok(Num::I16(300))
+        );
+        assert_eq!(
+            arith(Op::Mul, Num::I64(3), Num::F64(0.5)),
+            Ok(Num::F64(1.5))
+        );
+        assert_eq!(
+            arith(Op::Sub, Num::U8(1), Num::U8(2)),
+            Err(SlErrCode::ArithOverflow)
+        );
+        assert_eq!(
+            arith(Op::Add, Num::I64(i64::MAX), Num::I64(1)),
+            Err(SlErrCode::ArithOverflow)
+        );
+        assert_eq!(
+            arith(Op::Div, Num::I8(1), Num::I8(0)),
+            Err(SlErrCode::ArithDivByZero)
+        );
+        assert_eq!(arith(Op::Rem, Num::I64(-7), Num::I64(2)), Ok(Num::I64(-1)));
+        assert_eq!(neg(Num::U8(0)), Ok(Num::U8(0)));
+        assert_eq!(neg(Num::U8(1)), Err(SlErrCode::ArithOverflow));
+
+        assert_eq!(
+            compare(Num::U64(u64::MAX), Num::I8(-1)),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(compare(Num::I32(2), Num::F32(2.0)), Some(Ordering::Equal));
+        assert_eq!(compare(Num::F64(f64::NAN), Num::I32(0)), None);
+    }
+
+    #[test]
+    fn converts() {
+        assert_eq!(Num::F64(-3.9).to(NumType::I8), Some(Num::I8(-3)));
+        assert_eq!(Num::F64(255.5).to(NumType::U8), Some(Num::U8(255)));
+        assert_eq!(Num::F64(256.0).to(NumType::U8), None);
+        assert_eq!(Num::F64(f64::NAN).to(NumType::I64), None);
+        assert_eq!(Num::F64(1e300).to(NumType::F32), None);
+        assert_eq!(
+            Num::F64(f64::INFINITY).to(NumType::F32),
+            Some(Num::F32(f32::INFINITY))
+        );
+        assert_eq!(Num::I64(-1).to(NumType::U64), None);
+        assert_eq!(
+            Num::U128(u128::MAX).to(NumType::F64),
+            Some(Num::F64(u128::MAX as f64))
+        );
+        assert_eq!(Num::I16(300).to(NumType::I8), None);
+    }
+}
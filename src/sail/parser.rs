@@ -20,439 +20,933 @@
 
 // TODO: move to using &mut for the Region instead of *mut
 
-use super::{SlErrCode, Stab, core::*, memmgt};
+use super::{SlErrCode, Stab, core::*, memmgt, num};
 
-use std::iter;
+use std::collections::HashMap;
+use std::fmt;
 use std::str;
 
-// struct Parser {
-//     chars: iter::Peekable<str::Bytes<'static>>,
-//     acc: Vec<u8>,
-// }
+/// Place in the source text; lines and columns count from one, and
+/// columns count bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Pos {
+    pub line: u32,
+    pub col: u32,
+}
+
+/// Stretch of source text from which an object was read, up to but
+/// not including the end position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: Pos,
+    pub end: Pos,
+}
+
+/// Problem found in the source text, and where it was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseError {
+    pub code: SlErrCode,
+    pub pos: Pos,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?} at {}:{}", self.code, self.pos.line, self.pos.col)
+    }
+}
+
+/// Side table giving the span of every object read by the parser
+///
+/// Objects are known by address, so the table only holds while the
+/// parsed structure is kept alive.
+#[derive(Debug, Default)]
+pub struct SpanTable(HashMap<usize, Span>);
+
+impl SpanTable {
+    /// Finds the span of a parsed object, or of the object a
+    /// reference made to a parsed object refers to
+    pub fn locate(&self, obj: &SlHndl) -> Option<Span> {
+        if let Some(span) = self.0.get(&addr(obj)) {
+            return Some(*span);
+        }
+
+        if obj.clone().nnil_ref_p() {
+            let target = ref_get(obj.clone())?;
+            return self.0.get(&addr(&target)).copied();
+        }
+
+        None
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Gives the address by which an object is known in a span table
+fn addr(obj: &SlHndl) -> usize {
+    unsafe { obj.get_raw() as usize }
+}
+
+/// Gives the line or column number held by an object, if it holds one
+fn span_num(mut obj: SlHndl) -> Option<u32> {
+    if coretypp!(obj ; I64) {
+        u32::try_from(i64_get(obj)).ok()
+    } else {
+        None
+    }
+}
+
+/// Checks whether a byte may appear within a basic symbol
+fn symbol_char_p(c: u8) -> bool {
+    matches!(
+        c,
+        b'!' | b'&' | b'*' | b'+' | b'-' | b'/' | b'<' | b'=' | b'>' | b'?' | b'_'
+    ) || c.is_ascii_alphanumeric()
+}
+
+/// Checks whether a name reads back as the basic symbol it names,
+/// rather than as a number or anything else
+pub fn symbol_name_p(name: &[u8]) -> bool {
+    let lead = match name {
+        [b'+' | b'-', rest @ ..] => rest.first().is_none_or(|c| !c.is_ascii_digit()),
+        [c, ..] => {
+            matches!(c, b'&' | b'*' | b'/' | b'<' | b'=' | b'>' | b'_') || c.is_ascii_alphabetic()
+        }
+        [] => false,
+    };
+
+    lead && name.iter().all(|&c| symbol_char_p(c))
+}
 
 /// Parses a textual Sail expression into a structure of Sail objects
+///
+/// With `file` set, the text is read as a whole program, every
+/// expression of which is placed in a `do` form.
 pub fn parse(
     reg: *mut memmgt::Region,
     tbl: &mut Stab,
     code: &str,
     file: bool,
 ) -> Result<SlHndl, SlErrCode> {
-    // Accumulator for collecting string values
-    let mut acc: Vec<u8> = Vec::new();
+    parse_located(reg, tbl, code, file).map_err(|err| err.code)
+}
 
-    let chars = if file {
-        String::from("(do ") + code + ")"
-    } else {
-        code.to_string()
-    };
+/// Parses a textual Sail expression as `parse` does, giving where the
+/// first problem was found as well as what it was; no spans are kept
+pub fn parse_located(
+    reg: *mut memmgt::Region,
+    tbl: &mut Stab,
+    code: &str,
+    file: bool,
+) -> Result<SlHndl, ParseError> {
+    let mut parser = Parser::new(code, reg, tbl, false, false);
 
-    let mut cit = chars.bytes().peekable();
+    parser.read_all(file).map_err(|errs| errs[0])
+}
 
-    let val = read_value(&mut cit, &mut acc, reg, tbl)?;
+/// Parses a textual Sail expression as `parse` does, also giving the
+/// span of every object read
+///
+/// With `recover` set, reading continues past a malformed item to
+/// find any more problems; every problem found is returned, in order.
+pub fn parse_spanned(
+    reg: *mut memmgt::Region,
+    tbl: &mut Stab,
+    code: &str,
+    file: bool,
+    recover: bool,
+) -> Result<(SlHndl, SpanTable), Vec<ParseError>> {
+    let mut parser = Parser::new(code, reg, tbl, true, recover);
 
-    Ok(val)
+    let val = parser.read_all(file)?;
+    Ok((val, parser.spans))
 }
 
-// pub fn parse_bytes(tbl: *mut SlHead, code: &[u8]) -> Result<*mut SlHead, SlErrCode> {
-//     let mut acc: Vec<u8> = Vec::new();
-//     let mut chars = code.iter().peekable();
+/// Recursive descent parser state, over a single source text
+struct Parser<'a> {
+    text: &'a [u8],
+    /// Index of the next byte to read
+    at: usize,
+    pos: Pos,
 
-//     read_value(&mut chars, &mut acc, tbl, false)
-// }
+    /// Accumulator for collecting string values
+    acc: Vec<u8>,
 
-/// Returns the head of a Sail object structure representing a single
-/// item parsed from the input stream
-///
-/// This is a recursive descent parser; the appropriate reader can
-/// almost always be deduced from the first character
-fn read_value(
-    chars: &mut iter::Peekable<str::Bytes>,
-    acc: &mut Vec<u8>,
     reg: *mut memmgt::Region,
-    tbl: &mut Stab,
-) -> Result<SlHndl, SlErrCode> {
-    let value;
-
-    let mut c = *(chars.peek().ok_or(SlErrCode::ParseUnexpectedEnd)?);
-    while c.is_ascii_whitespace() || c == b';' {
-        if c == b';' {
-            while *(chars.peek().ok_or(SlErrCode::ParseUnexpectedEnd)?) != b'\n' {
-                chars.next();
-            }
-        }
-        chars.next();
-        c = *(chars.peek().ok_or(SlErrCode::ParseUnexpectedEnd)?);
-    }
-
-    match c {
-        b'\'' => {
-            chars.next();
-            value = read_quote(chars, acc, reg, tbl)?;
-        }
-        b'(' => {
-            chars.next();
-            value = read_list(chars, acc, reg, tbl)?;
-        }
-        b'[' => {
-            chars.next();
-            value = read_vec(chars, acc, reg, tbl)?;
-        }
-        b'{' => {
-            chars.next();
-            value = read_map(chars, acc, reg, tbl)?;
-        }
-        b':' => {
-            chars.next();
-            value = read_spec_sym(chars, acc, reg, tbl, SymbolMode::Keyword)?;
-            acc.clear();
-        }
-        b'$' => {
-            chars.next();
-            value = read_spec_sym(chars, acc, reg, tbl, SymbolMode::Type)?;
-            acc.clear();
-        }
-        b'@' => {
-            chars.next();
-            value = read_spec_sym(chars, acc, reg, tbl, SymbolMode::Module)?;
-            acc.clear();
-        }
-        b'"' => {
-            chars.next();
-            value = read_string(chars, acc, reg, tbl)?;
-            acc.clear();
-        }
-        b'#' => {
-            chars.next();
-            value = read_special(chars, acc, reg, tbl)?;
-            acc.clear();
-        }
-        b'+' | b'-' => {
-            acc.push(chars.next().unwrap());
-            if chars
-                .peek()
-                .ok_or(SlErrCode::ParseUnexpectedEnd)?
-                .is_ascii_digit()
-            {
-                value = read_number(chars, acc, reg, tbl)?;
+    tbl: &'a mut Stab,
+
+    /// Whether spans are recorded
+    spanned: bool,
+    spans: SpanTable,
+
+    /// Whether reading continues past malformed items
+    recover: bool,
+    errors: Vec<ParseError>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(
+        code: &'a str,
+        reg: *mut memmgt::Region,
+        tbl: &'a mut Stab,
+        spanned: bool,
+        recover: bool,
+    ) -> Self {
+        Self {
+            text: code.as_bytes(),
+            at: 0,
+            pos: Pos { line: 1, col: 1 },
+            acc: Vec::new(),
+            reg,
+            tbl,
+            spanned,
+            spans: SpanTable::default(),
+            recover,
+            errors: Vec::new(),
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.get(self.at).copied()
+    }
+
+    /// Looks at the next byte, which must exist
+    fn peek_some(&self) -> Result<u8, ParseError> {
+        self.peek().ok_or(self.fail(SlErrCode::ParseUnexpectedEnd))
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let c = self.peek()?;
+        self.at += 1;
+
+        if c == b'\n' {
+            self.pos.line += 1;
+            self.pos.col = 1;
+        } else {
+            self.pos.col += 1;
+        }
+
+        Some(c)
+    }
+
+    /// Gives the position where the current item began, when the given
+    /// number of its bytes, all on this line, have been read
+    fn item_start(&self, read: usize) -> Pos {
+        Pos {
+            line: self.pos.line,
+            col: self.pos.col - read as u32,
+        }
+    }
+
+    fn fail(&self, code: SlErrCode) -> ParseError {
+        ParseError {
+            code,
+            pos: self.pos,
+        }
+    }
+
+    /// Checks whether the next byte ends an item; the input ending
+    /// does too
+    fn at_delimiter(&self) -> bool {
+        match self.peek() {
+            Some(b')' | b']' | b'}') | None => true,
+            Some(c) => c.is_ascii_whitespace(),
+        }
+    }
+
+    /// Skips whitespace and comments, stopping at the end of input
+    fn skip_blank(&mut self) {
+        while let Some(c) = self.peek() {
+            if c == b';' {
+                while self.next().is_some_and(|c| c != b'\n') {}
+            } else if c.is_ascii_whitespace() {
+                self.next();
             } else {
-                value = read_symbol(chars, acc, reg, tbl)?;
+                break;
             }
-            acc.clear();
         }
-        b'*' | b'/' | b'<' | b'=' | b'>' | b'_' => {
-            value = read_symbol(chars, acc, reg, tbl)?;
-            acc.clear();
+    }
+
+    /// Skips the rest of a malformed item, so that reading may go on
+    /// from the next one
+    fn skip_item(&mut self, from: usize) {
+        // always move past the offending byte; a sequence checks for its
+        // own closing byte before reading an item, so this is not one
+        if self.at == from {
+            self.next();
         }
-        _ if c.is_ascii_alphabetic() => {
-            value = read_symbol(chars, acc, reg, tbl)?;
-            acc.clear();
+
+        while !self.at_delimiter() && !matches!(self.peek(), Some(b'(' | b'[' | b'{' | b'"' | b';'))
+        {
+            self.next();
         }
-        _ if c.is_ascii_digit() => {
-            value = read_number(chars, acc, reg, tbl)?;
-            acc.clear();
+    }
+
+    /// Reads the whole text, as a single expression or, for a file, as
+    /// a program; gives every problem found if reading fails
+    fn read_all(&mut self, file: bool) -> Result<SlHndl, Vec<ParseError>> {
+        let out = if file {
+            self.read_prog()
+        } else {
+            self.read_value()
+        };
+
+        if let Err(err) = out {
+            self.errors.push(err);
         }
-        _ => {
-            return Err(SlErrCode::ParseInvalidChar);
+
+        if self.errors.is_empty() {
+            Ok(out.unwrap())
+        } else {
+            self.errors.sort_by_key(|e| e.pos);
+            Err(std::mem::take(&mut self.errors))
         }
     }
-    Ok(value)
-}
 
-/// Reads a quoted expression off the input stream, into the
-/// appropriate object structure
-fn read_quote(
-    chars: &mut iter::Peekable<str::Bytes>,
-    acc: &mut Vec<u8>,
-    reg: *mut memmgt::Region,
-    tbl: &mut Stab,
-) -> Result<SlHndl, SlErrCode> {
-    let start = sym_init(reg, super::SP_QUOTE.0);
-    let head = ref_init(reg, start.clone());
+    /// Reads every expression in the text into a `do` form
+    fn read_prog(&mut self) -> Result<SlHndl, ParseError> {
+        let start = sym_init(self.reg, self.tbl.get_id(b"do"));
+        let head = ref_init(self.reg, start.clone());
+
+        let mut tail = start;
+        loop {
+            self.skip_blank();
+            if self.peek().is_none() {
+                break;
+            }
 
-    let end = read_value(chars, acc, reg, tbl)?;
-    unsafe {
-        inc_refc(end.get_raw());
-        set_next_list_elt_unsafe_unchecked(start, end);
+            if let Some(next) = self.read_item()? {
+                unsafe {
+                    inc_refc(next.get_raw());
+                    set_next_list_elt_unsafe_unchecked(tail, next.clone());
+                }
+                tail = next;
+            }
+        }
+
+        Ok(head)
     }
 
-    Ok(head)
-}
+    /// Reads one item of a sequence; when recovering, a malformed item
+    /// is noted and skipped, and gives nothing
+    fn read_item(&mut self) -> Result<Option<SlHndl>, ParseError> {
+        self.skip_blank();
+        let from = self.at;
+
+        match self.read_value() {
+            Ok(val) => Ok(Some(val)),
+            // nothing follows the end of input to recover with
+            Err(err) if self.recover && err.code != SlErrCode::ParseUnexpectedEnd => {
+                self.errors.push(err);
+                self.skip_item(from);
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
 
-/// Reads a list of values from the input stream and creates a
-/// corresponding list of Sail objects
-fn read_list(
-    chars: &mut iter::Peekable<str::Bytes>,
-    acc: &mut Vec<u8>,
-    reg: *mut memmgt::Region,
-    tbl: &mut Stab,
-) -> Result<SlHndl, SlErrCode> {
-    let head = ref_make(reg);
+    /// Returns the head of a Sail object structure representing a
+    /// single item parsed from the input stream
+    ///
+    /// This is a recursive descent parser; the appropriate reader can
+    /// almost always be deduced from the first character
+    fn read_value(&mut self) -> Result<SlHndl, ParseError> {
+        self.skip_blank();
+        // left over if the last item read was malformed
+        self.acc.clear();
+
+        let start = self.pos;
+        let c = self.peek_some()?;
+
+        let value = match c {
+            b'\'' => {
+                self.next();
+                self.read_quote(super::SP_QUOTE.0)?
+            }
+            b'`' => {
+                self.next();
+                self.read_quote(super::SP_QUASI.0)?
+            }
+            b',' => {
+                self.next();
+                if self.peek() == Some(b'@') {
+                    self.next();
+                    self.read_quote(super::SP_SPLICE.0)?
+                } else {
+                    self.read_quote(super::SP_UNQUOTE.0)?
+                }
+            }
+            b'(' => {
+                self.next();
+                self.read_list()?
+            }
+            b'[' => {
+                self.next();
+                self.read_vec()?
+            }
+            b'{' => {
+                self.next();
+                self.read_map()?
+            }
+            b':' => {
+                self.next();
+                self.read_spec_sym(SymbolMode::Keyword)?
+            }
+            b'$' => {
+                self.next();
+                self.read_spec_sym(SymbolMode::Type)?
+            }
+            b'@' => {
+                self.next();
+                self.read_spec_sym(SymbolMode::Module)?
+            }
+            b'"' => {
+                self.next();
+                self.read_string()?
+            }
+            b'#' => {
+                self.next();
+                self.read_special()?
+            }
+            b'+' | b'-' => {
+                let sign = self.next().unwrap();
+                self.acc.push(sign);
+                if self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                    self.read_number()?
+                } else {
+                    self.read_symbol()?
+                }
+            }
+            b'&' | b'*' | b'/' | b'<' | b'=' | b'>' | b'_' => self.read_symbol()?,
+            _ if c.is_ascii_alphabetic() => self.read_symbol()?,
+            _ if c.is_ascii_digit() => self.read_number()?,
+            _ => {
+                return Err(self.fail(SlErrCode::ParseInvalidChar));
+            }
+        };
+        self.acc.clear();
+
+        if self.spanned {
+            let span = Span {
+                start,
+                end: self.pos,
+            };
+            self.spans.0.insert(addr(&value), span);
+        }
+
+        Ok(value)
+    }
+
+    /// Reads a quoted expression off the input stream, into the
+    /// appropriate object structure; also serves quasiquote, unquote,
+    /// and splice syntax, according to the operator given
+    fn read_quote(&mut self, operator: u32) -> Result<SlHndl, ParseError> {
+        let start = sym_init(self.reg, operator);
+        let head = ref_init(self.reg, start.clone());
+
+        let end = self.read_value()?;
+        unsafe {
+            inc_refc(end.get_raw());
+            set_next_list_elt_unsafe_unchecked(start, end);
+        }
 
-    let mut c = *(chars.peek().ok_or(SlErrCode::ParseUnexpectedEnd)?);
-    if c == b')' {
-        chars.next();
-        return Ok(head);
+        Ok(head)
     }
 
-    let mut count = 0;
-    let mut tail = head.clone();
+    /// Reads a list of values from the input stream and creates a
+    /// corresponding list of Sail objects
+    fn read_list(&mut self) -> Result<SlHndl, ParseError> {
+        let head = ref_make(self.reg);
 
-    while c != b')' {
-        match c {
-            b';' => while chars.next().unwrap_or(b'\n') != b'\n' {},
-            _ if c.is_ascii_whitespace() => {
-                chars.next();
+        let mut count = 0;
+        let mut tail = head.clone();
+
+        loop {
+            self.skip_blank();
+            if self.peek_some()? == b')' {
+                break;
             }
-            _ => {
-                // append to the list tail
-                tail = {
-                    let next = read_value(chars, acc, reg, tbl)?;
-                    unsafe {
-                        inc_refc(next.get_raw());
-                        if count < 1 {
-                            write_ptr_unsafe_unchecked(tail, 0, next.clone())
-                        } else {
-                            set_next_list_elt_unsafe_unchecked(tail, next.clone())
-                        }
+
+            // append to the list tail
+            if let Some(next) = self.read_item()? {
+                unsafe {
+                    inc_refc(next.get_raw());
+                    if count < 1 {
+                        write_ptr_unsafe_unchecked(tail, 0, next.clone())
+                    } else {
+                        set_next_list_elt_unsafe_unchecked(tail, next.clone())
                     }
-                    next
-                };
+                }
+                tail = next;
 
                 count += 1;
             }
         }
 
-        c = *(chars.peek().ok_or(SlErrCode::ParseUnexpectedEnd)?);
+        self.next();
+        Ok(head)
     }
 
-    chars.next();
-    Ok(head)
-}
+    // TODO: lists may need to be evaluated even if they appear in a vec or map
+    // TODO: tighter parser-evaluator integration likely necessary for this & symbols
 
-// TODO: lists may need to be evaluated even if they appear in a vec or map
-// TODO: tighter parser-evaluator integration likely necessary for this & symbols
+    /// Reads a vector from the input stream and creates the
+    /// corresponding Sail object
+    fn read_vec(&mut self) -> Result<SlHndl, ParseError> {
+        let mut tvc = vec![];
 
-/// Reads a vector from the input stream and creates the corresponding
-/// Sail object
-fn read_vec(
-    chars: &mut iter::Peekable<str::Bytes>,
-    acc: &mut Vec<u8>,
-    reg: *mut memmgt::Region,
-    tbl: &mut Stab,
-) -> Result<SlHndl, SlErrCode> {
-    let mut tvc = vec![];
-    let mut c = *(chars.peek().ok_or(SlErrCode::ParseUnexpectedEnd)?);
-    while c != b']' {
-        match c {
-            b';' => while chars.next().unwrap_or(b'\n') != b'\n' {},
-            _ if c.is_ascii_whitespace() => {
-                chars.next();
+        loop {
+            self.skip_blank();
+            if self.peek_some()? == b']' {
+                break;
+            }
+
+            if let Some(next) = self.read_item()? {
+                tvc.push(next);
             }
-            _ => tvc.push(read_value(chars, acc, reg, tbl)?),
         }
-        c = *(chars.peek().ok_or(SlErrCode::ParseUnexpectedEnd)?);
+
+        let vec = stdvec_init(self.reg, &tvc.into_boxed_slice());
+        self.next();
+        Ok(vec)
     }
-    let vec = stdvec_init(reg, &tvc.into_boxed_slice());
-    chars.next();
-    Ok(vec)
-}
 
-/// Reads an associative map from the input stream and creates the
-/// corresponding Sail object
-fn read_map(
-    chars: &mut iter::Peekable<str::Bytes>,
-    acc: &mut Vec<u8>,
-    reg: *mut memmgt::Region,
-    tbl: &mut Stab,
-) -> Result<SlHndl, SlErrCode> {
-    let map = hashvec_make(reg, 16);
-    let mut c = *(chars.peek().ok_or(SlErrCode::ParseUnexpectedEnd)?);
-    while c != b'}' {
-        match c {
-            b';' => while chars.next().unwrap_or(b'\n') != b'\n' {},
-            _ if c.is_ascii_whitespace() => {
-                chars.next();
-            }
-            _ => hash_map_insert(
-                reg,
-                map.clone(),
-                read_value(chars, acc, reg, tbl)?,
-                read_value(chars, acc, reg, tbl)?,
-            ),
-        }
-        c = *(chars.peek().ok_or(SlErrCode::ParseUnexpectedEnd)?);
-    }
-    chars.next();
-    Ok(map)
-}
+    /// Reads an associative map from the input stream and creates the
+    /// corresponding Sail object
+    fn read_map(&mut self) -> Result<SlHndl, ParseError> {
+        let map = hashvec_make(self.reg, 16);
 
-/// Reads a basic symbol from the input stream and creates its Sail
-/// object
-fn read_symbol(
-    chars: &mut iter::Peekable<str::Bytes>,
-    acc: &mut Vec<u8>,
-    reg: *mut memmgt::Region,
-    tbl: &mut Stab,
-) -> Result<SlHndl, SlErrCode> {
-    while {
-        let peek = chars.peek().unwrap_or(&b' ');
-        match peek {
-            b')' | b']' | b'}' => false,
-            _ if peek.is_ascii_whitespace() => false,
-            _ => true,
-        }
-    } {
-        let next = chars.next().unwrap();
-        match next {
-            b'!' | b'*' | b'+' | b'-' | b'/' | b'<' | b'=' | b'>' | b'?' | b'_' => acc.push(next),
-            _ if next.is_ascii_alphanumeric() => acc.push(next),
-            _ => {
-                return Err(SlErrCode::ParseInvalidChar);
+        loop {
+            self.skip_blank();
+            if self.peek_some()? == b'}' {
+                break;
+            }
+
+            let key = self.read_item()?;
+
+            // every key needs a value
+            self.skip_blank();
+            let val = match self.peek_some()? {
+                b'}' => {
+                    let err = self.fail(SlErrCode::ParseInvalidChar);
+                    if !self.recover {
+                        return Err(err);
+                    }
+                    self.errors.push(err);
+                    None
+                }
+                _ => self.read_item()?,
+            };
+
+            if let (Some(key), Some(val)) = (key, val) {
+                hash_map_insert(self.reg, map.clone(), key, val);
             }
         }
+
+        self.next();
+        Ok(map)
     }
 
-    let sym = sym_init(reg, tbl.get_id(acc.as_slice()));
+    /// Collects the bytes of an item into the accumulator, up to the
+    /// next delimiter, so long as each is allowed
+    fn collect(&mut self, allowed: impl Fn(u8) -> bool) -> Result<(), ParseError> {
+        while !self.at_delimiter() {
+            let next = self.peek().unwrap();
+            if !allowed(next) {
+                return Err(self.fail(SlErrCode::ParseInvalidChar));
+            }
 
-    Ok(sym)
-}
+            self.acc.push(next);
+            self.next();
+        }
 
-/// Reads a specialized symbol from the input stream and creates its
-/// Sail object
-fn read_spec_sym(
-    chars: &mut iter::Peekable<str::Bytes>,
-    acc: &mut Vec<u8>,
-    reg: *mut memmgt::Region,
-    tbl: &mut Stab,
-    mode: SymbolMode,
-) -> Result<SlHndl, SlErrCode> {
-    while {
-        let peek = chars.peek().unwrap_or(&b' ');
-        match peek {
-            b')' | b']' | b'}' => false,
-            _ if peek.is_ascii_whitespace() => false,
-            _ => true,
-        }
-    } {
-        let next = chars.next().unwrap();
-        match next {
-            b'-' | b'_' => acc.push(next),
-            _ if next.is_ascii_alphanumeric() => acc.push(next),
-            _ => {
-                return Err(SlErrCode::ParseInvalidChar);
+        Ok(())
+    }
+
+    /// Reads a basic symbol from the input stream and creates its
+    /// Sail object
+    fn read_symbol(&mut self) -> Result<SlHndl, ParseError> {
+        self.collect(symbol_char_p)?;
+
+        Ok(sym_init(self.reg, self.tbl.get_id(self.acc.as_slice())))
+    }
+
+    /// Reads a specialized symbol from the input stream and creates
+    /// its Sail object
+    fn read_spec_sym(&mut self, mode: SymbolMode) -> Result<SlHndl, ParseError> {
+        let module = mode == SymbolMode::Module;
+
+        // a slash in a module name makes it a qualified name
+        self.collect(|c| {
+            c == b'-' || c == b'_' || c.is_ascii_alphanumeric() || (module && c == b'/')
+        })?;
+
+        if self.acc.is_empty() {
+            return Err(self.fail(SlErrCode::ParseUnexpectedEnd));
+        }
+
+        if let Some(slash) = self.acc.iter().position(|&c| c == b'/') {
+            return self.read_qualified(slash);
+        }
+
+        Ok(sym_init(
+            self.reg,
+            super::modeize_sym(self.tbl.get_id(self.acc.as_slice()), mode),
+        ))
+    }
+
+    /// Makes a qualified name, as in `@geom/line`, once it is in the
+    /// accumulator; the name is read as the list `(@geom line)`, which
+    /// the evaluator resolves within the module
+    fn read_qualified(&mut self, slash: usize) -> Result<SlHndl, ParseError> {
+        let (name, member) = self.acc.split_at(slash);
+        let member = &member[1..];
+
+        if name.is_empty() {
+            return Err(self.fail(SlErrCode::ParseInvalidChar));
+        }
+
+        match member.first() {
+            Some(c) if c.is_ascii_alphabetic() && !member.contains(&b'/') => (),
+            Some(_) => return Err(self.fail(SlErrCode::ParseInvalidChar)),
+            None => return Err(self.fail(SlErrCode::ParseUnexpectedEnd)),
+        }
+
+        let module = sym_init(
+            self.reg,
+            super::modeize_sym(self.tbl.get_id(name), SymbolMode::Module),
+        );
+        let member = sym_init(self.reg, self.tbl.get_id(member));
+        let head = ref_init(self.reg, module.clone());
+
+        unsafe {
+            inc_refc(member.get_raw());
+            set_next_list_elt_unsafe_unchecked(module, member);
+        }
+
+        Ok(head)
+    }
+
+    /// Reads a string from the input stream and creates its Sail
+    /// object
+    ///
+    /// A backslash escapes a quote or another backslash, and gives a
+    /// newline or a tab before `n` or `t`.
+    fn read_string(&mut self) -> Result<SlHndl, ParseError> {
+        let start = self.item_start(1);
+
+        while self.peek_some()? != b'"' {
+            let c = self.next().unwrap();
+            if c != b'\\' {
+                self.acc.push(c);
+                continue;
             }
+
+            let c = match self.peek_some()? {
+                b'"' => b'"',
+                b'\\' => b'\\',
+                b'n' => b'\n',
+                b't' => b'\t',
+                b'u' => {
+                    self.next();
+                    let c = self.read_code_point()?;
+                    self.acc
+                        .extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                    continue;
+                }
+                _ => return Err(self.fail(SlErrCode::ParseInvalidString)),
+            };
+            self.acc.push(c);
+            self.next();
+        }
+
+        self.next();
+
+        match str::from_utf8(&self.acc) {
+            Ok(s) => Ok(string_init(self.reg, s)),
+            _ => Err(ParseError {
+                code: SlErrCode::ParseInvalidString,
+                pos: start,
+            }),
         }
     }
 
-    if acc.is_empty() {
-        return Err(SlErrCode::ParseUnexpectedEnd);
+    /// Reads the braced hexadecimal code point of a `\u{..}` escape
+    /// within a string, as the printer writes control characters
+    fn read_code_point(&mut self) -> Result<char, ParseError> {
+        if self.next() != Some(b'{') {
+            return Err(self.fail(SlErrCode::ParseInvalidString));
+        }
+
+        let mut code = 0u32;
+        let mut digits = 0;
+        loop {
+            match self.next() {
+                Some(b'}') if digits > 0 => break,
+                Some(c) if c.is_ascii_hexdigit() && digits < 6 => {
+                    code = code * 16 + (c as char).to_digit(16).unwrap();
+                    digits += 1;
+                }
+                _ => return Err(self.fail(SlErrCode::ParseInvalidString)),
+            }
+        }
+
+        char::from_u32(code).ok_or_else(|| self.fail(SlErrCode::ParseInvalidString))
     }
 
-    let sym = sym_init(reg, super::modeize_sym(tbl.get_id(acc.as_slice()), mode));
+    /// Reads a number from the input stream and creates its Sail
+    /// object
+    fn read_number(&mut self) -> Result<SlHndl, ParseError> {
+        let start = self.item_start(self.acc.len());
 
-    Ok(sym)
-}
+        self.collect(|c| matches!(c, b'+' | b'-' | b'_' | b'.') || c.is_ascii_alphanumeric())?;
 
-/// Reads a string from the input stream and creates its Sail object
-fn read_string(
-    chars: &mut iter::Peekable<str::Bytes>,
-    acc: &mut Vec<u8>,
-    reg: *mut memmgt::Region,
-    _tbl: &mut Stab,
-) -> Result<SlHndl, SlErrCode> {
-    let mut next = *(chars.peek().ok_or(SlErrCode::ParseUnexpectedEnd)?);
-    while next != b'"' {
-        acc.push(chars.next().unwrap());
-        next = *(chars.peek().ok_or(SlErrCode::ParseUnexpectedEnd)?);
+        match num::read_num(unsafe { str::from_utf8_unchecked(&self.acc) }) {
+            Some(n) => Ok(n.init(self.reg)),
+            None => Err(ParseError {
+                code: SlErrCode::ParseInvalidNum,
+                pos: start,
+            }),
+        }
     }
 
-    chars.next();
+    /// Reads a special item from the input stream and creates a Sail
+    /// object if appropriate
+    fn read_special(&mut self) -> Result<SlHndl, ParseError> {
+        let start = self.item_start(1);
 
-    let string = string_init(
-        reg,
-        match str::from_utf8(acc) {
-            Ok(s) => s,
-            _ => return Err(SlErrCode::ParseInvalidString),
-        },
-    );
+        match self.peek() {
+            Some(b'\\') => {
+                self.next();
+                return self.read_char(start);
+            }
+            // objects printed in angle brackets cannot be read back
+            Some(b'<') => {
+                return Err(ParseError {
+                    code: SlErrCode::ParseUnreadable,
+                    pos: start,
+                });
+            }
+            _ => (),
+        }
 
-    Ok(string)
-}
+        while let Some(c) = self.peek() {
+            if !(c == b'_' || c == b'-' || c.is_ascii_alphanumeric()) {
+                break;
+            }
+            self.acc.push(c);
+            self.next();
+        }
 
-/// Reads a number from the input stream and creates its Sail object
-fn read_number(
-    chars: &mut iter::Peekable<str::Bytes>,
-    acc: &mut Vec<u8>,
-    reg: *mut memmgt::Region,
-    _tbl: &mut Stab,
-) -> Result<SlHndl, SlErrCode> {
-    while {
-        let peek = chars.peek().unwrap_or(&b' ');
-        match peek {
-            b')' | b']' | b'}' => false,
-            _ if peek.is_ascii_whitespace() => false,
-            _ => true,
-        }
-    } {
-        let next = chars.next().unwrap();
-        match next {
-            b'+' | b'-' | b'_' | b'.' => acc.push(next),
-            _ if next.is_ascii_alphanumeric() => acc.push(next),
-            _ => {
-                return Err(SlErrCode::ParseInvalidChar);
+        // a name may open an array or an error
+        match self.peek() {
+            Some(b'[') => {
+                self.next();
+                return self.read_arr(start);
+            }
+            Some(b'(') if self.acc == b"err" => {
+                self.next();
+                return self.read_err(start);
             }
+            _ if !self.at_delimiter() => return Err(self.fail(SlErrCode::ParseInvalidChar)),
+            _ => (),
+        }
+
+        if self.acc.is_empty() {
+            return Err(self.fail(SlErrCode::ParseUnexpectedEnd));
+        }
+
+        let name = self.acc.strip_prefix(b"-").unwrap_or(&self.acc);
+
+        if self.acc[0].eq_ignore_ascii_case(&b't') && self.acc.len() == 1 {
+            Ok(bool_init(self.reg, true))
+        } else if self.acc[0].eq_ignore_ascii_case(&b'f') && self.acc.len() == 1 {
+            Ok(bool_init(self.reg, false))
+        } else if let Some(n) = (name.starts_with(b"inf") || name.starts_with(b"nan"))
+            .then(|| num::read_num(unsafe { str::from_utf8_unchecked(&self.acc) }))
+            .flatten()
+        {
+            Ok(n.init(self.reg))
+        } else {
+            Err(ParseError {
+                code: SlErrCode::ParseBadSpecial,
+                pos: start,
+            })
         }
     }
-    process_num(unsafe { str::from_utf8_unchecked(acc) }, reg)
-}
 
-/// Reads a special item from the input stream and creates a Sail
-/// object if appropriate
-fn read_special(
-    chars: &mut iter::Peekable<str::Bytes>,
-    acc: &mut Vec<u8>,
-    reg: *mut memmgt::Region,
-    _tbl: &mut Stab,
-) -> Result<SlHndl, SlErrCode> {
-    while {
-        let peek = chars.peek().unwrap_or(&b' ');
-        match peek {
-            b')' | b']' | b'}' => false,
-            _ if peek.is_ascii_whitespace() => false,
-            _ => true,
-        }
-    } {
-        let next = chars.next().unwrap();
-        match next {
-            b'_' => acc.push(next),
-            _ if next.is_ascii_alphanumeric() => acc.push(next),
-            _ => {
-                return Err(SlErrCode::ParseInvalidChar);
+    /// Reads an array of numbers, as in `#u8[1 2 3]`, once the type
+    /// name is in the accumulator, and creates its Sail object
+    fn read_arr(&mut self, start: Pos) -> Result<SlHndl, ParseError> {
+        let bad = ParseError {
+            code: SlErrCode::ParseBadSpecial,
+            pos: start,
+        };
+        let typ =
+            num::NumType::of_suffix(unsafe { str::from_utf8_unchecked(&self.acc) }).ok_or(bad)?;
+
+        let mut vals = vec![];
+
+        loop {
+            self.skip_blank();
+            if self.peek_some()? == b']' {
+                break;
+            }
+
+            self.acc.clear();
+            let elt = self.pos;
+            self.collect(|c| {
+                matches!(c, b'#' | b'+' | b'-' | b'_' | b'.') || c.is_ascii_alphanumeric()
+            })?;
+
+            // elements are bare literals of the array's type
+            let text = unsafe { str::from_utf8_unchecked(&self.acc) };
+            match num::read_num_as(text.strip_prefix('#').unwrap_or(text), typ) {
+                Some(n) if n.typ() == typ => vals.push(n),
+                _ => {
+                    return Err(ParseError {
+                        code: SlErrCode::ParseInvalidNum,
+                        pos: elt,
+                    });
+                }
             }
         }
-    }
 
-    if acc.is_empty() {
-        return Err(SlErrCode::ParseUnexpectedEnd);
+        self.next();
+        super::arr::init(self.reg, typ, &vals).map_err(|_| bad)
     }
 
-    if acc[0].eq_ignore_ascii_case(&b't') && acc.len() == 1 {
-        Ok(bool_init(reg, true))
-    } else if acc[0].eq_ignore_ascii_case(&b'f') && acc.len() == 1 {
-        Ok(bool_init(reg, false))
-    } else {
-        Err(SlErrCode::ParseBadSpecial)
+    /// Reads an error, as in `#err(TypeMismatch "message" :form (f x))`,
+    /// and creates its Sail object
+    ///
+    /// An error code alone gives a bare error code. A full error has a
+    /// message, then optionally the form at fault after `:form` and
+    /// where the error occurred after `:at`: a file name, a line and
+    /// column, or both.
+    fn read_err(&mut self, start: Pos) -> Result<SlHndl, ParseError> {
+        let bad = ParseError {
+            code: SlErrCode::ParseBadSpecial,
+            pos: start,
+        };
+
+        let mut name = self.read_value()?;
+        if !coretypp!(name ; Symbol) {
+            return Err(bad);
+        }
+        let name = self.tbl.lookup_by_id(sym_get_id(name)).unwrap_or_default();
+        let code = (0..)
+            .map_while(|c| SlErrCode::try_from(c).ok())
+            .find(|c| format!("{:?}", c).as_bytes() == name)
+            .ok_or(bad)?;
+
+        self.skip_blank();
+        if self.peek_some()? == b')' {
+            self.next();
+            return Ok(super::errcode_init(self.reg, code));
+        }
+
+        let mut msg = self.read_value()?;
+        if !coretypp!(msg ; VecStr) {
+            return Err(bad);
+        }
+        let err = unsafe { super::err_init(self.reg, code, string_get(msg.clone()), None) };
+
+        let form_kw = super::modeize_sym(self.tbl.get_id(b"form"), SymbolMode::Keyword);
+        let at_kw = super::modeize_sym(self.tbl.get_id(b"at"), SymbolMode::Keyword);
+
+        loop {
+            self.skip_blank();
+            if self.peek_some()? == b')' {
+                break;
+            }
+
+            let mut field = self.read_value()?;
+            if !coretypp!(field ; Symbol) {
+                return Err(bad);
+            }
+
+            match sym_get_id(field) {
+                id if id == form_kw => {
+                    let form = self.read_value()?;
+                    unsafe {
+                        inc_refc(form.get_raw());
+                        write_ptr_unsafe_unchecked(err.clone(), PTR_LEN, form);
+                    }
+                }
+                id if id == at_kw => {
+                    let mut place = self.read_value()?;
+                    let file = coretypp!(place ; VecStr).then(|| place.clone());
+
+                    // a file may stand alone, but a line needs a column
+                    let line = match file {
+                        Some(_) => {
+                            self.skip_blank();
+                            match self.peek_some()? {
+                                c if c.is_ascii_digit() => Some(self.read_value()?),
+                                _ => None,
+                            }
+                        }
+                        None => Some(place),
+                    };
+                    let (line, col) = match line {
+                        Some(line) => (
+                            span_num(line).ok_or(bad)?,
+                            span_num(self.read_value()?).ok_or(bad)?,
+                        ),
+                        None => (0, 0),
+                    };
+
+                    super::err_set_span(err.clone(), file, line, col);
+                }
+                _ => return Err(bad),
+            }
+        }
+
+        self.next();
+        Ok(err)
     }
-}
 
-/// Parses a number and creates an object according to its textual
-/// representation
-fn process_num(slice: &str, reg: *mut memmgt::Region) -> Result<SlHndl, SlErrCode> {
-    if let Ok(n) = slice.parse::<i64>() {
-        Ok(i64_init(reg, n))
-    } else if let Ok(n) = slice.parse::<f64>() {
-        Ok(f64_init(reg, n))
-    } else {
-        Err(SlErrCode::ParseInvalidNum)
+    /// Reads a character from the input stream, given directly or by
+    /// name after `#\`, and creates its Sail object
+    fn read_char(&mut self, start: Pos) -> Result<SlHndl, ParseError> {
+        let first = self.peek_some()?;
+        self.acc.push(first);
+        self.next();
+
+        // the remaining bytes of a multibyte character, or of a name
+        while let Some(c) = self.peek() {
+            if c & 0xC0 != 0x80 && !(first.is_ascii_alphabetic() && c.is_ascii_alphanumeric()) {
+                break;
+            }
+            self.acc.push(c);
+            self.next();
+        }
+
+        if !self.at_delimiter() {
+            return Err(self.fail(SlErrCode::ParseInvalidChar));
+        }
+
+        let text = str::from_utf8(&self.acc).unwrap_or_default();
+        let mut chars = text.chars();
+
+        let val = match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(c),
+            _ => [' ', '\n', '\t']
+                .into_iter()
+                .find(|&c| super::char_name(c) == Some(text)),
+        };
+
+        match val {
+            Some(c) => Ok(unsafe { super::char_init(self.reg, c) }),
+            None => Err(ParseError {
+                code: SlErrCode::ParseBadSpecial,
+                pos: start,
+            }),
+        }
     }
 }
@@ -22,8 +22,10 @@
 // copies down that channel.
 
 use std::ptr;
+use std::sync::atomic::{AtomicPtr, Ordering, fence};
+use std::time::Instant;
 
-use super::{core::*, memmgt};
+use super::{core::*, memmgt, thread::ThreadHull};
 
 #[derive(Clone, Copy, PartialEq)]
 #[repr(transparent)]
@@ -55,6 +57,8 @@ pub struct Inlet {
     // - no resize handling
     head: Qptr,
     tail: Qptr,
+    // thread to wake at the next transmission, if it is waiting
+    sleeper: AtomicPtr<ThreadHull>,
     // TODO: maintain a stack of existing nodes for reuse
     // TODO: keep atomic reference count of senders
 
@@ -76,6 +80,16 @@ impl Drop for Inlet {
     }
 }
 
+/// Outcome of a blocking receive
+pub enum Receipt {
+    /// A message arrived, from the given sender
+    Msg(usize, SlHndl),
+    /// The thread is blocked, and should try again when woken
+    Waiting,
+    /// The deadline passed with no message
+    Expired,
+}
+
 struct QueueNode {
     source: usize,
     payload: Option<SlHndl>,
@@ -95,6 +109,7 @@ impl Inlet {
 
             head: Qptr::make(dummy_node, 0),
             tail: Qptr::make(dummy_node, 0),
+            sleeper: AtomicPtr::new(ptr::null_mut()),
         }
     }
 
@@ -144,6 +159,83 @@ impl Inlet {
         }
 
         // Core queue algorithm from (Michael & Scott, 1998)
+
+        // pairs with the fence in watch, so that either the receiver
+        // sees this message or this sees the receiver waiting
+        fence(Ordering::SeqCst);
+
+        if let Some(thr) = unsafe {
+            self.sleeper
+                .swap(ptr::null_mut(), Ordering::AcqRel)
+                .as_ref()
+        } {
+            thr.wake()
+        }
+    }
+
+    /// Checks whether a message is waiting, without taking it
+    pub fn pending_p(&self) -> bool {
+        unsafe {
+            let head = atom_ops::load(&self.head);
+            !atom_ops::load(&(*head.get().0).next).get().0.is_null()
+        }
+    }
+
+    /// Arranges for `waiter` to be woken by the next transmission;
+    /// returns false, arranging nothing, if a message is waiting
+    pub fn watch(&self, waiter: *mut ThreadHull) -> bool {
+        self.sleeper.store(waiter, Ordering::Release);
+        fence(Ordering::SeqCst);
+
+        if self.pending_p() {
+            self.sleeper.store(ptr::null_mut(), Ordering::Release);
+            false
+        } else {
+            true
+        }
+    }
+
+    /// Receives on behalf of a Sail thread, blocking it until a message
+    /// arrives or the deadline passes; the OS thread running it is
+    /// never held up, so the receive must be attempted again on waking
+    ///
+    /// # Safety
+    ///
+    /// `thr` must point to a live thread, which the caller is running.
+    pub unsafe fn receive_blocking(
+        &mut self,
+        thr: *mut ThreadHull,
+        deadline: Option<Instant>,
+    ) -> Receipt {
+        if let (id, Some(msg)) = self.receive() {
+            return Receipt::Msg(id, msg);
+        }
+
+        if deadline.is_some_and(|d| d <= Instant::now()) {
+            return Receipt::Expired;
+        }
+
+        if !self.watch(thr) {
+            let (id, msg) = self.receive();
+            return Receipt::Msg(id, msg.unwrap());
+        }
+
+        if let Some(d) = deadline {
+            super::thread::wake_at(thr, d);
+        }
+
+        unsafe { (*thr).block() };
+        Receipt::Waiting
+    }
+
+    /// Withdraws `waiter` from waiting on this inlet, if it still is
+    pub fn unwatch(&self, waiter: *mut ThreadHull) {
+        let _ = self.sleeper.compare_exchange(
+            waiter,
+            ptr::null_mut(),
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        );
     }
 
     pub fn receive(&mut self) -> (usize, Option<SlHndl>) {
@@ -189,6 +281,43 @@ impl Inlet {
     }
 }
 
+/// Receives from the first of several inlets to hold a message,
+/// blocking the thread until one does; gives the index of that inlet
+/// along with the sender and message, or nothing if the thread blocked
+///
+/// # Safety
+///
+/// `thr` must point to a live thread, which the caller is running, and
+/// every one of `inlets` to a live inlet.
+pub unsafe fn select_blocking(
+    inlets: &[*mut Inlet],
+    thr: *mut ThreadHull,
+) -> Option<(usize, usize, SlHndl)> {
+    let mut inlets: Vec<&mut Inlet> = inlets.iter().map(|i| unsafe { &mut **i }).collect();
+
+    let mut found = None;
+    for (idx, inlet) in inlets.iter().enumerate() {
+        if !inlet.watch(thr) {
+            found = Some(idx);
+            break;
+        }
+    }
+
+    match found {
+        Some(idx) => {
+            // the other inlets need not wake the thread any longer
+            inlets.iter().for_each(|i| i.unwatch(thr));
+
+            let (from, msg) = inlets[idx].receive();
+            Some((idx, from, msg.unwrap()))
+        }
+        None => {
+            unsafe { (*thr).block() };
+            None
+        }
+    }
+}
+
 mod atom_ops {
     use super::Qptr;
 
@@ -0,0 +1,621 @@
+// STARK, a system for computer augmented design.
+
+// SPDX-FileCopyrightText: © 2021 Matthew Rothlisberger
+// SPDX-License-Identifier: AGPL-3.0-only
+
+// STARK is licensed under the terms of the GNU Affero General Public
+// License version 3. See the top-level LICENSES directory for the
+// license text.
+
+// Find full copyright information in the top-level COPYRIGHT file.
+
+// <>
+
+// src/sail/serial.rs
+
+// Serialization of Sail structures to a compact binary blob. The
+// structure under a root is walked just as for a copy between regions;
+// each object is written with its links given by number, so shared
+// substructure and cycles are rebuilt as they were, and symbols are
+// written by name, so a blob may be read into any symbol table.
+
+// <>
+
+use super::core::*;
+use super::num::NumType;
+use super::{SlErrCode, Stab, StructureGraph, memmgt};
+
+use std::collections::{HashMap, HashSet};
+use std::ptr;
+
+/// Reason serialization failed, with a message for the error
+pub type SerialErr = (SlErrCode, &'static str);
+
+/// Opens every blob
+const MAGIC: &[u8; 4] = b"SAIL";
+
+/// Version of the blob layout; raised whenever the layout changes
+pub const VERSION: u16 = 2;
+
+/// Fails reading a blob
+fn bad<T>(msg: &'static str) -> Result<T, SerialErr> {
+    Err((SlErrCode::ParseBadBlob, msg))
+}
+
+/// Checks whether an object may be written to a blob; processes,
+/// environments and handles make sense only in the running system
+fn portable_p(loc: *mut SlHead) -> bool {
+    use CoreType::*;
+    match raw_core_type(loc) {
+        Some(
+            Bool | U8 | I8 | U16 | I16 | U32 | I32 | U64 | I64 | U128 | I128 | F32 | F64 | Symbol
+            | Ref | ErrCode | ErrObj | VecStd | VecStr | VecArr | VecHash,
+        ) => true,
+        Some(_) => false,
+        None => raw_typ_fld_p(loc) && raw_type_id(loc) == super::T_CHAR_ID.0,
+    }
+}
+
+/// Gives the offset in an object's value of the symbol it holds, if
+/// any: the symbol itself, or the element type of an array
+fn sym_slot(loc: *mut SlHead) -> Option<usize> {
+    match raw_core_type(loc) {
+        Some(CoreType::Symbol) | Some(CoreType::VecArr) => Some(0),
+        _ => None,
+    }
+}
+
+/// Gives the offset in an object's value of the error code it holds,
+/// if any
+fn code_slot(loc: *mut SlHead) -> Option<u32> {
+    match raw_core_type(loc) {
+        Some(CoreType::ErrCode) => Some(0),
+        Some(CoreType::ErrObj) => Some(super::ERR_CODE_OFS),
+        _ => None,
+    }
+}
+
+/// Finds the error code with the given name
+fn code_of_name(name: &[u8]) -> Option<SlErrCode> {
+    (0..)
+        .map_while(|c| SlErrCode::try_from(c).ok())
+        .find(|c| format!("{:?}", c).as_bytes() == name)
+}
+
+/// Reads a field of an object's value
+fn field<T>(loc: *mut SlHead, ofs: u32) -> T {
+    unsafe { ptr::read_unaligned(raw_val_ptr(loc).add(ofs as usize) as *const T) }
+}
+
+/// Writes a field of an object's value
+fn set_field<T>(loc: *mut SlHead, ofs: u32, val: T) {
+    unsafe { ptr::write_unaligned(raw_val_ptr(loc).add(ofs as usize) as *mut T, val) }
+}
+
+/// Clears every pointer slot of an object's value, including the
+/// slots of a vector beyond its length
+fn clear_slots(loc: *mut SlHead) {
+    for ofs in discern_ref_offsets_core(loc) {
+        set_field(loc, ofs, ptr::null_mut::<SlHead>());
+    }
+
+    if raw_core_type(loc) == Some(CoreType::VecStd) {
+        for i in field::<u32>(loc, 4)..field::<u32>(loc, 0) {
+            set_field(loc, PTR_LEN * (i + 1), ptr::null_mut::<SlHead>());
+        }
+    }
+}
+
+/// Number of bytes of an object after its head: the type and size
+/// fields, where present, and the value
+fn body_len(loc: *mut SlHead) -> usize {
+    raw_val_ptr(loc) as usize - loc as usize - HEAD_LEN as usize + raw_size(loc) as usize
+}
+
+/// Writes the structure under a root object to a blob
+///
+/// The blob opens with `SAIL` and the layout version (u16), then
+/// lists the names of the symbols used (count u32, then each name's
+/// length u32 and bytes), then the names of the error codes used in
+/// the same way, then the objects, the root first (count u32). Each
+/// object is its configuration byte, its length u32 and the bytes
+/// after its head, as they lie in memory but with pointers cleared,
+/// and symbols and error codes replaced by their place in the name
+/// lists; then
+/// the number of its next element plus one (zero for none), and the
+/// count u32 of its references, each an offset u32 into its value and
+/// the number of the object referred to. Counts and lengths are little
+/// endian; values keep the byte order of memory, which is little
+/// endian on every platform STARK runs on.
+pub fn serialize(tbl: &Stab, root: SlHndl) -> Result<Vec<u8>, SerialErr> {
+    let graph = super::structure_graph(unsafe { root.get_raw() });
+
+    let mut names: Vec<&[u8]> = vec![];
+    let mut name_idx: HashMap<u32, u32> = HashMap::new();
+
+    // error codes are written by name, so that their numbering may
+    // change without changing what a blob means
+    let mut codes: Vec<String> = vec![];
+
+    let mut objs = vec![];
+    objs.extend((graph.ptrs.len() as u32).to_le_bytes());
+
+    // objects are staged in an aligned buffer while their pointers
+    // are cleared and their symbols renumbered
+    let mut stage: Vec<u64> = vec![];
+
+    for (i, &loc) in graph.ptrs.iter().enumerate() {
+        let arr_p = raw_core_type(loc) == Some(CoreType::VecArr);
+        if !portable_p(loc) || (arr_p && NumType::of_sym(field(loc, 0)).is_none()) {
+            return Err((SlErrCode::TypeInvalid, "object cannot be serialized"));
+        }
+
+        let len = HEAD_LEN as usize + body_len(loc);
+        stage.clear();
+        stage.resize(len.div_ceil(8), 0);
+        let copy = stage.as_mut_ptr() as *mut SlHead;
+        unsafe { ptr::copy_nonoverlapping(loc as *const u8, copy as *mut u8, len) };
+
+        clear_slots(copy);
+
+        if let Some(ofs) = sym_slot(copy) {
+            let id: u32 = field(copy, ofs as u32);
+            let base = demodes_sym(id);
+            let idx = match name_idx.get(&base) {
+                Some(idx) => *idx,
+                None => {
+                    let Some(name) = tbl.lookup_by_id(base) else {
+                        return Err((SlErrCode::TypeInvalid, "symbol has no name"));
+                    };
+                    names.push(name);
+                    name_idx.insert(base, names.len() as u32 - 1);
+                    names.len() as u32 - 1
+                }
+            };
+            set_field(copy, ofs as u32, modeize_sym(idx, mode_of_sym(id)));
+        }
+
+        if let Some(ofs) = code_slot(copy) {
+            let Ok(code) = SlErrCode::try_from(field::<u16>(copy, ofs)) else {
+                return Err((SlErrCode::TypeInvalid, "invalid error code"));
+            };
+            let name = format!("{:?}", code);
+            let idx = match codes.iter().position(|c| *c == name) {
+                Some(idx) => idx,
+                None => {
+                    codes.push(name);
+                    codes.len() - 1
+                }
+            };
+            set_field(copy, ofs, idx as u16);
+        }
+
+        let bytes = unsafe { std::slice::from_raw_parts(copy as *const u8, len) };
+        objs.push(bytes[0]);
+        objs.extend((body_len(copy) as u32).to_le_bytes());
+        objs.extend(&bytes[HEAD_LEN as usize..]);
+
+        objs.extend(graph.nexts[i].map_or(0, |n| n + 1).to_le_bytes());
+        objs.extend((graph.refs[i].len() as u32).to_le_bytes());
+        for (ofs, id) in &graph.refs[i] {
+            objs.extend(ofs.to_le_bytes());
+            objs.extend(id.to_le_bytes());
+        }
+    }
+
+    let mut out = MAGIC.to_vec();
+    out.extend(VERSION.to_le_bytes());
+    out.extend((names.len() as u32).to_le_bytes());
+    for name in names {
+        out.extend((name.len() as u32).to_le_bytes());
+        out.extend(name);
+    }
+    out.extend((codes.len() as u32).to_le_bytes());
+    for name in codes {
+        out.extend((name.len() as u32).to_le_bytes());
+        out.extend(name.as_bytes());
+    }
+    out.extend(objs);
+
+    Ok(out)
+}
+
+/// Reads a blob front to back
+struct Input<'a> {
+    blob: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Input<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], SerialErr> {
+        if self.blob.len() - self.pos < n {
+            return bad("blob ends early");
+        }
+        self.pos += n;
+        Ok(&self.blob[self.pos - n..self.pos])
+    }
+
+    fn u8(&mut self) -> Result<u8, SerialErr> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, SerialErr> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, SerialErr> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+}
+
+/// Checks that the fields of an object agree with its size, and that
+/// it holds nothing the rest of the system could not cope with
+fn layout_ok(loc: *mut SlHead) -> bool {
+    let size = raw_size(loc) as u64;
+    let vec_fits = |elt: u64| size >= 8 && size == 8 + elt * field::<u32>(loc, 0) as u64;
+
+    match raw_core_type(loc) {
+        Some(CoreType::VecStd) => vec_fits(PTR_LEN as u64) && field::<u32>(loc, 4) <= field(loc, 0),
+        Some(CoreType::VecStr) => {
+            let len = field::<u32>(loc, 4);
+            vec_fits(1)
+                && len <= field(loc, 0)
+                && std::str::from_utf8(unsafe {
+                    std::slice::from_raw_parts(raw_val_ptr(loc).add(8), len as usize)
+                })
+                .is_ok()
+        }
+        Some(CoreType::VecHash) => vec_fits(PTR_LEN as u64),
+        // the element type is checked once its name is known
+        Some(CoreType::VecArr) => size >= 8,
+        // the error code is checked once its name is known
+        Some(CoreType::ErrObj) => size == super::ERR_SIZE as u64,
+        Some(_) => true,
+        None => size == 4 && char::from_u32(field(loc, 0)).is_some(),
+    }
+}
+
+/// Reads a blob written by `serialize` into a region, returning the
+/// root object; every symbol named in the blob is entered into the
+/// symbol table
+///
+/// The blob is checked in full before anything is allocated, so that
+/// a damaged or hostile blob fails without side effects.
+pub fn deserialize(
+    reg: *mut memmgt::Region,
+    tbl: &mut Stab,
+    blob: &[u8],
+) -> Result<SlHndl, SerialErr> {
+    let mut input = Input { blob, pos: 0 };
+
+    if input.take(4).ok() != Some(&MAGIC[..]) {
+        return bad("not a Sail blob");
+    }
+    if input.u16()? != VERSION {
+        return bad("unsupported blob version");
+    }
+
+    let mut names = vec![];
+    for _ in 0..input.u32()? {
+        let len = input.u32()? as usize;
+        names.push(input.take(len)?);
+    }
+
+    let mut codes = vec![];
+    for _ in 0..input.u32()? {
+        let len = input.u32()? as usize;
+        match code_of_name(input.take(len)?) {
+            Some(code) => codes.push(code),
+            None => return bad("unknown error code"),
+        }
+    }
+
+    let count = input.u32()?;
+    if count == 0 {
+        return bad("blob holds no objects");
+    }
+
+    // each object is rebuilt in an aligned buffer, from which it is
+    // copied into the region once all are known to be sound
+    let mut bufs: Vec<Vec<u64>> = vec![];
+    let mut graph = StructureGraph {
+        ptrs: vec![],
+        nexts: vec![],
+        refs: vec![],
+        rcs: vec![],
+    };
+
+    for _ in 0..count {
+        let cfg = input.u8()?;
+        let len = input.u32()? as usize;
+        let body = input.take(len)?;
+
+        if Cfg::try_from(cfg).is_err() {
+            return bad("invalid object configuration");
+        }
+
+        let mut buf = vec![0u64; (HEAD_LEN as usize + len).div_ceil(8)];
+        let loc = buf.as_mut_ptr() as *mut SlHead;
+        unsafe {
+            ptr::write(loc as *mut u8, cfg);
+            ptr::copy_nonoverlapping(body.as_ptr(), (loc as *mut u8).add(HEAD_LEN as usize), len);
+        }
+
+        let fields = (raw_typ_fld_p(loc) as usize + raw_siz_fld_p(loc) as usize) * 4;
+        if len < fields || len != body_len(loc) {
+            return bad("object length does not match its head");
+        }
+        if !portable_p(loc) || !layout_ok(loc) {
+            return bad("invalid object");
+        }
+
+        if let Some(ofs) = sym_slot(loc) {
+            let id: u32 = field(loc, ofs as u32);
+            let Some(name) = names.get(demodes_sym(id) as usize) else {
+                return bad("symbol out of range");
+            };
+
+            if raw_core_type(loc) == Some(CoreType::VecArr) {
+                let typ = std::str::from_utf8(name).ok().and_then(NumType::of_suffix);
+                let fits = typ.is_some_and(|t| {
+                    id >> 30 == t.sym() >> 30
+                        && raw_size(loc) as u64
+                            == 8 + (t.bits() / 8) as u64 * field::<u32>(loc, 4) as u64
+                });
+                if !fits {
+                    return bad("invalid array");
+                }
+            }
+        }
+
+        if let Some(ofs) = code_slot(loc) {
+            let Some(code) = codes.get(field::<u16>(loc, ofs) as usize) else {
+                return bad("error code out of range");
+            };
+            set_field(loc, ofs, *code as u16);
+        }
+
+        clear_slots(loc);
+
+        let next = input.u32()?;
+        if next > count {
+            return bad("link out of range");
+        }
+        graph.nexts.push(next.checked_sub(1));
+
+        let slots = discern_ref_offsets_core(loc);
+        let mut refs: Vec<(u32, u32)> = vec![];
+        for _ in 0..input.u32()? {
+            let (ofs, id) = (input.u32()?, input.u32()?);
+            if id >= count || !slots.contains(&ofs) || refs.iter().any(|(o, _)| *o == ofs) {
+                return bad("link out of range");
+            }
+            refs.push((ofs, id));
+        }
+        graph.refs.push(refs);
+
+        graph.ptrs.push(loc);
+        bufs.push(buf);
+    }
+
+    if input.pos != blob.len() {
+        return bad("blob runs on past its objects");
+    }
+
+    let cfg_of = |id: u32| raw_cfg_spec(graph.ptrs[id as usize]);
+    let target = |i: usize, ofs: u32| {
+        graph.refs[i]
+            .iter()
+            .find_map(|(o, id)| (*o == ofs).then_some(*id))
+    };
+
+    // maps and errors are used without further checks, so must be
+    // shaped as the system builds them
+    let mut entries = HashSet::new();
+    for (i, &loc) in graph.ptrs.iter().enumerate() {
+        match raw_core_type(loc) {
+            Some(CoreType::VecHash) => {
+                for (_, first) in &graph.refs[i] {
+                    let mut entry = Some(*first);
+                    while let Some(e) = entry {
+                        // the key is followed by a reference to the value
+                        let cell = target(e as usize, 0).and_then(|k| graph.nexts[k as usize]);
+                        let sound = cfg_of(e) == Cfg::B8Ptr
+                            && entries.insert(e)
+                            && cell.is_some_and(|c| {
+                                cfg_of(c) == Cfg::B8Ptr && target(c as usize, 0).is_some()
+                            });
+                        if !sound {
+                            return bad("invalid map entry");
+                        }
+                        entry = graph.nexts[e as usize];
+                    }
+                }
+            }
+            Some(CoreType::ErrObj) => {
+                let msg = target(i, 0).map(cfg_of);
+                let file = target(i, 2 * PTR_LEN).map(cfg_of);
+                if msg != Some(Cfg::VecStr) || file.is_some_and(|c| c != Cfg::VecStr) {
+                    return bad("invalid error object");
+                }
+            }
+            _ => (),
+        }
+    }
+
+    // every object is linked to, and the root is held by the caller
+    graph.rcs = vec![0; count as usize];
+    graph.rcs[0] = 1;
+    for i in 0..count as usize {
+        for id in graph.nexts[i]
+            .iter()
+            .chain(graph.refs[i].iter().map(|(_, id)| id))
+        {
+            graph.rcs[*id as usize] += 1;
+        }
+    }
+    if graph.rcs.contains(&0) {
+        return bad("blob holds an unlinked object");
+    }
+
+    // only now, with the blob known sound, are its symbols entered
+    let ids: Vec<u32> = names.iter().map(|name| tbl.get_id(name)).collect();
+    for &loc in &graph.ptrs {
+        if let Some(ofs) = sym_slot(loc) {
+            let id: u32 = field(loc, ofs as u32);
+            set_field(
+                loc,
+                ofs as u32,
+                modeize_sym(ids[demodes_sym(id) as usize], mode_of_sym(id)),
+            );
+        }
+    }
+
+    let new_ptrs = super::structure_build(reg, &graph);
+    drop(bufs);
+
+    // keys hash by symbol number, which differs between tables
+    for &loc in &new_ptrs {
+        if raw_core_type(loc) == Some(CoreType::VecHash) {
+            hash_map_rehash(unsafe { SlHndl::from_raw(loc) }.unwrap());
+        }
+    }
+
+    Ok(unsafe { SlHndl::from_raw_unchecked(new_ptrs[0]) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{SYM_ARRAY, context, parser};
+    use super::*;
+
+    /// Creates a symbol table holding the builtin symbols, then some
+    /// others, so that later symbols number differently per table
+    fn table(extra: &[&str]) -> Stab {
+        let mut tbl = Stab::new(51);
+        for s in SYM_ARRAY.iter().chain(extra) {
+            tbl.get_id(s.as_bytes());
+        }
+        tbl
+    }
+
+    #[test]
+    fn round_trips() {
+        let (reg, mut tbl) = (memmgt::Region::acq(1000), table(&[]));
+        let (dst, mut other) = (memmgt::Region::acq(1000), table(&["x", "y", "z"]));
+
+        let text = concat!(
+            r#"[(foo :bar $baz @qux) "line\n" #\λ 7u8 -2.5 #T #err(EvalArgCount) "#,
+            r#"#f64[1.0 2.5] #i16[-3 4] {:only [1 2]} "#,
+            r#"#err(TypeInvalid "bad" :form (f 1) :at "a.sl" 3 4)]"#
+        );
+        let obj = parser::parse(reg, &mut tbl, text, false).unwrap();
+
+        let blob = serialize(&tbl, obj.clone()).unwrap();
+        let back = deserialize(dst, &mut other, &blob).unwrap();
+
+        assert_eq!(
+            context(&tbl, obj).to_string(),
+            context(&other, back).to_string()
+        );
+    }
+
+    #[test]
+    fn rehashes_maps() {
+        let (reg, mut tbl) = (memmgt::Region::acq(1000), table(&[]));
+        let (dst, mut other) = (memmgt::Region::acq(1000), table(&["p", "q", "r"]));
+
+        let map = hashvec_make(reg, 4);
+        for (i, name) in ["a", "b", "c", "d", "e", "f", "g"].iter().enumerate() {
+            let key = sym_init(reg, tbl.get_id(name.as_bytes()));
+            hash_map_insert(reg, map.clone(), key, i64_init(reg, i as i64));
+        }
+        hash_map_insert(reg, map.clone(), string_init(reg, "h"), i64_init(reg, 7));
+
+        let back = deserialize(dst, &mut other, &serialize(&tbl, map).unwrap()).unwrap();
+
+        assert_eq!(hash_map_len(back.clone()), 8);
+        for (i, name) in ["a", "b", "c", "d", "e", "f", "g"].iter().enumerate() {
+            let key = sym_init(dst, other.get_id(name.as_bytes()));
+            let val = hash_map_lookup(back.clone(), key).unwrap();
+            assert_eq!(i64_get(val), i as i64);
+        }
+        let val = hash_map_lookup(back, string_init(dst, "h")).unwrap();
+        assert_eq!(i64_get(val), 7);
+    }
+
+    #[test]
+    fn keeps_sharing_and_cycles() {
+        let (reg, tbl) = (memmgt::Region::acq(1000), table(&[]));
+        let (dst, mut other) = (memmgt::Region::acq(1000), table(&[]));
+
+        let item = string_init(reg, "shared");
+        let vec = stdvec_make(reg, 4);
+        stdvec_push(vec.clone(), item.clone());
+        stdvec_push(vec.clone(), item);
+        stdvec_push(vec.clone(), vec.clone());
+
+        let back = deserialize(dst, &mut other, &serialize(&tbl, vec).unwrap()).unwrap();
+
+        assert_eq!(stdvec_get_len(back.clone()), 3);
+        unsafe {
+            let fst = stdvec_idx(back.clone(), 0).get_raw();
+            assert_eq!(fst, stdvec_idx(back.clone(), 1).get_raw());
+            assert_eq!(refc_total(fst), 2);
+            assert_eq!(stdvec_idx(back.clone(), 2).get_raw(), back.get_raw());
+        }
+        assert_eq!(string_get(stdvec_idx(back, 0)), "shared");
+    }
+
+    #[test]
+    fn refuses_bad_input() {
+        let (reg, mut tbl) = (memmgt::Region::acq(1000), table(&[]));
+        let dst = memmgt::Region::acq(1000);
+
+        let syms = super::super::arrvec_init(reg, super::super::T_SYMBOL.0, 1, &[0u32]);
+        assert_eq!(serialize(&tbl, syms).unwrap_err().0, SlErrCode::TypeInvalid);
+
+        let obj = parser::parse(
+            reg,
+            &mut tbl,
+            "[(a b) {:k \"v\"} #err(Raised \"m\")]",
+            false,
+        )
+        .unwrap();
+        let blob = serialize(&tbl, obj).unwrap();
+
+        for len in 0..blob.len() {
+            let err = deserialize(dst, &mut tbl, &blob[..len]).unwrap_err();
+            assert_eq!(err.0, SlErrCode::ParseBadBlob);
+        }
+
+        let mut long = blob.clone();
+        long.push(0);
+        assert!(deserialize(dst, &mut tbl, &long).is_err());
+
+        let mut newer = blob.clone();
+        newer[4] += 1;
+        assert!(deserialize(dst, &mut tbl, &newer).is_err());
+
+        // error codes are written by name, and unknown names refused
+        let at = blob.windows(6).position(|w| w == b"Raised").unwrap();
+        let mut unknown = blob.clone();
+        unknown[at..at + 6].copy_from_slice(b"Rained");
+        assert_eq!(
+            deserialize(dst, &mut tbl, &unknown).unwrap_err(),
+            (SlErrCode::ParseBadBlob, "unknown error code")
+        );
+
+        // damage anywhere is either refused or reads as some sound
+        // structure, never as one that breaks the system
+        for i in 0..blob.len() {
+            for flip in [0x01, 0x80, 0xFF] {
+                let mut bent = blob.clone();
+                bent[i] ^= flip;
+                if let Ok(out) = deserialize(dst, &mut tbl, &bent) {
+                    let _ = serialize(&tbl, out).unwrap();
+                }
+            }
+        }
+    }
+}
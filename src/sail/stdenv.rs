@@ -18,9 +18,16 @@
 
 // <>
 
-use super::{core::*, memmgt};
+use super::{arr, core::*, num, queue::Receipt};
 
-// TODO: type checks and variable length arglists for native functions
+use std::cmp;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+/// Counter for generated symbol names, shared by all threads
+static GENSYM_COUNT: AtomicU32 = AtomicU32::new(0);
+
+// TODO: variable length arglists for native functions
 // TODO: generate these functions somehow else if macros won't cut it
 
 /// Generates a slice of native Sail function pointers along with
@@ -79,6 +86,39 @@ macro_rules! sail_fn {
     };
 }
 
+/// Raises a Sail error from the body of a native procedure, returning
+/// early; the offending form is optional
+#[macro_export]
+macro_rules! sail_raise {
+    ( $thr:ident, $code:ident, $msg:expr ) => {{
+        let (thr, msg) = ($thr, $msg);
+        let code = crate::sail::SlErrCode::$code;
+        return unsafe { crate::sail::raise_err(thr, code, msg, None) };
+    }};
+
+    ( $thr:ident, $code:ident, $msg:expr, $form:expr ) => {{
+        let (thr, msg, form) = ($thr, $msg, $form);
+        let code = crate::sail::SlErrCode::$code;
+        return unsafe { crate::sail::raise_err(thr, code, msg, Some(form)) };
+    }};
+}
+
+/// Core type check for native procedure arguments, raising a Sail
+/// error rather than panicking
+#[macro_export]
+macro_rules! sail_typck {
+    ( $thr:ident, $var:ident ; $typ:ident ) => {
+        if !coretypp!($var ; $typ) {
+            sail_raise!(
+                $thr,
+                TypeInvalid,
+                concat!("`", stringify!($var), "` must be of type ", stringify!($typ)),
+                $var.clone()
+            );
+        }
+    };
+}
+
 // TODO: native functions MUST be fully safe to use
 // TODO: sensible type checking & operator overloading
 sail_fn! {
@@ -88,121 +128,46 @@ sail_fn! {
     // TODO: use fixed point at times to avoid floating point errors?
 
     "+" [fst, snd] {
-        let typ = fst.core_type();
-        assert_eq!(typ, snd.core_type());
-
-        let reg = unsafe { (*_thr).region() };
-
-        match typ.expect("type invalid") {
-            CoreType::I64 => {
-                let out = i64_make(reg);
-                let result = i64_get(fst) + i64_get(snd);
-                i64_set(out.clone(), result);
-                return out;
-            }
-            CoreType::F32 => {
-                let out = f32_make(reg);
-                let result = f32_get(fst) + f32_get(snd);
-                f32_set(out.clone(), result);
-                return out;
-            }
-            _ => panic!("type invalid for add"),
-        }
+        return arith(_thr, num::Op::Add, fst, snd);
     }
 
     "-" [fst, snd] {
-        let typ = fst.core_type();
-        assert_eq!(typ, snd.core_type());
-
-        let reg = unsafe { (*_thr).region() };
-
-        match typ.expect("type invalid") {
-            CoreType::I64 => {
-                let out = i64_make(reg);
-                let result = i64_get(fst) - i64_get(snd);
-                i64_set(out.clone(), result);
-                return out;
-            }
-            CoreType::F32 => {
-                let out = f32_make(reg);
-                let result = f32_get(fst) - f32_get(snd);
-                f32_set(out.clone(), result);
-                return out;
-            }
-            _ => panic!("type invalid for sub"),
-        }
+        return arith(_thr, num::Op::Sub, fst, snd);
     }
 
     "*" [fst, snd] {
-        let typ = fst.core_type();
-        assert_eq!(typ, snd.core_type());
-
-        let reg = unsafe { (*_thr).region() };
-
-        match typ.expect("type invalid") {
-            CoreType::I64 => {
-                let out = i64_make(reg);
-                let result = i64_get(fst) * i64_get(snd);
-                i64_set(out.clone(), result);
-                return out;
-            }
-            CoreType::F32 => {
-                let out = f32_make(reg);
-                let result = f32_get(fst) * f32_get(snd);
-                f32_set(out.clone(), result);
-                return out;
-            }
-            _ => panic!("type invalid for mul"),
-        }
+        return arith(_thr, num::Op::Mul, fst, snd);
     }
 
     "/" [fst, snd] {
-        let typ = fst.core_type();
-        assert_eq!(typ, snd.core_type());
-
-        let reg = unsafe { (*_thr).region() };
-
-        match typ.expect("type invalid") {
-            CoreType::I64 => {
-                let out = i64_make(reg);
-                let result = i64_get(fst) / i64_get(snd);
-                i64_set(out.clone(), result);
-                return out;
-            }
-            CoreType::F32 => {
-                let out = f32_make(reg);
-                let result = f32_get(fst) / f32_get(snd);
-                f32_set(out.clone(), result);
-                return out;
-            }
-            _ => panic!("type invalid for div"),
-        }
+        return arith(_thr, num::Op::Div, fst, snd);
     }
 
     "mod" [fst, snd] {
-        let reg = unsafe { (*_thr).region() };
-
-        let out = i64_make(reg);
-        let result = i64_get(fst) % i64_get(snd);
-        i64_set(out.clone(), result);
-        return out;
+        return arith(_thr, num::Op::Rem, fst, snd);
     }
 
     "neg" [val] {
+        let Some(n) = num::Num::of(&val) else {
+            sail_raise!(_thr, TypeInvalid, "type invalid for neg", val);
+        };
+
         let reg = unsafe { (*_thr).region() };
-        match val.core_type().expect("type invalid") {
-            CoreType::I64 => {
-                return i64_init(reg, -i64_get(val));
-            }
-            CoreType::F32 => {
-                return f32_init(reg, -f32_get(val));
-            }
-            _ => panic!("type invalid for div"),
+        match num::neg(n) {
+            Ok(out) => out.init(reg),
+            Err(code) => unsafe { super::raise_err(_thr, code, "negation overflowed", Some(val)) },
         }
     }
 
     "=" [fst, snd] {
-        let result = i64_get(fst) == i64_get(snd);
+        let Some(a) = num::Num::of(&fst) else {
+            sail_raise!(_thr, TypeInvalid, "`fst` must be a number", fst);
+        };
+        let Some(b) = num::Num::of(&snd) else {
+            sail_raise!(_thr, TypeInvalid, "`snd` must be a number", snd);
+        };
+
+        let result = num::compare(a, b) == Some(std::cmp::Ordering::Equal);
         if result {
             env_lookup_by_id(_env, super::S_T_INTERN.0).unwrap()
         } else {
@@ -233,73 +198,210 @@ sail_fn! {
         // return out;
     }
 
-    // TODO: and and or as macros or, more easily, special forms
-    "and" [lhs, rhs] {
-        if lhs.truthy() && rhs.truthy() {
-            env_lookup_by_id(_env, super::S_T_INTERN.0).unwrap()
-        } else {
-            env_lookup_by_id(_env, super::S_F_INTERN.0).unwrap()
+    "raise" [val] {
+        if coretypp!(val ; ErrObj) {
+            unsafe { (*_thr).raise(val.clone()) };
+            return val;
+        }
+
+        if coretypp!(val ; VecStr) {
+            let msg = string_get(val.clone());
+            sail_raise!(_thr, Raised, msg);
         }
+
+        sail_raise!(_thr, Raised, "value raised", val)
     }
 
-    "own-tx-hdl" [] {
+    "err-msg" [err] {
+        sail_typck!(_thr, err ; ErrObj);
+
+        let reg = unsafe { (*_thr).region() };
+        string_init(reg, super::err_get_msg(err))
+    }
+
+    "err-form" [err] {
+        sail_typck!(_thr, err ; ErrObj);
+
+        let reg = unsafe { (*_thr).region() };
+        match super::err_get_form(err) {
+            Some(form) => form,
+            None => ref_make(reg),
+        }
+    }
+
+    "err-code" [err] {
+        sail_typck!(_thr, err ; ErrObj);
+
         let reg = unsafe { (*_thr).region() };
+        let tbl = unsafe { ((*_thr).context()).symtab() };
+
+        super::debug_keyword(reg, tbl, super::err_get_code(err))
+    }
 
-        let tq = unsafe { (*_thr).queue_inlet() };
+    "own-tx-hdl" [] {
+        let reg = unsafe { (*_thr).region() };
 
-        super::warp_hdl_init(reg, tq)
+        super::warp_hdl_init(reg, unsafe { (*_thr).id }, None)
     }
 
     "th-spawn" [fun] {
+        sail_typck!(_thr, fun ; ProcLambda);
+
         let reg = unsafe { (*_thr).region() };
         let new = unsafe { (*_thr).spawn(None, None) };
 
+        // the new thread evaluates the procedure within its own top
+        // environment, so the one it closes over is not copied
+        let detached = unsafe { list_elt_copy(reg, fun.clone()) };
+        proc_lambda_set_env(detached.clone(), None);
+
         let nr = unsafe { (*new).region() };
         let to_apply = unsafe {
-            SlHndl::from_raw_unchecked(super::structure_copy(nr, fun))
+            SlHndl::from_raw_unchecked(super::structure_copy(nr, detached))
         };
 
         unsafe { (*new).load_proc_immed(to_apply) };
 
+        let send_tgt = super::warp_hdl_init(reg, unsafe { (*new).id }, None);
+        let new_hdl = super::thread_ref_init(reg, new);
+
         if !unsafe { (*new).attempt_start() } {
-            panic!()
+            sail_raise!(_thr, ThreadStartFailed, "spawned thread could not start", fun);
         }
 
-        let nq = unsafe { (*new).queue_inlet() };
-
-        let send_tgt = super::warp_hdl_init(reg, nq);
-        let new_hdl = super::thread_ref_init(reg, new);
-
         set_next_list_elt(_env, new_hdl.clone(), send_tgt);
 
         new_hdl
     }
 
     "th-join" [thref] {
+        if let Err(err) = thread_ref_arg(_thr, thref.clone()) {
+            return err;
+        }
+
+        match super::thread_ref_join(_thr, thref.clone()) {
+            super::Joined::Ended(Some(res)) => res,
+            super::Joined::Ended(None) => env_lookup_by_id(_env, super::S_F_INTERN.0).unwrap(),
+            super::Joined::Waiting => env_lookup_by_id(_env, super::S_F_INTERN.0).unwrap(),
+            super::Joined::Gone => {
+                sail_raise!(_thr, ThreadNotFound, "thread is gone", thref)
+            }
+        }
+    }
+
+    "th-kill" [thref] {
+        match thread_ref_arg(_thr, thref) {
+            Ok(id) => on_thread(_thr, _env, id, |t| t.kill()),
+            Err(err) => err,
+        }
+    }
+
+    "th-interrupt" [thref] {
+        match thread_ref_arg(_thr, thref) {
+            Ok(id) => on_thread(_thr, _env, id, |t| t.interrupt()),
+            Err(err) => err,
+        }
+    }
+
+    "th-priority" [thref, prio] {
+        let id = match thread_ref_arg(_thr, thref) {
+            Ok(id) => id,
+            Err(err) => return err,
+        };
+        sail_typck!(_thr, prio ; I64);
+
+        let p = i64_get(prio.clone());
+        if !(1..=super::thread::PRIORITY_MAX as i64).contains(&p) {
+            sail_raise!(_thr, IndexOutOfRange, "priority must be from 1 to 8", prio);
+        }
+
+        on_thread(_thr, _env, id, |t| t.set_priority(p as u8))
+    }
+
+    "th-budget" [thref, steps] {
+        let id = match thread_ref_arg(_thr, thref) {
+            Ok(id) => id,
+            Err(err) => return err,
+        };
+        sail_typck!(_thr, steps ; I64);
+
+        let n = i64_get(steps.clone());
+        if n < 0 {
+            sail_raise!(_thr, IndexOutOfRange, "step budget must not be negative", steps);
+        }
+
+        on_thread(_thr, _env, id, |t| t.set_budget(n as u64))
+    }
+
+    "th-status" [thref] {
+        let id = match thread_ref_arg(_thr, thref) {
+            Ok(id) => id,
+            Err(err) => return err,
+        };
+
         let reg = unsafe { (*_thr).region() };
-        super::thread_ref_join(reg, thref)
+        let tbl = unsafe { ((*_thr).context()).symtab() };
+
+        let status = unsafe { (*_thr).weft() }.status_of(id);
+
+        super::debug_keyword(reg, tbl, status)
     }
 
-    "th-id" [thref] {
-        assert_eq!(thref.type_id(), super::T_THR_REF_ID.0);
+    "th-monitor" [thref] {
+        let id = match thread_ref_arg(_thr, thref) {
+            Ok(id) => id,
+            Err(err) => return err,
+        };
+
+        let reg = unsafe { (*_thr).region() };
+        let me = unsafe { (*_thr).id };
+
+        // a thread which has already ended is reported on at once
+        let notice = unsafe { (*_thr).weft() }.with_thread(id, |t| {
+            if t.monitor(me) { None } else { Some(t.end_notice(reg)) }
+        });
+
+        let notice = match notice {
+            Some(n) => n,
+            None => {
+                let (status, res) = unsafe { (*_thr).weft() }
+                    .kept_outcome(id, reg)
+                    .unwrap_or((super::thread::ThreadStatus::Gone, None));
+
+                let n = super::debug_keyword(reg, unsafe { ((*_thr).context()).symtab() }, status);
+                if let Some(r) = res {
+                    set_next_list_elt(_env.clone(), n.clone(), r);
+                }
+                Some(n)
+            }
+        };
 
-        let addrs: u64 = read_field(thref, 0);
+        if let Some(n) = notice {
+            unsafe { (*(*_thr).queue_inlet()).transmit(id, n) };
+        }
+
+        env_lookup_by_id(_env, super::S_T_INTERN.0).unwrap()
+    }
 
-        let th_ref = unsafe {
-            (addrs as *mut super::thread::ThreadHull)
-                .as_mut()
-                .expect("null thread reference")
+    "th-id" [thref] {
+        let id = match thread_ref_arg(_thr, thref) {
+            Ok(id) => id,
+            Err(err) => return err,
         };
 
         let reg = unsafe { (*_thr).region() };
 
-        super::i64_init(reg, th_ref.id as _)
+        super::i64_init(reg, id as _)
     }
 
     "qtx" [sender, item] {
-        let id = unsafe { (*_thr).id };
-        super::warp_hdl_send(sender, item, id);
-        env_lookup_by_id(_env, super::S_T_INTERN.0).unwrap()
+        sail_typck!(_thr, sender ; WarpHdl);
+
+        if super::warp_hdl_send(_thr, sender, item) {
+            env_lookup_by_id(_env, super::S_T_INTERN.0).unwrap()
+        } else {
+            env_lookup_by_id(_env, super::S_F_INTERN.0).unwrap()
+        }
     }
 
     "qrx" [] {
@@ -318,6 +420,103 @@ sail_fn! {
         }
     }
 
+    "qrx-wait" [] {
+        let reg = unsafe { (*_thr).region() };
+        let inlet = unsafe { (*_thr).queue_inlet() };
+
+        match unsafe { (&mut *inlet).receive_blocking(_thr, None) } {
+            Receipt::Msg(from, r) => {
+                let id = i64_init(reg, from as _);
+                set_next_list_elt(_env, id.clone(), r);
+                id
+            }
+            _ => env_lookup_by_id(_env, super::S_F_INTERN.0).unwrap(),
+        }
+    }
+
+    "qrx-timeout" [ms] {
+        sail_typck!(_thr, ms ; I64);
+
+        let wait = i64_get(ms.clone());
+        if wait < 0 {
+            sail_raise!(_thr, IndexOutOfRange, "timeout must not be negative", ms);
+        }
+
+        let reg = unsafe { (*_thr).region() };
+        let inlet = unsafe { (*_thr).queue_inlet() };
+
+        let deadline = unsafe { (*_thr).deadline_after(Duration::from_millis(wait as _)) };
+
+        match unsafe { (&mut *inlet).receive_blocking(_thr, Some(deadline)) } {
+            Receipt::Msg(from, r) => {
+                let id = i64_init(reg, from as _);
+                set_next_list_elt(_env, id.clone(), r);
+                id
+            }
+            _ => env_lookup_by_id(_env, super::S_F_INTERN.0).unwrap(),
+        }
+    }
+
+    "chan-open" [name] {
+        sail_typck!(_thr, name ; Symbol);
+
+        let reg = unsafe { (*_thr).region() };
+        let id = sym_get_id(name);
+
+        // opened now, so that sends are never lost
+        unsafe { (*_thr).channel(id) };
+
+        super::warp_hdl_init(reg, unsafe { (*_thr).id }, Some(id))
+    }
+
+    "chan-rx" [name] {
+        sail_typck!(_thr, name ; Symbol);
+
+        let reg = unsafe { (*_thr).region() };
+        let inlet = unsafe { (*_thr).channel(sym_get_id(name)) };
+
+        let msg = unsafe { (&mut *inlet).receive() };
+
+        match msg.1 {
+            Some(r) => {
+                let id = i64_init(reg, msg.0 as _);
+                set_next_list_elt(_env, id.clone(), r);
+                id
+            }
+            None => env_lookup_by_id(_env, super::S_F_INTERN.0).unwrap(),
+        }
+    }
+
+    "select" [names] {
+        sail_typck!(_thr, names ; VecStd);
+
+        let reg = unsafe { (*_thr).region() };
+
+        let len = stdvec_get_len(names.clone());
+        if len == 0 {
+            sail_raise!(_thr, IndexOutOfRange, "no channels to select from", names);
+        }
+
+        let mut inlets = Vec::with_capacity(len as usize);
+        for i in 0..len {
+            let mut name = stdvec_idx(names.clone(), i);
+            sail_typck!(_thr, name ; Symbol);
+            inlets.push(unsafe { (*_thr).channel(sym_get_id(name)) });
+        }
+
+        match unsafe { super::queue::select_blocking(&inlets, _thr) } {
+            Some((idx, from, r)) => {
+                let name = stdvec_idx(names, idx as _);
+                let fired = sym_init(reg, sym_get_id(name));
+                let id = i64_init(reg, from as _);
+                set_next_list_elt(_env.clone(), fired.clone(), id.clone());
+                set_next_list_elt(_env, id, r);
+                fired
+            }
+            None => env_lookup_by_id(_env, super::S_F_INTERN.0).unwrap(),
+        }
+    }
+
     "rest" [loc] {
         match get_next_list_elt(loc) {
             Some(h) => h,
@@ -330,51 +529,113 @@ sail_fn! {
         fst
     }
 
-    "as-f32" [val] {
+    "first" [list] {
+        sail_typck!(_thr, list ; Ref);
+        match ref_get(list.clone()) {
+            Some(h) => h,
+            None => sail_raise!(_thr, IndexOutOfRange, "empty list has no first element", list),
+        }
+    }
+
+    "tail" [list] {
+        sail_typck!(_thr, list ; Ref);
+
         let reg = unsafe { (*_thr).region() };
-        return f32_init(reg, f64_get(val) as f32);
+        match ref_get(list.clone()).and_then(get_next_list_elt) {
+            Some(h) => ref_init(reg, h),
+            None => ref_make(reg),
+        }
     }
 
-    "arr-vec-make" [typ, len, init] {
-        coretypck!(typ ; Symbol);
-        coretypck!(len ; I64);
+    "gensym" [] {
+        let reg = unsafe { (*_thr).region() };
 
-        let typ = sym_get_id(typ);
-        let len = i64_get(len) as u32;
+        // uninterned, so that making many never fills the symbol table
+        let n = GENSYM_COUNT.fetch_add(1, Ordering::Relaxed) % GENSYM_SPAN;
+        sym_init(reg, GENSYM_TOP - n)
+    }
 
-        assert!(temp_base_sized_p(typ));
-        // assert_eq!(typ, super::get_self_type(init));
+    "as-u8" [val] {
+        return convert(_thr, val, num::NumType::U8);
+    }
 
-        let reg = unsafe { (*_thr).region() };
+    "as-i8" [val] {
+        return convert(_thr, val, num::NumType::I8);
+    }
 
-        unsafe {
-            let size = vec_size(8, temp_get_size(typ), len);
-            let mut out = SlHndl::from_raw_unchecked(memmgt::alloc(reg, size, memmgt::cap(Cfg::VecArr)));
+    "as-u16" [val] {
+        return convert(_thr, val, num::NumType::U16);
+    }
 
-            write_field_unchecked::<u32>(out.clone(), 0, typ);
-            write_field_unchecked::<u32>(out.clone(), 4, len);
+    "as-i16" [val] {
+        return convert(_thr, val, num::NumType::I16);
+    }
 
-            for i in 0..len {
-                std::ptr::copy_nonoverlapping(
-                    init.value_ptr(),
-                    out.value_ptr().add(8 + (temp_get_size(typ) * i) as usize),
-                    temp_get_size(typ) as usize,
-                )
-            }
+    "as-u32" [val] {
+        return convert(_thr, val, num::NumType::U32);
+    }
 
-            return out;
-        }
+    "as-i32" [val] {
+        return convert(_thr, val, num::NumType::I32);
+    }
+
+    "as-u64" [val] {
+        return convert(_thr, val, num::NumType::U64);
+    }
+
+    "as-i64" [val] {
+        return convert(_thr, val, num::NumType::I64);
+    }
+
+    "as-u128" [val] {
+        return convert(_thr, val, num::NumType::U128);
+    }
+
+    "as-i128" [val] {
+        return convert(_thr, val, num::NumType::I128);
+    }
+
+    "as-f32" [val] {
+        return convert(_thr, val, num::NumType::F32);
+    }
+
+    "as-f64" [val] {
+        return convert(_thr, val, num::NumType::F64);
+    }
+
+    "arr-vec-make" [typ, len, init] {
+        sail_typck!(_thr, typ ; Symbol);
+        sail_typck!(_thr, len ; I64);
+
+        let Ok(n) = u32::try_from(i64_get(len.clone())) else {
+            sail_raise!(_thr, IndexOutOfRange, "array length must not be negative", len);
+        };
 
+        let reg = unsafe { (*_thr).region() };
+        return arr_out(_thr, arr::fill(reg, sym_get_id(typ), n, init.clone()), init);
+    }
+
+    "arr-vec-len" [target] {
+        sail_typck!(_thr, target ; VecArr);
+
+        let reg = unsafe { (*_thr).region() };
+        super::i64_init(reg, super::arrvec_get_len(target) as _)
     }
 
     "arr-vec-get" [target, idx] {
-        coretypck!(target ; VecArr);
-        coretypck!(idx ; I64);
+        sail_typck!(_thr, target ; VecArr);
+        sail_typck!(_thr, idx ; I64);
         let typ = super::arrvec_get_typ(target.clone());
-        assert!(temp_base_sized_p(typ));
+        if !temp_base_sized_p(typ) {
+            sail_raise!(_thr, TypeInvalid, "array element type must be sized", target);
+        }
 
-        let idx = i64_get(idx) as u32;
-        assert!(idx < super::arrvec_get_len(target.clone()));
+        let Ok(idx) = u32::try_from(i64_get(idx.clone())) else {
+            sail_raise!(_thr, IndexOutOfRange, "array index out of range", idx);
+        };
+        if idx >= super::arrvec_get_len(target.clone()) {
+            sail_raise!(_thr, IndexOutOfRange, "array index out of range", target);
+        }
 
         let reg = unsafe { (*_thr).region() };
 
@@ -384,14 +645,23 @@ sail_fn! {
     }
 
     "arr-vec-set" [target, idx, val] {
-        coretypck!(target ; VecArr);
-        coretypck!(idx ; I64);
+        sail_typck!(_thr, target ; VecArr);
+        sail_typck!(_thr, idx ; I64);
         let typ = super::arrvec_get_typ(target.clone());
-        assert!(temp_base_sized_p(typ));
+        if !temp_base_sized_p(typ) {
+            sail_raise!(_thr, TypeInvalid, "array element type must be sized", target);
+        }
         // assert_eq!(typ, super::get_self_type(val));
+        if val.size() != temp_get_size(typ) {
+            sail_raise!(_thr, TypeMismatch, "value does not match element type", val);
+        }
 
-        let idx = i64_get(idx) as u32;
-        assert!(idx < super::arrvec_get_len(target.clone()));
+        let Ok(idx) = u32::try_from(i64_get(idx.clone())) else {
+            sail_raise!(_thr, IndexOutOfRange, "array index out of range", idx);
+        };
+        if idx >= super::arrvec_get_len(target.clone()) {
+            sail_raise!(_thr, IndexOutOfRange, "array index out of range", target);
+        }
 
         unsafe {
             std::ptr::copy_nonoverlapping(
@@ -404,6 +674,138 @@ sail_fn! {
         return target;
     }
 
+    "arr-vec-rplc" [target, src] {
+        sail_typck!(_thr, target ; VecArr);
+
+        let vals = if coretypp!(src ; VecArr) {
+            match arr::nums(src.clone()) {
+                Ok((v, _)) => v,
+                Err((code, msg)) => return unsafe { super::raise_err(_thr, code, msg, Some(src)) },
+            }
+        } else if coretypp!(src ; VecStd) {
+            let mut vals = vec![];
+            for i in 0..super::stdvec_get_len(src.clone()) {
+                match num::Num::of(&super::stdvec_idx(src.clone(), i)) {
+                    Some(n) => vals.push(n),
+                    None => sail_raise!(_thr, TypeInvalid, "vector elements must be numbers", src),
+                }
+            }
+            vals
+        } else {
+            sail_raise!(_thr, TypeInvalid, "`src` must be an array or a vector", src);
+        };
+
+        match arr::replace(target.clone(), &vals) {
+            Ok(()) => target,
+            Err((code, msg)) => unsafe { super::raise_err(_thr, code, msg, Some(src)) },
+        }
+    }
+
+    "arr-vec-add" [fst, snd] {
+        sail_typck!(_thr, fst ; VecArr);
+        sail_typck!(_thr, snd ; VecArr);
+
+        let reg = unsafe { (*_thr).region() };
+        return arr_out(_thr, arr::zip(reg, num::Op::Add, fst.clone(), snd), fst);
+    }
+
+    "arr-vec-sub" [fst, snd] {
+        sail_typck!(_thr, fst ; VecArr);
+        sail_typck!(_thr, snd ; VecArr);
+
+        let reg = unsafe { (*_thr).region() };
+        return arr_out(_thr, arr::zip(reg, num::Op::Sub, fst.clone(), snd), fst);
+    }
+
+    "arr-vec-scale" [target, k] {
+        sail_typck!(_thr, target ; VecArr);
+        let Some(k) = num::Num::of(&k) else {
+            sail_raise!(_thr, TypeInvalid, "`k` must be a number", k);
+        };
+
+        let reg = unsafe { (*_thr).region() };
+        return arr_out(_thr, arr::scale(reg, target.clone(), k), target);
+    }
+
+    "arr-vec-dot" [fst, snd] {
+        sail_typck!(_thr, fst ; VecArr);
+        sail_typck!(_thr, snd ; VecArr);
+
+        let reg = unsafe { (*_thr).region() };
+        return arr_out(_thr, arr::dot(fst.clone(), snd).map(|n| n.init(reg)), fst);
+    }
+
+    "arr-vec-cross" [fst, snd] {
+        sail_typck!(_thr, fst ; VecArr);
+        sail_typck!(_thr, snd ; VecArr);
+
+        let reg = unsafe { (*_thr).region() };
+        return arr_out(_thr, arr::cross(reg, fst.clone(), snd), fst);
+    }
+
+    "arr-vec-norm" [target] {
+        sail_typck!(_thr, target ; VecArr);
+
+        let reg = unsafe { (*_thr).region() };
+        return arr_out(_thr, arr::norm(target.clone()).map(|n| n.init(reg)), target);
+    }
+
+    "arr-vec-lerp" [fst, snd, t] {
+        sail_typck!(_thr, fst ; VecArr);
+        sail_typck!(_thr, snd ; VecArr);
+        let Some(t) = num::Num::of(&t) else {
+            sail_raise!(_thr, TypeInvalid, "`t` must be a number", t);
+        };
+
+        let reg = unsafe { (*_thr).region() };
+        return arr_out(_thr, arr::lerp(reg, fst.clone(), snd, t), fst);
+    }
+
+    "arr-vec-min" [fst, snd] {
+        sail_typck!(_thr, fst ; VecArr);
+        sail_typck!(_thr, snd ; VecArr);
+
+        let reg = unsafe { (*_thr).region() };
+        return arr_out(_thr, arr::pick(reg, fst.clone(), snd, cmp::Ordering::Less), fst);
+    }
+
+    "arr-vec-max" [fst, snd] {
+        sail_typck!(_thr, fst ; VecArr);
+        sail_typck!(_thr, snd ; VecArr);
+
+        let reg = unsafe { (*_thr).region() };
+        return arr_out(_thr, arr::pick(reg, fst.clone(), snd, cmp::Ordering::Greater), fst);
+    }
+
+    "arr-vec-slice" [target, start, end] {
+        sail_typck!(_thr, target ; VecArr);
+        sail_typck!(_thr, start ; I64);
+        sail_typck!(_thr, end ; I64);
+
+        let reg = unsafe { (*_thr).region() };
+        let (a, b) = (i64_get(start), i64_get(end));
+        return arr_out(_thr, arr::slice(reg, target.clone(), a, b), target);
+    }
+
+    "arr-vec-cat" [fst, snd] {
+        sail_typck!(_thr, fst ; VecArr);
+        sail_typck!(_thr, snd ; VecArr);
+
+        let reg = unsafe { (*_thr).region() };
+        return arr_out(_thr, arr::concat(reg, fst.clone(), snd), fst);
+    }
+
+    "arr-vec-as" [target, typ] {
+        sail_typck!(_thr, target ; VecArr);
+        sail_typck!(_thr, typ ; Symbol);
+        let Some(to) = num::NumType::of_sym(sym_get_id(typ.clone())) else {
+            sail_raise!(_thr, TypeInvalid, "`typ` must be a numeric type", typ);
+        };
+
+        let reg = unsafe { (*_thr).region() };
+        return arr_out(_thr, arr::convert(reg, target.clone(), to), target);
+    }
+
     "print" [arg] {
         let tbl = unsafe { ((*_thr).context()).symtab() };
 
@@ -423,47 +825,203 @@ sail_fn! {
     //     return bool_init(_reg, false);
     // }
 
+    "write" [val] {
+        let reg = unsafe { (*_thr).region() };
+        let tbl = unsafe { ((*_thr).context()).symtab() };
+
+        string_init(reg, &super::context(tbl, val).to_string())
+    }
+
     "parse" [strin] {
-        coretypck!(strin ; VecStr);
+        sail_typck!(_thr, strin ; VecStr);
         let strsl = string_get(strin);
 
         let reg = unsafe { (*_thr).region() };
         let tbl = unsafe { ((*_thr).context()).symtab() };
 
-        return match super::parser::parse(reg, tbl, strsl, false) {
+        return match super::parser::parse_located(reg, tbl, strsl, false) {
             Ok(head) => head,
-            Err(err) => super::errcode_init(reg, err),
+            Err(bad) => {
+                let err = unsafe { super::raise_err(_thr, bad.code, "could not parse text", None) };
+                super::err_set_span(err.clone(), None, bad.pos.line, bad.pos.col);
+                err
+            }
         };
     }
 
     "parse-prog" [strin] {
-        coretypck!(strin ; VecStr);
+        sail_typck!(_thr, strin ; VecStr);
         let strsl = string_get(strin);
 
         let reg = unsafe { (*_thr).region() };
         let tbl = unsafe { ((*_thr).context()).symtab() };
 
-        return match super::parser::parse(reg, tbl, strsl, true) {
+        return match super::parser::parse_located(reg, tbl, strsl, true) {
             Ok(head) => head,
-            Err(err) => super::errcode_init(reg, err),
+            Err(bad) => {
+                let err = unsafe { super::raise_err(_thr, bad.code, "could not parse text", None) };
+                super::err_set_span(err.clone(), None, bad.pos.line, bad.pos.col);
+                err
+            }
+        };
+    }
+
+    "serialize" [val] {
+        let reg = unsafe { (*_thr).region() };
+        let tbl = unsafe { ((*_thr).context()).symtab() };
+
+        return match super::serial::serialize(tbl, val.clone()) {
+            Ok(blob) => arr::from_u8s(reg, &blob),
+            Err((code, msg)) => unsafe { super::raise_err(_thr, code, msg, Some(val)) },
+        };
+    }
+
+    "deserialize" [blob] {
+        sail_typck!(_thr, blob ; VecArr);
+        let bytes = match arr::u8s(blob.clone()) {
+            Ok(b) => b,
+            Err((code, msg)) => return unsafe { super::raise_err(_thr, code, msg, Some(blob)) },
+        };
+
+        let reg = unsafe { (*_thr).region() };
+        let tbl = unsafe { ((*_thr).context()).symtab() };
+
+        return match super::serial::deserialize(reg, tbl, &bytes) {
+            Ok(out) => out,
+            Err((code, msg)) => unsafe { super::raise_err(_thr, code, msg, Some(blob)) },
         };
     }
 
     "temp-read-file" [path] {
-        coretypck!(path ; VecStr);
-        let pth = string_get(path);
+        sail_typck!(_thr, path ; VecStr);
+        let pth = string_get(path.clone());
 
         let reg = unsafe { (*_thr).region() };
 
-        let out = match std::fs::read_to_string(pth) {
+        match std::fs::read_to_string(pth) {
             Ok(s) => super::string_init(reg, &s),
-            Err(_) => super::errcode_init(reg, super::SlErrCode::FileCouldNotRead)
+            Err(_) => sail_raise!(_thr, FileCouldNotRead, "could not read file", path),
+        }
+    }
+
+    "save-doc" [path, doc] {
+        sail_typck!(_thr, path ; VecStr);
+
+        let tbl = unsafe { ((*_thr).context()).symtab() };
+
+        let text = match super::doc::Document::of_sail(tbl, doc.clone()) {
+            Ok(d) => d.write(),
+            Err((code, msg)) => return unsafe { super::raise_err(_thr, code, msg, Some(doc)) },
         };
 
-        out
+        match std::fs::write(string_get(path.clone()), text) {
+            Ok(()) => doc,
+            Err(_) => sail_raise!(_thr, FileCouldNotWrite, "could not write file", path),
+        }
+    }
+
+    "load-doc" [path] {
+        sail_typck!(_thr, path ; VecStr);
+
+        let text = match std::fs::read_to_string(string_get(path.clone())) {
+            Ok(s) => s,
+            Err(_) => sail_raise!(_thr, FileCouldNotRead, "could not read file", path),
+        };
+
+        let reg = unsafe { (*_thr).region() };
+        let tbl = unsafe { ((*_thr).context()).symtab() };
+
+        match super::doc::Document::read(reg, tbl, &text) {
+            Ok(d) => d.to_sail(reg, tbl),
+            Err((code, msg)) => unsafe { super::raise_err(_thr, code, msg, Some(path)) },
+        }
+    }
+
+    "load" [path] {
+        sail_typck!(_thr, path ; VecStr);
+        let thr = unsafe { &mut *_thr };
+
+        let (found, prog) = match thr.source_file(string_get(path)) {
+            Ok(source) => source,
+            Err(err) => {
+                thr.raise(err.clone());
+                return err;
+            }
+        };
+
+        let file = super::string_init(thr.region(), &found.to_string_lossy());
+        thr.eval_instead(prog.clone(), file, None);
+        prog
+    }
+
+    "require" [path] {
+        sail_typck!(_thr, path ; VecStr);
+        let thr = unsafe { &mut *_thr };
+
+        let (found, prog) = match thr.source_file(string_get(path)) {
+            Ok(source) => source,
+            Err(err) => {
+                thr.raise(err.clone());
+                return err;
+            }
+        };
+
+        if !thr.require_once(&found) {
+            return env_lookup_by_id(_env, super::S_F_INTERN.0).unwrap();
+        }
+
+        let file = super::string_init(thr.region(), &found.to_string_lossy());
+        let t = env_lookup_by_id(_env, super::S_T_INTERN.0).unwrap();
+        thr.eval_instead(prog.clone(), file, Some(t));
+        prog
+    }
+
+    "gc" [] {
+        let reg = unsafe { (*_thr).region() };
+
+        let freed = unsafe { super::collect_cycles(reg) };
+        super::i64_init(reg, freed as _)
+    }
+
+    "heap-stats" [] {
+        let reg = unsafe { (*_thr).region() };
+        let tbl = unsafe { ((*_thr).context()).symtab() };
+
+        let stats = unsafe { (*reg).stats() };
+
+        let count = |n: usize| i64_init(reg, n as _);
+
+        let live: Vec<SlHndl> = stats
+            .live
+            .iter()
+            .flat_map(|(cfg, n)| [super::debug_keyword(reg, tbl, cfg), count(*n)])
+            .collect();
+
+        let mut key = |name: &str| {
+            sym_init(reg, modeize_sym(tbl.get_id(name.as_bytes()), SymbolMode::Keyword))
+        };
+
+        stdvec_init(reg, &[
+            key("zones"), count(stats.zones),
+            key("zone-bytes"), count(stats.zone_bytes),
+            key("used-bytes"), count(stats.used_bytes),
+            key("empty-bytes"), count(stats.empty_bytes),
+            key("free-blocks"), count(stats.free_blocks),
+            key("free-bytes"), count(stats.free_bytes),
+            key("largest-free"), count(stats.largest_free),
+            key("fragmentation"), f64_init(reg, stats.fragmentation()),
+            key("large-objects"), count(stats.large_objects),
+            key("large-bytes"), count(stats.large_bytes),
+            key("live-objects"), count(stats.live_objects()),
+            key("live"), stdvec_init(reg, &live),
+        ])
     }
 
     "_itsp_mdbg_id" [obj] {
+        if !cfg!(feature = "memdbg") {
+            sail_raise!(_thr, ErrorUnknown, "memory debug mode not active");
+        }
+
         let id = obj.memdbg_obj_id();
 
         let reg = unsafe { (*_thr).region() };
@@ -474,40 +1032,279 @@ sail_fn! {
     }
 
     "vec-push" [target, item] {
-        coretypck!(target ; VecStd);
+        sail_typck!(_thr, target ; VecStd);
 
         super::stdvec_push(target.clone(), item);
 
         return target;
     }
 
+    "vec-pop" [target] {
+        sail_typck!(_thr, target ; VecStd);
+
+        match super::stdvec_pop(target.clone()) {
+            Some(item) => item,
+            None => sail_raise!(_thr, IndexOutOfRange, "cannot pop from empty vector", target),
+        }
+    }
+
     "vec-len" [target] {
+        sail_typck!(_thr, target ; VecStd);
+
         let reg = unsafe { (*_thr).region() };
         super::i64_init(reg, super::stdvec_get_len(target) as _)
     }
 
     "vec-get" [target, idx] {
-        super::stdvec_idx(target, super::i64_get(idx) as _)
+        sail_typck!(_thr, target ; VecStd);
+        sail_typck!(_thr, idx ; I64);
+
+        match u32::try_from(super::i64_get(idx.clone())) {
+            Ok(i) if i < super::stdvec_get_len(target.clone()) => super::stdvec_idx(target, i),
+            _ => sail_raise!(_thr, IndexOutOfRange, "vector index out of range", idx),
+        }
     }
 
-    "tmp-vec-match" [target, item] {
-        coretypck!(target ; VecStd);
+    "str-len" [target] {
+        sail_typck!(_thr, target ; VecStr);
 
-        for i in 0..super::stdvec_get_len(target.clone()) {
-            let this = super::stdvec_idx(target.clone(), i);
-            if super::i64_get(this.clone()) == super::i64_get(item.clone()) {
-                return match get_next_list_elt(this.clone()) {
-                    Some(t) => t,
-                    None => env_lookup_by_id(_env, super::S_F_INTERN.0).unwrap(),
-                }
-            }
+        let reg = unsafe { (*_thr).region() };
+        super::i64_init(reg, string_get(target).chars().count() as _)
+    }
+
+    "str-cat" [fst, snd] {
+        let mut out = match text_of(_thr, fst, "fst") {
+            Ok(t) => t,
+            Err(err) => return err,
+        };
+        match text_of(_thr, snd, "snd") {
+            Ok(t) => out.push_str(&t),
+            Err(err) => return err,
+        }
+
+        string_init(unsafe { (*_thr).region() }, &out)
+    }
+
+    "str-push" [target, item] {
+        sail_typck!(_thr, target ; VecStr);
+
+        match text_of(_thr, item, "item") {
+            Ok(t) => string_push(target, &t),
+            Err(err) => err,
+        }
+    }
+
+    "str-sub" [target, start, end] {
+        sail_typck!(_thr, target ; VecStr);
+        sail_typck!(_thr, start ; I64);
+        sail_typck!(_thr, end ; I64);
+
+        let text = string_get(target);
+        let (a, b) = (super::i64_get(start.clone()), super::i64_get(end.clone()));
+
+        match (char_offset(text, a), char_offset(text, b)) {
+            (Some(a), Some(b)) if a <= b => string_init(unsafe { (*_thr).region() }, &text[a..b]),
+            (Some(_), Some(_)) => sail_raise!(_thr, IndexOutOfRange, "string slice ends before it starts", end),
+            (None, _) => sail_raise!(_thr, IndexOutOfRange, "string index out of range", start),
+            (_, None) => sail_raise!(_thr, IndexOutOfRange, "string index out of range", end),
+        }
+    }
+
+    "str-ref" [target, idx] {
+        sail_typck!(_thr, target ; VecStr);
+        sail_typck!(_thr, idx ; I64);
+
+        let text = string_get(target);
+        match usize::try_from(super::i64_get(idx.clone())).ok().and_then(|i| text.chars().nth(i)) {
+            Some(c) => unsafe { super::char_init((*_thr).region(), c) },
+            None => sail_raise!(_thr, IndexOutOfRange, "string index out of range", idx),
+        }
+    }
+
+    "str-find" [target, item] {
+        sail_typck!(_thr, target ; VecStr);
+
+        let needle = match text_of(_thr, item, "item") {
+            Ok(t) => t,
+            Err(err) => return err,
+        };
+
+        let text = string_get(target);
+        match text.find(&needle) {
+            Some(at) => super::i64_init(unsafe { (*_thr).region() }, text[..at].chars().count() as _),
+            None => env_lookup_by_id(_env, super::S_F_INTERN.0).unwrap(),
+        }
+    }
+
+    "str-split" [target, sep] {
+        sail_typck!(_thr, target ; VecStr);
+
+        let sep = match text_of(_thr, sep, "sep") {
+            Ok(t) => t,
+            Err(err) => return err,
+        };
+
+        let reg = unsafe { (*_thr).region() };
+        let text = string_get(target);
+
+        // an empty separator splits between every character
+        let parts: Vec<_> = if sep.is_empty() {
+            text.chars().map(|c| string_init(reg, c.encode_utf8(&mut [0; 4]))).collect()
+        } else {
+            text.split(sep.as_str()).map(|p| string_init(reg, p)).collect()
+        };
+
+        stdvec_init(reg, &parts)
+    }
+
+    "str-of" [val] {
+        let reg = unsafe { (*_thr).region() };
+
+        if coretypp!(val ; VecStr) {
+            string_init(reg, string_get(val))
+        } else if super::char_p(&mut val) {
+            string_init(reg, super::char_get(val).encode_utf8(&mut [0; 4]))
+        } else {
+            let tbl = unsafe { ((*_thr).context()).symtab() };
+            string_init(reg, &super::context(tbl, val).to_string())
+        }
+    }
+
+    "str-sym" [name] {
+        sail_typck!(_thr, name ; VecStr);
+
+        if !super::parser::symbol_name_p(string_get(name.clone()).as_bytes()) {
+            sail_raise!(_thr, TypeInvalid, "name would not read back as a symbol", name);
+        }
+
+        let reg = unsafe { (*_thr).region() };
+        let tbl = unsafe { ((*_thr).context()).symtab() };
+        sym_init(reg, tbl.get_id(string_get(name).as_bytes()))
+    }
+
+    "sym-str" [sym] {
+        sail_typck!(_thr, sym ; Symbol);
+
+        let reg = unsafe { (*_thr).region() };
+        let tbl = unsafe { ((*_thr).context()).symtab() };
+
+        // the name alone, without any mode prefix
+        match tbl.lookup_by_id(demodes_sym(sym_get_id(sym.clone()))) {
+            Some(n) => string_init(reg, &String::from_utf8_lossy(n)),
+            None => sail_raise!(_thr, EvalUnboundSymbol, "symbol has no name", sym),
+        }
+    }
+
+    "char-code" [val] {
+        if !super::char_p(&mut val) {
+            sail_raise!(_thr, TypeInvalid, "`val` must be of type char", val);
+        }
+
+        let reg = unsafe { (*_thr).region() };
+        super::i64_init(reg, super::char_get(val) as _)
+    }
+
+    "code-char" [code] {
+        sail_typck!(_thr, code ; I64);
+
+        match u32::try_from(super::i64_get(code.clone())).ok().and_then(char::from_u32) {
+            Some(c) => unsafe { super::char_init((*_thr).region(), c) },
+            None => sail_raise!(_thr, TypeInvalid, "`code` must be a Unicode scalar value", code),
         }
+    }
+
+    "map-make" [] {
+        let reg = unsafe { (*_thr).region() };
+        hashvec_make(reg, MAP_INIT_SIZE)
+    }
+
+    "alist-make" [] {
+        let reg = unsafe { (*_thr).region() };
+        ref_make(reg)
+    }
+
+    "map-get" [target, key] {
+        let found = match map_kind(_thr, target.clone()) {
+            Ok(true) => hash_map_lookup(target, key),
+            Ok(false) => alist_map_lookup(target, key),
+            Err(err) => return err,
+        };
+
+        match found {
+            Some(val) => val,
+            None => env_lookup_by_id(_env, super::S_F_INTERN.0).unwrap(),
+        }
+    }
+
+    "map-has" [target, key] {
+        let found = match map_kind(_thr, target.clone()) {
+            Ok(true) => hash_map_contains(target, key),
+            Ok(false) => alist_map_contains(target, key),
+            Err(err) => return err,
+        };
 
-        env_lookup_by_id(_env, super::S_F_INTERN.0).unwrap()
+        let out = if found { super::S_T_INTERN.0 } else { super::S_F_INTERN.0 };
+        env_lookup_by_id(_env, out).unwrap()
+    }
+
+    "map-set" [target, key, val] {
+        let reg = unsafe { (*_thr).region() };
+
+        match map_kind(_thr, target.clone()) {
+            Ok(true) => hash_map_insert(reg, target.clone(), key, val),
+            Ok(false) => unsafe { alist_map_insert(reg, target.clone(), key, val) },
+            Err(err) => return err,
+        }
+
+        return target;
+    }
+
+    "map-del" [target, key] {
+        let found = match map_kind(_thr, target.clone()) {
+            Ok(true) => hash_map_remove(target, key),
+            Ok(false) => alist_map_remove(target, key),
+            Err(err) => return err,
+        };
+
+        let out = if found { super::S_T_INTERN.0 } else { super::S_F_INTERN.0 };
+        env_lookup_by_id(_env, out).unwrap()
+    }
+
+    "map-len" [target] {
+        let len = match map_kind(_thr, target.clone()) {
+            Ok(true) => hash_map_len(target),
+            Ok(false) => alist_map_entries(target).len() as u32,
+            Err(err) => return err,
+        };
+
+        let reg = unsafe { (*_thr).region() };
+        super::i64_init(reg, len as _)
+    }
+
+    "map-keys" [target] {
+        let entries = match map_kind(_thr, target.clone()) {
+            Ok(true) => hash_map_entries(target),
+            Ok(false) => alist_map_entries(target),
+            Err(err) => return err,
+        };
+
+        let keys: Vec<_> = entries.into_iter().map(|(k, _)| k).collect();
+        stdvec_init(unsafe { (*_thr).region() }, &keys)
+    }
+
+    "map-vals" [target] {
+        let entries = match map_kind(_thr, target.clone()) {
+            Ok(true) => hash_map_entries(target),
+            Ok(false) => alist_map_entries(target),
+            Err(err) => return err,
+        };
+
+        let vals: Vec<_> = entries.into_iter().map(|(_, v)| v).collect();
+        stdvec_init(unsafe { (*_thr).region() }, &vals)
     }
 
     "tmp-coord-log" [tgt] {
-        coretypck!(tgt ; VecArr);
+        sail_typck!(_thr, tgt ; VecArr);
 
         let x = super::read_field::<f32>(tgt.clone(), 8);
         let y = super::read_field::<f32>(tgt.clone(), 12);
@@ -517,3 +1314,171 @@ sail_fn! {
         tgt
     }
 }
+
+/// Gives the result of an array operation to a native procedure, or
+/// raises the Sail error it failed with
+fn arr_out(
+    thr: *mut super::thread::ThreadHull,
+    out: Result<SlHndl, arr::ArrErr>,
+    form: SlHndl,
+) -> SlHndl {
+    match out {
+        Ok(o) => o,
+        Err((code, msg)) => unsafe { super::raise_err(thr, code, msg, Some(form)) },
+    }
+}
+
+/// Gets the text of a native procedure's argument that may be either a
+/// string or a character, raising a Sail error if it is neither
+fn text_of(
+    thr: *mut super::thread::ThreadHull,
+    mut val: SlHndl,
+    name: &str,
+) -> Result<String, SlHndl> {
+    if coretypp!(val ; VecStr) {
+        Ok(string_get(val).to_owned())
+    } else if super::char_p(&mut val) {
+        Ok(super::char_get(val).to_string())
+    } else {
+        let msg = format!("`{name}` must be a string or char");
+        Err(unsafe { super::raise_err(thr, super::SlErrCode::TypeInvalid, &msg, Some(val)) })
+    }
+}
+
+/// Gives the byte offset of a character index into a string; the index
+/// just past the last character is allowed
+fn char_offset(text: &str, idx: i64) -> Option<usize> {
+    let idx = usize::try_from(idx).ok()?;
+    text.char_indices()
+        .map(|(at, _)| at)
+        .chain(std::iter::once(text.len()))
+        .nth(idx)
+}
+
+/// Buckets in a hash map made by `map-make`; it grows as it fills
+const MAP_INIT_SIZE: u32 = 8;
+
+/// Tells whether a native procedure's map argument is a hash map
+/// rather than an association list map; anything else raises a Sail
+/// error
+fn map_kind(thr: *mut super::thread::ThreadHull, mut target: SlHndl) -> Result<bool, SlHndl> {
+    if coretypp!(target ; VecHash) {
+        Ok(true)
+    } else if coretypp!(target ; Ref) {
+        if alist_map_p(target.clone()) {
+            Ok(false)
+        } else {
+            Err(unsafe {
+                super::raise_err(
+                    thr,
+                    super::SlErrCode::TypeInvalid,
+                    "association list entries must be key and value pairs",
+                    Some(target),
+                )
+            })
+        }
+    } else {
+        Err(unsafe {
+            super::raise_err(
+                thr,
+                super::SlErrCode::TypeInvalid,
+                "`target` must be a map",
+                Some(target),
+            )
+        })
+    }
+}
+
+/// Gives the ID of the thread a native procedure's handle argument
+/// refers to; anything but a thread handle raises a Sail error
+fn thread_ref_arg(thr: *mut super::thread::ThreadHull, mut thref: SlHndl) -> Result<usize, SlHndl> {
+    if super::thread_ref_p(&mut thref) {
+        Ok(super::thread_ref_id(thref))
+    } else {
+        Err(unsafe {
+            super::raise_err(
+                thr,
+                super::SlErrCode::TypeInvalid,
+                "`thref` must be a thread handle",
+                Some(thref),
+            )
+        })
+    }
+}
+
+/// Acts on a live thread for a native procedure, giving true if the
+/// thread was found and false otherwise
+fn on_thread(
+    thr: *mut super::thread::ThreadHull,
+    env: SlHndl,
+    id: usize,
+    f: impl FnOnce(&super::thread::ThreadHull),
+) -> SlHndl {
+    let found = unsafe { (*thr).weft() }.with_thread(id, f);
+
+    match found {
+        Some(()) => env_lookup_by_id(env, super::S_T_INTERN.0).unwrap(),
+        None => env_lookup_by_id(env, super::S_F_INTERN.0).unwrap(),
+    }
+}
+
+/// Applies an arithmetic operation for a native procedure, promoting
+/// the operands to a common type; failure raises a Sail error
+fn arith(thr: *mut super::thread::ThreadHull, op: num::Op, fst: SlHndl, snd: SlHndl) -> SlHndl {
+    let name = match op {
+        num::Op::Add => "add",
+        num::Op::Sub => "sub",
+        num::Op::Mul => "mul",
+        num::Op::Div => "div",
+        num::Op::Rem => "mod",
+    };
+
+    let Some(a) = num::Num::of(&fst) else {
+        let msg = format!("type invalid for {name}");
+        return unsafe { super::raise_err(thr, super::SlErrCode::TypeInvalid, &msg, Some(fst)) };
+    };
+    let Some(b) = num::Num::of(&snd) else {
+        let msg = format!("operand not a number for {name}");
+        return unsafe { super::raise_err(thr, super::SlErrCode::TypeMismatch, &msg, Some(snd)) };
+    };
+
+    let reg = unsafe { (*thr).region() };
+
+    match num::arith(op, a, b) {
+        Ok(out) => out.init(reg),
+        Err(code @ super::SlErrCode::ArithDivByZero) => unsafe {
+            super::raise_err(thr, code, "integer division by zero", Some(fst))
+        },
+        Err(code) => {
+            let msg = format!("{name} overflowed its type");
+            unsafe { super::raise_err(thr, code, &msg, Some(fst)) }
+        }
+    }
+}
+
+/// Converts a number to the given type for a native procedure; a value
+/// which does not fit raises a Sail error
+fn convert(thr: *mut super::thread::ThreadHull, val: SlHndl, typ: num::NumType) -> SlHndl {
+    let Some(n) = num::Num::of(&val) else {
+        return unsafe {
+            super::raise_err(
+                thr,
+                super::SlErrCode::TypeInvalid,
+                "`val` must be a number",
+                Some(val),
+            )
+        };
+    };
+
+    match n.to(typ) {
+        Some(out) => out.init(unsafe { (*thr).region() }),
+        None => unsafe {
+            super::raise_err(
+                thr,
+                super::SlErrCode::ArithOverflow,
+                "value does not fit the type",
+                Some(val),
+            )
+        },
+    }
+}
@@ -17,15 +17,6 @@
 
 // <>
 
-// TODO: how does the module / path system work? (@mod/proc @mod/sym
-// 10) we need to resolve path symbol constructions
-
-// TODO: switch the system to lexical binding, to prevent "spooky
-// action from within", or procedures changing variables in the
-// calling scope; procedures should always either be pure (no
-// environment besides arguments), or closures (carrying their
-// creation environment with them)
-
 use std::{alloc, mem, ptr, slice};
 
 /// A symbol table, to map symbol strings to symbol IDs and vice versa
@@ -51,6 +42,10 @@ pub struct SymbolTable {
     next_id: u32,
     ins_lock: u8,
     rsz_lock: u8,
+
+    // memory left behind by resizes, which lookups begun before one
+    // may still be reading; freed along with the table
+    retired: Vec<(*mut u8, alloc::Layout)>,
 }
 
 unsafe impl Send for SymbolTable {}
@@ -86,6 +81,10 @@ impl Drop for SymbolTable {
             alloc::dealloc(self.id_to_nm as *mut u8, layout);
             alloc::dealloc(self.nm_to_id as *mut u8, layout);
             alloc::dealloc(self.entries, layout);
+
+            for (mem, layout) in self.retired.drain(..) {
+                alloc::dealloc(mem, layout);
+            }
         }
     }
 }
@@ -124,23 +123,17 @@ impl SymbolTable {
             next_id: 0,
             ins_lock: false as u8,
             rsz_lock: false as u8,
+
+            retired: Vec::new(),
         }
     }
 
     // TODO: resize according to a progression of known primes!
     // NOTE: hashing is FAR slower with sizes not known at compile
+    /// Multiplies the size of the maps and the entry zone by the given
+    /// factor; no insertion may happen meanwhile, so `get_id` calls
+    /// this with the insertion lock held
     fn resize(&mut self, factor: usize) {
-        // acquire insertion lock
-        unsafe {
-            while !std::intrinsics::atomic_cxchg_acquire_acquire(
-                &mut self.ins_lock,
-                false as u8,
-                true as u8,
-            )
-            .1
-            {}
-        }
-
         // create tables of new size
         let new_len = self.map_len * factor;
         let new_size = new_len * 8;
@@ -155,11 +148,12 @@ impl SymbolTable {
 
         // write current entries into entry zone
         let ebct = self.btop as usize - self.entries as usize;
-        unsafe { ptr::copy_nonoverlapping(&self.entries, nezpt as _, ebct) }
+        unsafe { ptr::copy_nonoverlapping(self.entries, nezpt, ebct) }
+        let nbtop = unsafe { nezpt.add(ebct) };
 
         // re-hash ids and names; place pointers in tables
-        let mut zcur = self.entries;
-        while zcur < self.btop {
+        let mut zcur = nezpt;
+        while zcur < nbtop {
             // step through entries from start to end
             let cur_id = unsafe { ptr::read(zcur as *const u32) };
             let cur_name = Self::e_name_slice(unsafe { &*zcur });
@@ -179,7 +173,7 @@ impl SymbolTable {
             zcur = unsafe { zcur.add(elen) }
         }
 
-        assert_eq!(zcur, self.btop);
+        assert_eq!(zcur, nbtop);
 
         // acquire resize lock
         unsafe {
@@ -192,6 +186,13 @@ impl SymbolTable {
             {}
         }
 
+        let old_layout = alloc::Layout::from_size_align(self.map_len * 8, 8).unwrap();
+        self.retired.extend([
+            (self.id_to_nm as *mut u8, old_layout),
+            (self.nm_to_id as *mut u8, old_layout),
+            (self.entries, old_layout),
+        ]);
+
         // replace table pointers, entry zone pointers, and map len
         self.id_to_nm = ntapt as _;
         self.nm_to_id = ntbpt as _;
@@ -203,9 +204,6 @@ impl SymbolTable {
 
         // release resize lock
         unsafe { std::intrinsics::atomic_store_release(&mut self.rsz_lock, false as u8) };
-
-        // release insertion lock
-        unsafe { std::intrinsics::atomic_store_release(&mut self.ins_lock, false as u8) };
     }
 
     pub fn get_id(&mut self, name: &[u8]) -> u32 {
@@ -231,6 +229,15 @@ impl SymbolTable {
         let out_id = if let Some(cur_id) = self.lookup_by_name(name) {
             cur_id
         } else {
+            // grow while the maps are half full or the entry zone has
+            // no room for this entry
+            let elen = name.len() + Self::U32S + Self::U16S;
+            while (self.load + 1) * 2 > self.map_len
+                || self.btop as usize + elen > self.entries as usize + self.map_len * 8
+            {
+                self.resize(2);
+            }
+
             // direct_insert using next_id
             let ins_id = self.next_id;
             self.direct_insert(ins_id, name);
@@ -309,7 +316,6 @@ impl SymbolTable {
         let nm_id_wp = self.probe(false, nm_id_tgt);
         unsafe { ptr::write(nm_id_wp, eptr) };
 
-        // TODO: some mechanism to resize table upon excess load (1/3?)
         self.load += 1;
     }
 
@@ -498,4 +504,22 @@ mod tests {
         assert_eq!(b"mesa", tab.lookup_by_id(id2).unwrap());
         assert_eq!(b"droll", tab.lookup_by_id(id3).unwrap());
     }
+
+    #[test]
+    fn grows() {
+        let mut tab = SymbolTable::new(10);
+
+        // far more entries, and longer names, than it was made for
+        let names: Vec<String> = (0..5000)
+            .map(|i| format!("a-longer-symbol-{}", i))
+            .collect();
+        for (i, name) in names.iter().enumerate() {
+            assert_eq!(i as u32, tab.get_id(name.as_bytes()));
+        }
+
+        for (i, name) in names.iter().enumerate() {
+            assert_eq!(Some(i as u32), tab.lookup_by_name(name.as_bytes()));
+            assert_eq!(name.as_bytes(), tab.lookup_by_id(i as u32).unwrap());
+        }
+    }
 }
@@ -17,10 +17,15 @@
 
 // <>
 
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
-use std::usize;
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, Once, OnceLock, RwLock, Weak};
+use std::time::{Duration, Instant, SystemTime};
 
-use super::{SlHndl, Stab, Styc};
+use super::{SlErrCode, SlHndl, Stab, Styc};
 use super::{eval, memmgt, parser, queue};
 
 /// Global context data for inter-thread and human interaction
@@ -30,13 +35,20 @@ pub struct Tact {
 
     /// Counter to issue unique IDs to every object type
     typctr: Styc,
+
+    /// Directories in which to look for files to load, in order
+    search_path: Vec<PathBuf>,
 }
 
+/// Directories searched for files to load, unless others are set
+pub const SEARCH_PATH: &[&str] = &["scripts"];
+
 impl Tact {
     pub fn create(symtab_size: usize) -> Self {
         Self {
             symtab: Stab::new(symtab_size),
             typctr: Styc::new(),
+            search_path: SEARCH_PATH.iter().map(PathBuf::from).collect(),
         }
     }
 
@@ -47,6 +59,27 @@ impl Tact {
     pub fn typctr(&mut self) -> &mut Styc {
         &mut self.typctr
     }
+
+    pub fn search_path(&self) -> &[PathBuf] {
+        &self.search_path
+    }
+
+    pub fn set_search_path(&mut self, dirs: impl IntoIterator<Item = PathBuf>) {
+        self.search_path = dirs.into_iter().collect();
+    }
+
+    /// Finds a file, giving its canonical path; a relative name is
+    /// tried within each directory of the search path in turn
+    pub fn resolve(&self, name: &Path) -> Option<PathBuf> {
+        if name.is_absolute() {
+            return name.canonicalize().ok().filter(|p| p.is_file());
+        }
+
+        self.search_path
+            .iter()
+            .filter_map(|dir| dir.join(name).canonicalize().ok())
+            .find(|p| p.is_file())
+    }
 }
 
 // TODO: track refs to thread from parent thread / other owners
@@ -54,11 +87,9 @@ impl Tact {
 // TODO: adjust the weft / thread data structures to avoid misuse of
 // pointers; align with Rust norms to benefit from guarantees
 
-// TODO: hand out handles to threads, not raw pointers
-
-// TODO: structures / functions for arbitrary mapping between Sail
-// threads and OS threads, in order to evenly and efficiently advance
-// all active Sail threads
+// NOTE: Sail threads start on the least loaded worker, and an idle
+// worker steals waiting threads from the busiest, so that all active
+// Sail threads advance evenly across the OS threads
 
 pub struct SlThreadRef {
     raw: *mut ThreadHull,
@@ -67,79 +98,195 @@ pub struct SlThreadRef {
 // NOTE: once Sail threads are added to the weft (so as soon as they
 // are created), they should "magically" / automatically run
 
-struct WorkTgtNode {
-    next: *mut WorkTgtNode,
-    prev: *mut WorkTgtNode,
-    thr: *mut ThreadHull,
+/// Queue of runnable Sail threads belonging to one worker, which
+/// parks its OS thread whenever the queue runs dry
+struct WorkerPost {
+    runq: Mutex<VecDeque<usize>>,
+    worker: OnceLock<std::thread::Thread>,
+    /// recent evaluation steps taken by the worker, decaying by an
+    /// eighth with every slice
+    load: AtomicU64,
+    idle: AtomicBool,
+    halt: AtomicBool,
+    crew: Weak<Crew>,
+}
 
-    _pin: std::marker::PhantomPinned,
+impl WorkerPost {
+    fn new(crew: &Arc<Crew>) -> Self {
+        Self {
+            runq: Mutex::new(VecDeque::new()),
+            worker: OnceLock::new(),
+            load: AtomicU64::new(0),
+            idle: AtomicBool::new(false),
+            halt: AtomicBool::new(false),
+            crew: Arc::downgrade(crew),
+        }
+    }
+
+    /// Adds a thread to the run queue and wakes the worker for it; if
+    /// the worker already has more to do, an idle one is woken to help
+    fn post(&self, thr: *mut ThreadHull) {
+        let waiting = {
+            let mut runq = self.runq.lock().unwrap();
+            runq.push_back(thr as usize);
+            runq.len()
+        };
+
+        if let Some(w) = self.worker.get() {
+            w.unpark()
+        }
+
+        if waiting > 1
+            && let Some(crew) = self.crew.upgrade()
+        {
+            crew.nudge()
+        }
+    }
+
+    fn record(&self, steps: u64) {
+        let load = self.load.load(Ordering::Relaxed);
+        self.load.store(load - load / 8 + steps, Ordering::Relaxed);
+    }
 }
 
-unsafe impl Send for WorkTgtNode {}
-unsafe impl Sync for WorkTgtNode {}
+/// All of a weft's worker posts, so that each worker can find others
+/// to take work from
+struct Crew {
+    posts: RwLock<Vec<Arc<WorkerPost>>>,
+}
 
-// NOTE: code which runs in each OS thread to advance Sail threads
-// TODO: track stopped Sail threads and thread::park when inactive
-// TODO: exit / terminate thread on command (sentinel in sentinel?)
-fn worker_runtime(th_list_sentinel: usize) {
-    let th_list_sentinel = th_list_sentinel as *const WorkTgtNode;
-    let list_head = unsafe { th_list_sentinel.as_ref().unwrap() };
+impl Crew {
+    /// Takes a waiting thread from the most heavily loaded worker with
+    /// any to spare, and makes the thief its new home
+    fn steal(&self, thief: &Arc<WorkerPost>) -> Option<usize> {
+        let posts = self.posts.read().unwrap();
 
-    // TODO: permit remote termination
-    let mut cur_tgt = list_head;
+        let mut victims = posts
+            .iter()
+            .filter(|p| !Arc::ptr_eq(p, thief))
+            .collect::<Vec<_>>();
+        victims.sort_by_key(|p| Reverse(p.load.load(Ordering::Relaxed)));
+
+        for v in victims {
+            // the victim runs from the front, so take from the back
+            let Some(thr) = v.runq.lock().unwrap().pop_back() else {
+                continue;
+            };
 
-    let mut count = 0;
-    let mut last_len;
+            let thref = unsafe { &*(thr as *const ThreadHull) };
+            *thref.home.lock().unwrap() = Some(thief.clone());
 
-    // TODO: some way of assessing load on thread?
-    loop {
-        cur_tgt = unsafe {
-            match cur_tgt.next.as_ref() {
-                Some(r) => r,
-                None => list_head,
-            }
-        };
+            return Some(thr);
+        }
 
-        if list_head.thr as usize == usize::MAX {
-            break;
+        None
+    }
+
+    /// Wakes one idle worker, which will look for work to steal
+    fn nudge(&self) {
+        let posts = self.posts.read().unwrap();
+
+        if let Some(w) = posts
+            .iter()
+            .find(|p| p.idle.load(Ordering::Acquire))
+            .and_then(|p| p.worker.get())
+        {
+            w.unpark()
         }
+    }
+}
 
-        // TODO: somehow communicate when thread done
-        match unsafe { cur_tgt.thr.as_mut() } {
-            Some(thref) => {
-                // TODO: re-freeze when stack empty
-                if thref.advance() {
-                    count += 1;
+/// Steps a worker gives one Sail thread, for each point of its
+/// priority, before moving to the next
+const SLICE: usize = 16;
+
+/// Priority of a thread until it is told otherwise
+pub const PRIORITY_DEFAULT: u8 = 4;
+/// Greatest priority a thread may have; the least is one
+pub const PRIORITY_MAX: u8 = 8;
+
+// NOTE: code which runs in each OS thread to advance Sail threads
+fn worker_runtime(crew: Arc<Crew>, post: Arc<WorkerPost>) {
+    while !post.halt.load(Ordering::Acquire) {
+        let next = post.runq.lock().unwrap().pop_front();
+
+        match next.or_else(|| crew.steal(&post)) {
+            Some(thr) => {
+                let thref = unsafe { &mut *(thr as *mut ThreadHull) };
+
+                let before = thref.steps();
+                let runnable = thref.run_slice(SLICE * thref.priority() as usize);
+                post.record(thref.steps() - before);
+
+                // threads which finish or block leave the queue; a
+                // blocked thread is posted again when woken, and one
+                // which ended is released
+                if runnable {
+                    post.runq.lock().unwrap().push_back(thr);
+                } else if thref.ended.load(Ordering::Acquire) != ThreadStatus::Running as u8 {
+                    thref.weft().release(thref);
                 }
             }
             None => {
-                last_len = count;
-
-                if last_len == 0 && count == 0 {
-                    std::thread::park()
-                }
-
-                count = 0;
+                // an idle worker has no recent work to speak of
+                post.load.store(0, Ordering::Relaxed);
+                post.idle.store(true, Ordering::Release);
+                std::thread::park();
+                post.idle.store(false, Ordering::Release);
             }
-        };
+        }
     }
 }
 
-// to add node to worker: insert immediately after its sentinel. write
-// next element pointer of new node first, then write next element
-// pointer of sentinel node, atomically. prev element pointer of next
-// element and prev element pointer of new node can be handled
-// separately.
+// groups of vectors in the weft are parallel arrays, with equal
+// element counts and associated indices!
+
+/// Deadlines at which to wake Sail threads, earliest first
+static ALARMS: Mutex<BinaryHeap<Reverse<(Instant, usize)>>> = Mutex::new(BinaryHeap::new());
+static ALARM_BELL: Condvar = Condvar::new();
+static ALARM_START: Once = Once::new();
+
+/// Wakes a Sail thread once the given instant passes, if it is not
+/// woken before then; the thread must still check for itself whether
+/// its deadline has come
+pub fn wake_at(thr: *mut ThreadHull, at: Instant) {
+    ALARM_START.call_once(|| {
+        std::thread::Builder::new()
+            .name("alarms".to_string())
+            .spawn(alarm_runtime)
+            .unwrap();
+    });
+
+    ALARMS.lock().unwrap().push(Reverse((at, thr as usize)));
+    ALARM_BELL.notify_one();
+}
 
-// to remove node from worker: read prev and next node fields. write
-// next node to prev element's next field, atomically. also write prev
-// node to next element's prev field.
+// NOTE: code which runs in a dedicated OS thread, sleeping until the
+// next deadline
+fn alarm_runtime() {
+    let mut pending = ALARMS.lock().unwrap();
 
-// the work target lists are rings, with the permanently owned empty
-// sentinel always present.
+    loop {
+        let now = Instant::now();
 
-// groups of vectors in the weft are parallel arrays, with equal
-// element counts and associated indices!
+        while let Some(Reverse((at, thr))) = pending.peek().copied() {
+            if at > now {
+                break;
+            }
+
+            pending.pop();
+            unsafe { (*(thr as *mut ThreadHull)).wake() }
+        }
+
+        pending = match pending.peek() {
+            Some(Reverse((at, _))) => {
+                let wait = at.saturating_duration_since(now);
+                ALARM_BELL.wait_timeout(pending, wait).unwrap().0
+            }
+            None => ALARM_BELL.wait(pending).unwrap(),
+        };
+    }
+}
 
 // TODO: use a different name to distinguish Sail threads OS threads?
 
@@ -147,35 +294,47 @@ fn worker_runtime(th_list_sentinel: usize) {
 
 // assign usizes are indices into the Sail thread list
 
-// TODO: should assign by thread ID instead? might require mapping
-
 // NOTE: can shorten structure by unifying parallel vector accounting
 
 // TODO: basis lock and assignment lock?
 
 pub struct Weft {
     tact: Tact,
-    append_lock: u8,
+    /// held to read the thread list, or exclusively to change it
+    roster: RwLock<()>,
     nxt_sl_thr_id: usize,
 
     sl_threads: Vec<Pin<Box<ThreadHull>>>,
     // flags: Vec<u8>,
     // arcs: Vec<u32>,
-    work_nodes: Vec<Pin<Box<WorkTgtNode>>>,
-
     worker_os_threads: Vec<std::thread::JoinHandle<()>>,
-    sentinels: Vec<Pin<Box<WorkTgtNode>>>,
-    assignments: Vec<Vec<usize>>,
+    crew: Arc<Crew>,
+    /// threads handed to the workers, which may trade them freely
+    assignments: Vec<usize>,
 
     special: Vec<usize>,
 
     frozen: Vec<usize>,
+
+    /// region holding the results of removed threads, until joined
+    kept_reg: *mut memmgt::Region,
+    kept: Mutex<HashMap<usize, Kept>>,
+}
+
+/// Outcome of a thread removed from the weft while joins of it were
+/// still owed, by ID
+struct Kept {
+    status: ThreadStatus,
+    /// copy of the result, within the weft's own region
+    res: Option<SlHndl>,
+    /// joins still owed; the last removes the outcome
+    owed: usize,
 }
 
 impl Drop for Weft {
     fn drop(&mut self) {
-        for mut s in self.sentinels.drain(..) {
-            unsafe { Pin::into_inner_unchecked(s.as_mut()) }.thr = usize::MAX as *mut ThreadHull;
+        for p in self.crew.posts.write().unwrap().drain(..) {
+            p.halt.store(true, Ordering::Release);
         }
 
         for t in self.worker_os_threads.iter() {
@@ -185,6 +344,16 @@ impl Drop for Weft {
         for t in self.worker_os_threads.drain(..) {
             t.join().unwrap()
         }
+
+        for t in self.sl_threads.iter() {
+            self.unschedule((&**t as *const ThreadHull).cast_mut());
+        }
+
+        self.sl_threads.clear();
+
+        // kept results go before the region holding them
+        self.kept.get_mut().unwrap().clear();
+        drop(unsafe { Box::from_raw(self.kept_reg) });
     }
 }
 
@@ -192,19 +361,23 @@ impl Weft {
     pub fn create(tact: Tact) -> Self {
         Self {
             tact,
-            append_lock: false as u8,
+            roster: RwLock::new(()),
             nxt_sl_thr_id: 2,
 
             sl_threads: Vec::with_capacity(4),
-            work_nodes: Vec::with_capacity(4),
 
             worker_os_threads: Vec::new(),
-            sentinels: Vec::new(),
+            crew: Arc::new(Crew {
+                posts: RwLock::new(Vec::new()),
+            }),
             assignments: Vec::new(),
 
             special: Vec::new(),
 
             frozen: Vec::new(),
+
+            kept_reg: memmgt::Region::acq(10000),
+            kept: Mutex::new(HashMap::new()),
         }
     }
 
@@ -224,93 +397,208 @@ impl Weft {
     // unsafecell to be sound
 
     fn add_thread(&mut self, thread_hull: Pin<Box<ThreadHull>>) -> *mut ThreadHull {
-        unsafe {
-            let lock: *mut u8 = &mut self.append_lock;
-            while !std::intrinsics::atomic_cxchg_acqrel_acquire(lock, false as u8, true as u8).1 {
-                std::hint::spin_loop();
-            }
+        let this = unsafe { (self as *const Self).cast_mut().as_mut_unchecked() };
+        let _roster = self.roster.write().unwrap();
 
-            let thr_raw_pos = (Pin::into_inner_unchecked(Pin::as_ref(&thread_hull))
-                as *const ThreadHull)
-                .cast_mut();
+        let thr_raw_pos = (unsafe { Pin::into_inner_unchecked(Pin::as_ref(&thread_hull)) }
+            as *const ThreadHull)
+            .cast_mut();
 
-            let new_idx = self.sl_threads.len();
-            self.sl_threads.push(thread_hull);
-            assert!(self.sl_threads.len() > new_idx);
+        let new_idx = this.sl_threads.len();
+        this.sl_threads.push(thread_hull);
+        assert!(this.sl_threads.len() > new_idx);
 
-            let node = Box::pin(WorkTgtNode {
-                next: std::ptr::null_mut(),
-                prev: std::ptr::null_mut(),
-                thr: thr_raw_pos,
+        this.frozen.push(new_idx);
 
-                _pin: std::marker::PhantomPinned,
-            });
+        thr_raw_pos
+    }
+
+    /// Removes a thread and every trace of it from the weft, freeing
+    /// its region, evaluation stack and queues; the result of one
+    /// which ended is kept while joins of it are owed
+    pub(super) fn rmv_thread(&self, thread_ptr: *mut ThreadHull) {
+        let this = unsafe { (self as *const Self).cast_mut().as_mut_unchecked() };
+        let roster = self.roster.write().unwrap();
+
+        let Some(idx) = this
+            .sl_threads
+            .iter()
+            .position(|b| std::ptr::eq(&**b, thread_ptr))
+        else {
+            return;
+        };
+
+        let removed = this.sl_threads.remove(idx);
 
-            self.work_nodes.push(node);
-            self.frozen.push(new_idx);
+        // indices past the removed thread shift down by one
+        let renumber = |v: &mut Vec<usize>| {
+            v.retain(|i| *i != idx);
+            v.iter_mut().filter(|i| **i > idx).for_each(|i| *i -= 1);
+        };
 
-            assert_eq!(self.sl_threads.len(), self.work_nodes.len());
+        renumber(&mut this.assignments);
+        renumber(&mut this.special);
+        renumber(&mut this.frozen);
 
-            std::intrinsics::atomic_store_release(lock, false as u8);
+        self.unschedule(thread_ptr);
 
-            thr_raw_pos
+        for t in this.sl_threads.iter() {
+            let mut w = t.watchers.lock().unwrap();
+            w.joiners.retain(|j| *j != removed.id);
+            w.monitors.retain(|m| *m != removed.id);
         }
-    }
 
-    fn rmv_thread(&self, thread_ptr: *mut ThreadHull) {
-        unsafe {
-            let idx = match self.sl_threads.iter().enumerate().find(|(_, b)| {
-                Pin::into_inner_unchecked((*b).as_ref()) as *const _ as usize == thread_ptr as usize
-            }) {
-                Some((i, _)) => i,
-                None => return,
+        let owed = removed.owed.load(Ordering::Acquire);
+        if owed > 0 {
+            let kept = Kept {
+                status: removed.status(),
+                res: removed.result().map(|r| unsafe {
+                    SlHndl::from_raw_unchecked(super::structure_copy(this.kept_reg, r))
+                }),
+                owed,
             };
+            this.kept.lock().unwrap().insert(removed.id, kept);
+        }
 
-            let this = (self as *const Self).cast_mut().as_mut_unchecked();
+        drop(roster);
+        drop(removed);
+    }
 
-            let lock: *mut u8 = &mut this.append_lock;
-            while !std::intrinsics::atomic_cxchg_acqrel_acquire(lock, false as u8, true as u8).1 {
-                std::hint::spin_loop();
-            }
+    /// Removes an ended thread which the weft handed to its workers;
+    /// any other is left to whoever drives it
+    fn release(&self, thread_ptr: *mut ThreadHull) {
+        let assigned = {
+            let _roster = self.roster.read().unwrap();
+
+            self.sl_threads
+                .iter()
+                .position(|b| std::ptr::eq(&**b, thread_ptr))
+                .is_some_and(|idx| self.assignments.contains(&idx))
+        };
 
-            let removed = this.sl_threads.remove(idx);
+        if assigned {
+            self.rmv_thread(thread_ptr);
+        }
+    }
 
-            // TODO: remove all traces of the thread in the structure
+    /// Withdraws a thread about to be freed from everything which may
+    /// wake or run it: run queues, alarms, and its own queues
+    fn unschedule(&self, thread_ptr: *mut ThreadHull) {
+        let addr = thread_ptr as usize;
+        for p in self.crew.posts.read().unwrap().iter() {
+            p.runq.lock().unwrap().retain(|t| *t != addr);
+        }
 
-            std::intrinsics::atomic_store_release(lock, false as u8);
+        ALARMS.lock().unwrap().retain(|Reverse((_, t))| *t != addr);
 
-            drop(removed);
+        let thr = unsafe { &*thread_ptr };
+        thr.qin.unwatch(thread_ptr);
+        for (_, inlet) in thr.chans.lock().unwrap().iter() {
+            inlet.unwatch(thread_ptr);
         }
     }
 
-    pub fn add_worker(&mut self) {
-        // TODO: maybe add reentry protection
+    /// Runs a procedure on the thread with the given ID, if it is still
+    /// in the weft; the thread cannot be removed in the meantime
+    pub fn with_thread<R>(&self, id: usize, f: impl FnOnce(&ThreadHull) -> R) -> Option<R> {
+        let _roster = self.roster.read().unwrap();
 
-        let sentinel = {
-            let mut uninit = Box::<WorkTgtNode>::new_uninit();
-            let ptr = uninit.as_mut_ptr();
+        self.sl_threads.iter().find(|t| t.id == id).map(|t| f(t))
+    }
 
-            unsafe {
-                (&raw mut (*ptr).next).write(ptr);
-                (&raw mut (*ptr).prev).write(ptr);
-                (&raw mut (*ptr).thr).write(std::ptr::null_mut());
-            }
+    /// Copies the kept result of a removed thread into the given region,
+    /// for a thread joining it; gives nothing if no outcome is kept
+    pub(super) fn collect_kept(
+        &self,
+        id: usize,
+        reg: *mut memmgt::Region,
+    ) -> Option<Option<SlHndl>> {
+        let mut kept = self.kept.lock().unwrap();
+        let k = kept.get_mut(&id)?;
+
+        let res = k
+            .res
+            .clone()
+            .map(|r| unsafe { SlHndl::from_raw_unchecked(super::structure_copy(reg, r)) });
+
+        k.owed -= 1;
+        if k.owed == 0 {
+            kept.remove(&id);
+        }
 
-            Box::into_pin(unsafe { uninit.assume_init() })
-        };
+        Some(res)
+    }
 
-        let sp = unsafe { Pin::into_inner_unchecked(sentinel.as_ref()) as *const _ } as usize;
+    /// Tells how a removed thread ended, with a copy of its result
+    /// within the given region, if its outcome is kept
+    pub(super) fn kept_outcome(
+        &self,
+        id: usize,
+        reg: *mut memmgt::Region,
+    ) -> Option<(ThreadStatus, Option<SlHndl>)> {
+        let kept = self.kept.lock().unwrap();
+        let k = kept.get(&id)?;
+
+        let res = k
+            .res
+            .clone()
+            .map(|r| unsafe { SlHndl::from_raw_unchecked(super::structure_copy(reg, r)) });
+
+        Some((k.status, res))
+    }
+
+    /// Tells what the thread with the given ID is doing, or how it
+    /// ended if its outcome is kept after its removal
+    pub fn status_of(&self, id: usize) -> ThreadStatus {
+        self.with_thread(id, |t| t.status())
+            .or_else(|| self.kept.lock().unwrap().get(&id).map(|k| k.status))
+            .unwrap_or(ThreadStatus::Gone)
+    }
+
+    pub fn add_worker(&mut self) {
+        // TODO: maybe add reentry protection
+
+        let post = Arc::new(WorkerPost::new(&self.crew));
+
+        let (cw, wp) = (self.crew.clone(), post.clone());
         let new_thr = std::thread::Builder::new()
             .name(format!("worker {}", self.worker_os_threads.len()))
-            .spawn(move || worker_runtime(sp))
+            .spawn(move || worker_runtime(cw, wp))
             .unwrap();
 
+        // anything posted before this is found when the worker starts
+        post.worker.set(new_thr.thread().clone()).unwrap();
+
         self.worker_os_threads.push(new_thr);
-        self.sentinels.push(sentinel);
-        self.assignments.push(Vec::new());
 
-        assert_eq!(self.sentinels.len(), self.worker_os_threads.len());
-        assert_eq!(self.assignments.len(), self.worker_os_threads.len());
+        let mut posts = self.crew.posts.write().unwrap();
+        posts.push(post);
+
+        assert_eq!(posts.len(), self.worker_os_threads.len());
+    }
+
+    /// Adds the given number of workers, or one per processor if none
+    /// is given; there is always at least one, since nothing would
+    /// run the spawned threads otherwise
+    pub fn add_workers(&mut self, count: Option<usize>) {
+        let count = count
+            .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()))
+            .max(1);
+
+        for _ in 0..count {
+            self.add_worker()
+        }
+    }
+
+    /// Counts the workers currently parked for lack of runnable threads
+    pub fn idle_workers(&self) -> usize {
+        self.crew
+            .posts
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|p| p.idle.load(Ordering::Acquire))
+            .count()
     }
 
     fn apportion_work(&mut self) {
@@ -318,12 +606,9 @@ impl Weft {
 
         // check that all sail threads are assigned to a hardware thread
         let live_unassigned = {
-            let mut una = vec![true; self.work_nodes.len()];
+            let mut una = vec![true; self.sl_threads.len()];
 
-            self.assignments
-                .iter()
-                .flatten()
-                .for_each(|u| una[*u] = false);
+            self.assignments.iter().for_each(|u| una[*u] = false);
             self.special.iter().for_each(|u| una[*u] = false);
             self.frozen.iter().for_each(|u| una[*u] = false);
 
@@ -333,30 +618,22 @@ impl Weft {
                 .collect::<Vec<usize>>()
         };
 
-        // for any unassigned threads, assign them in a balanced manner
-        let mut next_worker = 0;
-        // TODO: this is not balanced
-        for idx in live_unassigned {
-            self.assignments[next_worker].push(idx);
-
-            let insert_point =
-                unsafe { Pin::into_inner_unchecked(self.sentinels[next_worker].as_mut()) };
-            let to_insert = unsafe { Pin::into_inner_unchecked(self.work_nodes[idx].as_mut()) };
-
-            to_insert.next = insert_point.next;
-            to_insert.prev = insert_point;
+        let posts = self.crew.posts.read().unwrap().clone();
 
-            unsafe {
-                insert_point.next.as_mut().unwrap().prev = to_insert;
-            }
+        // for any unassigned threads, start each on the worker with the
+        // least recent work and the fewest threads waiting
+        for idx in live_unassigned {
+            self.assignments.push(idx);
 
-            unsafe {
-                std::intrinsics::atomic_store_release(&mut insert_point.next, to_insert);
-            }
+            let post = posts
+                .iter()
+                .min_by_key(|p| (p.runq.lock().unwrap().len(), p.load.load(Ordering::Relaxed)))
+                .unwrap();
 
-            self.worker_os_threads[next_worker].thread().unpark();
+            let thr = unsafe { Pin::into_inner_unchecked(self.sl_threads[idx].as_mut()) };
+            *thr.home.lock().unwrap() = Some(post.clone());
 
-            next_worker = (next_worker + 1) % self.worker_os_threads.len();
+            post.post(thr);
         }
 
         // remove assignments of any finished sail threads (?)
@@ -385,6 +662,9 @@ impl Weft {
     }
 
     fn thaw(&mut self, id: usize) -> bool {
+        let this = unsafe { (self as *const Self).cast_mut().as_mut_unchecked() };
+        let _roster = this.roster.write().unwrap();
+
         let tgt_idx = self
             .sl_threads
             .iter()
@@ -415,9 +695,6 @@ impl Weft {
     }
 }
 
-// TODO: threads could contain their own prev and next pointers for
-// worker target tracking; then they'd be exactly 128 bytes (2 words)
-
 // TODO: currently tact and weft probably contain the same pointer
 
 pub struct ThreadHull {
@@ -430,12 +707,12 @@ pub struct ThreadHull {
     /// tracker for all extant threads
     weft: *mut Weft,
 
-    /// dedicated memory
-    reg: memmgt::Region,
-
     /// queue target
     qin: queue::Inlet,
 
+    /// further queues, each named by a symbol
+    chans: Mutex<Vec<(u32, Box<queue::Inlet>)>>,
+
     /// top-level environment
     tenv: SlHndl,
 
@@ -445,12 +722,136 @@ pub struct ThreadHull {
     /// final return location
     out: *mut super::SlHead,
 
+    /// error raised by a native procedure, awaiting unwinding
+    fault: Option<SlHndl>,
+
+    /// set by a native procedure which must wait to finish
+    blocked: bool,
+
+    /// scheduling state, shared with whoever wakes the thread
+    sched: AtomicU8,
+
+    /// worker which runs the thread, changing when another steals it;
+    /// none if it is driven directly
+    home: Mutex<Option<Arc<WorkerPost>>>,
+
+    /// OS thread which sleeps while driving the thread directly
+    driver: Mutex<Option<std::thread::Thread>>,
+
+    /// threads to tell when this one finishes
+    watchers: Mutex<Watchers>,
+
+    /// set when the thread is to be killed at its next step
+    doomed: AtomicBool,
+
+    /// set when the thread is to raise an error at its next step, to
+    /// one of the `INTR_` kinds
+    interrupted: AtomicU8,
+
+    /// share of its worker's time the thread receives
+    priority: AtomicU8,
+
+    /// most steps any one program may take; zero for no limit
+    budget: AtomicU64,
+
+    /// steps taken by the latest program
+    prog_steps: u64,
+
+    /// how the latest program ended, if it has
+    ended: AtomicU8,
+
+    /// joins the result is owed once the thread ends: one for each
+    /// thread then waiting, or else one for the first to come
+    owed: AtomicUsize,
+
+    /// evaluation steps taken so far
+    steps: u64,
+
+    /// when a blocked native procedure stops waiting, if ever
+    deadline: Option<Instant>,
+
+    /// code for the evaluator to run in place of a native procedure
+    handoff: Option<Handoff>,
+
+    /// programs parsed from files, by canonical path
+    sources: HashMap<PathBuf, Source>,
+
+    /// files this thread has required, by canonical path
+    required: HashSet<PathBuf>,
+
+    /// dedicated memory; dropped after every field which may hold
+    /// objects within it
+    reg: memmgt::Region,
+
     /// does not implement Unpin
     _pin: std::marker::PhantomPinned,
 }
 
 unsafe impl Send for ThreadHull {}
 
+/// Running, or waiting in a run queue
+const AWAKE: u8 = 0;
+/// Blocked, and in no run queue until woken
+const PARKED: u8 = 1;
+/// Woken while awake, so the next attempt to park fails
+const NOTIFIED: u8 = 2;
+
+/// No interrupt is pending
+const INTR_NONE: u8 = 0;
+/// Raise the interrupt error wherever the thread is
+const INTR_ANY: u8 = 1;
+/// Raise the interrupt error only if an `eval` or `try` will stop it,
+/// and otherwise forget it
+const INTR_BOUNDED: u8 = 2;
+
+/// Program parsed from a file, kept for loading it again
+struct Source {
+    /// modification time of the file when it was read
+    modified: Option<SystemTime>,
+    prog: SlHndl,
+    /// where each object of the program was read from
+    spans: parser::SpanTable,
+}
+
+/// Code a native procedure hands back to the evaluator, to run in
+/// place of the procedure's own result
+pub(super) struct Handoff {
+    pub expr: SlHndl,
+    /// file the code came from
+    pub file: SlHndl,
+    /// result to give instead of the code's own, if any
+    pub value: Option<SlHndl>,
+}
+
+/// What a Sail thread is doing, as far as other threads can tell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ThreadStatus {
+    Running,
+    Blocked,
+    Done,
+    Failed,
+    Killed,
+    /// Removed from the weft
+    Gone,
+}
+
+/// Threads to tell when a thread finishes, by ID
+#[derive(Default)]
+struct Watchers {
+    /// woken, to collect the result themselves
+    joiners: Vec<usize>,
+    /// sent a notice of how the thread ended
+    monitors: Vec<usize>,
+}
+
+/// Outcome of one evaluation step
+enum Progress {
+    Ran,
+    Blocked,
+    Done,
+}
+
 // TODO: implement new-model threads and queues to cleanly support
 // manager thread, record threads, and render thread
 
@@ -475,10 +876,30 @@ impl ThreadHull {
                 (*ptr).reg.init();
 
                 (&raw mut (*ptr).qin).write(queue::Inlet::new(&raw mut (*ptr).reg));
+                (&raw mut (*ptr).chans).write(Mutex::new(Vec::new()));
                 (&raw mut (*ptr).tenv).write(super::env_create(&raw mut (*ptr).reg, env_parent));
                 (&raw mut (*ptr).eval).write(eval::EvalStack::new(s_init_size));
 
                 (&raw mut (*ptr).out).write(std::ptr::null_mut());
+                (&raw mut (*ptr).fault).write(None);
+
+                (&raw mut (*ptr).blocked).write(false);
+                (&raw mut (*ptr).sched).write(AtomicU8::new(AWAKE));
+                (&raw mut (*ptr).home).write(Mutex::new(None));
+                (&raw mut (*ptr).driver).write(Mutex::new(None));
+                (&raw mut (*ptr).watchers).write(Mutex::new(Watchers::default()));
+                (&raw mut (*ptr).doomed).write(AtomicBool::new(false));
+                (&raw mut (*ptr).interrupted).write(AtomicU8::new(INTR_NONE));
+                (&raw mut (*ptr).priority).write(AtomicU8::new(PRIORITY_DEFAULT));
+                (&raw mut (*ptr).budget).write(AtomicU64::new(0));
+                (&raw mut (*ptr).prog_steps).write(0);
+                (&raw mut (*ptr).ended).write(AtomicU8::new(ThreadStatus::Running as u8));
+                (&raw mut (*ptr).owed).write(AtomicUsize::new(0));
+                (&raw mut (*ptr).steps).write(0);
+                (&raw mut (*ptr).deadline).write(None);
+                (&raw mut (*ptr).handoff).write(None);
+                (&raw mut (*ptr).sources).write(HashMap::new());
+                (&raw mut (*ptr).required).write(HashSet::new());
             }
 
             Box::into_pin(unsafe { uninit.assume_init() })
@@ -487,6 +908,9 @@ impl ThreadHull {
         weft.add_thread(it)
     }
 
+    /// Creates a thread whose top environment extends a snapshot of
+    /// this thread's, copied into its own region; bindings made here
+    /// after the spawn are not seen there
     pub fn spawn(&self, s_init_size: Option<usize>, r_zone_size: Option<u32>) -> *mut Self {
         let weft = unsafe { self.weft.as_mut().unwrap() };
 
@@ -506,15 +930,38 @@ impl ThreadHull {
                 (*ptr).reg.init();
 
                 (&raw mut (*ptr).qin).write(queue::Inlet::new(&raw mut (*ptr).reg));
+                (&raw mut (*ptr).chans).write(Mutex::new(Vec::new()));
+                // a copy, so that nothing in the new region refers into
+                // this one, which may be freed first
+                let parent = super::structure_copy(&raw mut (*ptr).reg, self.tenv.clone());
                 (&raw mut (*ptr).tenv).write(super::env_create(
                     &raw mut (*ptr).reg,
-                    Some(self.tenv.clone()),
+                    Some(SlHndl::from_raw_unchecked(parent)),
                 ));
                 (&raw mut (*ptr).eval).write(eval::EvalStack::new(
                     s_init_size.unwrap_or(self.eval.size()),
                 ));
 
                 (&raw mut (*ptr).out).write(std::ptr::null_mut());
+                (&raw mut (*ptr).fault).write(None);
+
+                (&raw mut (*ptr).blocked).write(false);
+                (&raw mut (*ptr).sched).write(AtomicU8::new(AWAKE));
+                (&raw mut (*ptr).home).write(Mutex::new(None));
+                (&raw mut (*ptr).driver).write(Mutex::new(None));
+                (&raw mut (*ptr).watchers).write(Mutex::new(Watchers::default()));
+                (&raw mut (*ptr).doomed).write(AtomicBool::new(false));
+                (&raw mut (*ptr).interrupted).write(AtomicU8::new(INTR_NONE));
+                (&raw mut (*ptr).priority).write(AtomicU8::new(PRIORITY_DEFAULT));
+                (&raw mut (*ptr).budget).write(AtomicU64::new(0));
+                (&raw mut (*ptr).prog_steps).write(0);
+                (&raw mut (*ptr).ended).write(AtomicU8::new(ThreadStatus::Running as u8));
+                (&raw mut (*ptr).owed).write(AtomicUsize::new(0));
+                (&raw mut (*ptr).steps).write(0);
+                (&raw mut (*ptr).deadline).write(None);
+                (&raw mut (*ptr).handoff).write(None);
+                (&raw mut (*ptr).sources).write(HashMap::new());
+                (&raw mut (*ptr).required).write(HashSet::new());
             }
 
             Box::into_pin(unsafe { uninit.assume_init() })
@@ -523,6 +970,11 @@ impl ThreadHull {
         weft.add_thread(it)
     }
 
+    pub fn weft<'a, 'b>(&'a self) -> &'b mut Weft {
+        // the weft outlives every thread within it
+        unsafe { self.weft.as_mut_unchecked() }
+    }
+
     pub fn context<'a, 'b>(&'a self) -> &'b mut Tact {
         // the TAble and CounTer will always outlive all threads
         unsafe { self.tact.as_mut_unchecked() }
@@ -536,6 +988,32 @@ impl ThreadHull {
         ((&self.qin) as *const queue::Inlet).cast_mut()
     }
 
+    /// Finds the queue named by a symbol, opening it if need be
+    pub fn channel(&mut self, name: u32) -> *mut queue::Inlet {
+        let reg = self.region();
+        let mut chans = self.chans.lock().unwrap();
+
+        let idx = match chans.iter().position(|(n, _)| *n == name) {
+            Some(i) => i,
+            None => {
+                chans.push((name, Box::new(queue::Inlet::new(reg))));
+                chans.len() - 1
+            }
+        };
+
+        &mut *chans[idx].1
+    }
+
+    /// Finds the queue named by a symbol, if the thread has opened it
+    pub fn find_channel(&self, name: u32) -> Option<*mut queue::Inlet> {
+        let mut chans = self.chans.lock().unwrap();
+
+        chans
+            .iter_mut()
+            .find(|(n, _)| *n == name)
+            .map(|(_, i)| &mut **i as *mut queue::Inlet)
+    }
+
     pub fn top_env(&self) -> SlHndl {
         self.tenv.clone()
     }
@@ -544,14 +1022,114 @@ impl ThreadHull {
         let expr_sexp = parser::parse(self.region(), self.context().symtab(), text, prog)?;
 
         assert!(self.eval.is_empty());
+        self.ended
+            .store(ThreadStatus::Running as u8, Ordering::Release);
+        self.prog_steps = 0;
         self.eval.start(&mut self.out, self.top_env(), expr_sexp);
 
         Ok(())
     }
 
+    /// Loads a program from a file found on the search path; errors
+    /// raised by the program carry the file's name
+    pub fn load_from_file(&mut self, name: &str) -> Result<(), SlHndl> {
+        let (path, prog) = self.source_file(name)?;
+        let file = super::string_init(self.region(), &path.to_string_lossy());
+
+        assert!(self.eval.is_empty());
+        self.ended
+            .store(ThreadStatus::Running as u8, Ordering::Release);
+        self.prog_steps = 0;
+        self.eval
+            .start_from(&mut self.out, self.top_env(), prog, file);
+
+        Ok(())
+    }
+
+    /// Finds a file on the search path and parses it as a program,
+    /// giving its canonical path; a file parsed before is not parsed
+    /// again unless it has changed since
+    pub fn source_file(&mut self, name: &str) -> Result<(PathBuf, SlHndl), SlHndl> {
+        let reg = self.region();
+
+        let Some(path) = self.context().resolve(Path::new(name)) else {
+            return Err(unsafe {
+                super::err_init(
+                    reg,
+                    SlErrCode::FileCouldNotRead,
+                    "file not found on the search path",
+                    Some(super::string_init(reg, name)),
+                )
+            });
+        };
+
+        let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+
+        if let Some(source) = self.sources.get(&path)
+            && source.modified == modified
+        {
+            return Ok((path, source.prog.clone()));
+        }
+
+        let file = super::string_init(reg, &path.to_string_lossy());
+
+        let Ok(text) = std::fs::read_to_string(&path) else {
+            return Err(unsafe {
+                super::err_init(
+                    reg,
+                    SlErrCode::FileCouldNotRead,
+                    "could not read file",
+                    Some(file),
+                )
+            });
+        };
+
+        let (prog, spans) =
+            match parser::parse_spanned(reg, self.context().symtab(), &text, true, true) {
+                Ok(parsed) => parsed,
+                Err(errs) => {
+                    // the first problem is reported, and the others counted
+                    let msg = match errs.len() {
+                        1 => "could not parse file".to_string(),
+                        n => format!("could not parse file; {n} problems found"),
+                    };
+                    let first = errs[0];
+
+                    let err = unsafe { super::err_init(reg, first.code, &msg, None) };
+                    super::err_set_span(err.clone(), Some(file), first.pos.line, first.pos.col);
+                    return Err(err);
+                }
+            };
+
+        let source = Source {
+            modified,
+            prog: prog.clone(),
+            spans,
+        };
+        self.sources.insert(path.clone(), source);
+
+        Ok((path, prog))
+    }
+
+    /// Finds where in a file an object was read from, if the object
+    /// was read from the file as it was last loaded
+    pub fn source_pos(&self, file: &str, obj: &SlHndl) -> Option<parser::Pos> {
+        let source = self.sources.get(Path::new(file))?;
+        source.spans.locate(obj).map(|span| span.start)
+    }
+
+    /// Notes that the thread has required a file; returns false if it
+    /// already had
+    pub fn require_once(&mut self, path: &Path) -> bool {
+        self.required.insert(path.to_path_buf())
+    }
+
     pub fn load_proc_immed(&mut self, mut proc: SlHndl) {
         coretypck!(proc ; ProcLambda);
         assert!(self.eval.is_empty());
+        self.ended
+            .store(ThreadStatus::Running as u8, Ordering::Release);
+        self.prog_steps = 0;
 
         self.eval
             .push_frame_head(&mut self.out, eval::Opcode::Apply, self.top_env());
@@ -567,6 +1145,9 @@ impl ThreadHull {
 
     pub fn load_direct(&mut self, sexp: SlHndl) {
         assert!(self.eval.is_empty());
+        self.ended
+            .store(ThreadStatus::Running as u8, Ordering::Release);
+        self.prog_steps = 0;
         self.eval.start(&mut self.out, self.top_env(), sexp);
     }
 
@@ -579,9 +1160,378 @@ impl ThreadHull {
         self.eval.is_empty()
     }
 
+    /// Takes one step on behalf of whichever OS thread drives this
+    /// Sail thread directly; while the thread is blocked, the OS thread
+    /// sleeps within this call
     pub fn advance(&mut self) -> bool {
-        let self_ptr = self as *mut Self;
-        self.eval.iter_once(self_ptr)
+        match self.step() {
+            Progress::Ran => true,
+            Progress::Blocked => {
+                self.sleep();
+                true
+            }
+            Progress::Done => false,
+        }
+    }
+
+    fn step(&mut self) -> Progress {
+        if self.doomed.load(Ordering::Acquire) {
+            if !self.eval.is_empty() {
+                self.end_with(SlErrCode::ThreadKilled, "thread was killed");
+            }
+
+            return Progress::Done;
+        }
+
+        let budget = self.budget.load(Ordering::Relaxed);
+        if budget != 0 && self.prog_steps >= budget {
+            if !self.eval.is_empty() {
+                self.end_with(SlErrCode::ThreadOverBudget, "thread ran out of steps");
+            }
+
+            return Progress::Done;
+        }
+
+        let intr = self.interrupted.swap(INTR_NONE, Ordering::AcqRel);
+        let raise = match intr {
+            INTR_ANY => true,
+            INTR_BOUNDED => self.eval.bounded_p(),
+            _ => false,
+        };
+
+        if raise && !self.eval.is_empty() {
+            // the error unwinds to the nearest boundary, like any other
+            let err = unsafe {
+                super::err_init(
+                    self.region(),
+                    SlErrCode::ThreadInterrupted,
+                    "thread was interrupted",
+                    None,
+                )
+            };
+            self.eval.interrupt(err);
+        } else {
+            let self_ptr = self as *mut Self;
+            if !self.eval.iter_once(self_ptr) {
+                return Progress::Done;
+            }
+        }
+
+        self.steps += 1;
+        self.prog_steps += 1;
+
+        // between steps, every object in use is held by a counted
+        // reference, so cycles may be collected safely
+        if self.reg.collect_due {
+            unsafe { super::collect_cycles(self.region()) };
+        }
+
+        if self.eval.is_empty() {
+            self.finish();
+        }
+
+        if std::mem::take(&mut self.blocked) {
+            Progress::Blocked
+        } else {
+            self.deadline = None;
+            Progress::Ran
+        }
+    }
+
+    /// Takes up to `budget` steps on behalf of a worker; returns
+    /// whether the thread should stay in the worker's run queue
+    fn run_slice(&mut self, budget: usize) -> bool {
+        for _ in 0..budget {
+            match self.step() {
+                Progress::Ran => (),
+                Progress::Blocked => {
+                    if self.suspend() {
+                        return false;
+                    }
+                }
+                Progress::Done => return false,
+            }
+        }
+
+        true
+    }
+
+    /// Parks the thread after a blocked step; fails if it was woken
+    /// in the meantime, in which case it should simply keep running
+    fn suspend(&self) -> bool {
+        match self
+            .sched
+            .compare_exchange(AWAKE, PARKED, Ordering::AcqRel, Ordering::Acquire)
+        {
+            Ok(_) => true,
+            Err(_) => {
+                self.sched.store(AWAKE, Ordering::Release);
+                false
+            }
+        }
+    }
+
+    fn sleep(&self) {
+        *self.driver.lock().unwrap() = Some(std::thread::current());
+
+        if self.suspend() {
+            while self.sched.load(Ordering::Acquire) == PARKED {
+                std::thread::park();
+            }
+        }
+    }
+
+    /// Makes a parked thread runnable again, posting it to its worker
+    /// or waking the OS thread which drives it; a thread which is
+    /// awake instead fails its next attempt to park
+    pub fn wake(&self) {
+        loop {
+            match self.sched.load(Ordering::Acquire) {
+                PARKED => {
+                    if self
+                        .sched
+                        .compare_exchange(PARKED, AWAKE, Ordering::AcqRel, Ordering::Acquire)
+                        .is_ok()
+                    {
+                        break;
+                    }
+                }
+                AWAKE => {
+                    if self
+                        .sched
+                        .compare_exchange(AWAKE, NOTIFIED, Ordering::AcqRel, Ordering::Acquire)
+                        .is_ok()
+                    {
+                        return;
+                    }
+                }
+                _ => return,
+            }
+        }
+
+        let home = self.home.lock().unwrap().clone();
+
+        match home {
+            Some(post) => post.post((self as *const Self).cast_mut()),
+            None => {
+                if let Some(d) = self.driver.lock().unwrap().as_ref() {
+                    d.unpark()
+                }
+            }
+        }
+    }
+
+    /// Called by a native procedure which cannot finish yet, having
+    /// arranged to be woken; its result is discarded, and it is
+    /// applied again once the thread wakes
+    pub fn block(&mut self) {
+        self.blocked = true;
+    }
+
+    /// Called by a native procedure to have the evaluator run code in
+    /// its place, in the top level; errors raised by the code carry
+    /// the given file name, and `value`, if given, replaces its result
+    pub fn eval_instead(&mut self, expr: SlHndl, file: SlHndl, value: Option<SlHndl>) {
+        self.handoff = Some(Handoff { expr, file, value });
+    }
+
+    pub(super) fn take_handoff(&mut self) -> Option<Handoff> {
+        self.handoff.take()
+    }
+
+    pub(super) fn blocked_p(&self) -> bool {
+        self.blocked
+    }
+
+    /// Gives the deadline for a blocked native procedure, which is
+    /// fixed the first time it is applied and kept until it finishes
+    pub fn deadline_after(&mut self, wait: Duration) -> Instant {
+        *self.deadline.get_or_insert_with(|| Instant::now() + wait)
+    }
+
+    /// Ends the program at once, making a new error its result
+    fn end_with(&mut self, code: SlErrCode, msg: &str) {
+        self.eval.abandon();
+
+        let err = unsafe { super::err_init(self.region(), code, msg, None) };
+
+        self.clr_res();
+        unsafe {
+            super::inc_refc(err.get_raw());
+            self.out = err.get_raw();
+        }
+
+        self.finish();
+    }
+
+    /// Records how the program ended, then tells every watcher
+    fn finish(&mut self) {
+        let failed = self.result().is_some_and(|mut r| coretypp!(r ; ErrObj));
+
+        let status = if !failed {
+            ThreadStatus::Done
+        } else if self.doomed.load(Ordering::Acquire) {
+            ThreadStatus::Killed
+        } else {
+            ThreadStatus::Failed
+        };
+
+        let Watchers { joiners, monitors } = {
+            let mut w = self.watchers.lock().unwrap();
+            self.owed.store(w.joiners.len().max(1), Ordering::Release);
+            self.ended.store(status as u8, Ordering::Release);
+            std::mem::take(&mut *w)
+        };
+
+        let weft = self.weft();
+
+        for j in joiners {
+            weft.with_thread(j, |t| t.wake());
+        }
+
+        if !monitors.is_empty() {
+            let notice = self.end_notice(self.region());
+
+            for m in monitors {
+                weft.with_thread(m, |t| unsafe {
+                    (*t.queue_inlet()).transmit(self.id, notice.clone())
+                });
+            }
+        }
+    }
+
+    /// Arranges for the thread with ID `waiter` to be woken when this
+    /// thread finishes; returns false, arranging nothing, if it has
+    pub fn watch_end(&self, waiter: usize) -> bool {
+        let mut w = self.watchers.lock().unwrap();
+
+        if self.ended.load(Ordering::Acquire) != ThreadStatus::Running as u8 {
+            false
+        } else {
+            w.joiners.push(waiter);
+            true
+        }
+    }
+
+    /// Arranges for the thread with ID `watcher` to receive a notice on
+    /// its queue when this thread finishes; returns false, arranging
+    /// nothing, if it has, so the watcher should make its own notice
+    pub fn monitor(&self, watcher: usize) -> bool {
+        let mut w = self.watchers.lock().unwrap();
+
+        if self.ended.load(Ordering::Acquire) != ThreadStatus::Running as u8 {
+            false
+        } else {
+            w.monitors.push(watcher);
+            true
+        }
+    }
+
+    /// Creates, within the given region, a keyword for how the thread
+    /// ended linked to a copy of its result
+    pub fn end_notice(&self, reg: *mut memmgt::Region) -> SlHndl {
+        let tbl = self.context().symtab();
+        let status = super::debug_keyword(reg, tbl, self.status());
+
+        if let Some(res) = self.result() {
+            let res = unsafe { SlHndl::from_raw_unchecked(super::structure_copy(reg, res)) };
+            super::set_next_list_elt(self.top_env(), status.clone(), res);
+        }
+
+        status
+    }
+
+    /// Copies the result of this ended thread into the given region,
+    /// for a thread joining it
+    pub(super) fn collect(&self, reg: *mut memmgt::Region) -> Option<SlHndl> {
+        let _ = self
+            .owed
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| n.checked_sub(1));
+
+        self.result()
+            .map(|r| unsafe { SlHndl::from_raw_unchecked(super::structure_copy(reg, r)) })
+    }
+
+    /// Has the thread killed at its next step, waking it if need be
+    pub fn kill(&self) {
+        self.doomed.store(true, Ordering::Release);
+        self.wake();
+    }
+
+    /// Has the thread raise an error at its next step, which unwinds
+    /// to the nearest `eval` or `try`, waking it if need be
+    pub fn interrupt(&self) {
+        self.interrupted.store(INTR_ANY, Ordering::Release);
+        self.wake();
+    }
+
+    /// Interrupts the thread only if it is running code within an
+    /// `eval` or `try`, which will stop the error; returns whether it
+    /// did. An interrupt still pending once the thread leaves such
+    /// code is forgotten. Only touches atomics, so it is safe within a
+    /// signal handler
+    pub fn interrupt_bounded(&self) -> bool {
+        if self.status() != ThreadStatus::Running || !self.eval.bounded_p() {
+            return false;
+        }
+
+        // an interrupt of any kind already pending is left as it is
+        let _ = self.interrupted.compare_exchange(
+            INTR_NONE,
+            INTR_BOUNDED,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        );
+        true
+    }
+
+    pub fn priority(&self) -> u8 {
+        self.priority.load(Ordering::Relaxed)
+    }
+
+    /// Sets the thread's share of its worker's time, from one up to
+    /// `PRIORITY_MAX`
+    pub fn set_priority(&self, priority: u8) {
+        assert!((1..=PRIORITY_MAX).contains(&priority));
+        self.priority.store(priority, Ordering::Relaxed);
+    }
+
+    /// Limits every program the thread runs to the given number of
+    /// steps; a program which reaches it fails. Zero lifts the limit
+    pub fn set_budget(&self, steps: u64) {
+        self.budget.store(steps, Ordering::Relaxed);
+    }
+
+    pub fn status(&self) -> ThreadStatus {
+        match self.ended.load(Ordering::Acquire) {
+            x if x == ThreadStatus::Done as u8 => ThreadStatus::Done,
+            x if x == ThreadStatus::Failed as u8 => ThreadStatus::Failed,
+            x if x == ThreadStatus::Killed as u8 => ThreadStatus::Killed,
+            _ if self.sched.load(Ordering::Acquire) == PARKED => ThreadStatus::Blocked,
+            _ => ThreadStatus::Running,
+        }
+    }
+
+    /// Arranges for this thread to be woken by the next message on its
+    /// queue; returns false, arranging nothing, if one is waiting
+    pub fn watch_queue(&mut self) -> bool {
+        let me = self as *mut Self;
+        self.qin.watch(me)
+    }
+
+    /// Counts the evaluation steps the thread has taken
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Raises an error from a native procedure; the evaluator unwinds
+    /// the stack with it as soon as the procedure returns
+    pub fn raise(&mut self, err: SlHndl) {
+        self.fault = Some(err);
+    }
+
+    pub(super) fn take_fault(&mut self) -> Option<SlHndl> {
+        self.fault.take()
     }
 
     pub fn result(&self) -> Option<SlHndl> {
@@ -622,3 +1572,234 @@ pub fn exec_thread(ptr: usize) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parks_blocked() {
+        let (weft, thr) = super::super::test_weft(|_| (), 1);
+        let main = unsafe { &mut *thr };
+
+        // the child waits on its empty queue, and the main thread on it
+        let code = "(do (def t (th-spawn (fn [] (+ 1 (rest (qrx-wait)))))) (th-join t))";
+        main.load_from_text(code, false).unwrap();
+
+        let tp = thr as usize;
+        let driver = std::thread::spawn(move || exec_thread(tp));
+
+        let start = Instant::now();
+        let settle = |done: &dyn Fn() -> bool| {
+            while !done() {
+                assert!(start.elapsed() < Duration::from_secs(5));
+                std::thread::yield_now();
+            }
+        };
+
+        // the main thread only joins once the child exists
+        settle(&|| main.sched.load(Ordering::Acquire) == PARKED);
+        let child = unsafe { Pin::into_inner_unchecked(weft.sl_threads[1].as_ref()) };
+
+        // blocked threads park, and so does the worker left idle
+        settle(&|| child.sched.load(Ordering::Acquire) == PARKED && weft.idle_workers() == 1);
+        let steps = (main.steps(), child.steps());
+
+        std::thread::yield_now();
+        assert_eq!(steps, (main.steps(), child.steps()));
+        assert_eq!(main.sched.load(Ordering::Acquire), PARKED);
+
+        let reg = memmgt::Region::acq(1000);
+        unsafe { (*child.queue_inlet()).transmit(0, super::super::i64_init(reg, 41)) };
+
+        driver.join().unwrap();
+
+        let res = main.result().unwrap();
+        assert_eq!(super::super::i64_get(res), 42);
+    }
+
+    #[test]
+    fn steals_waiting() {
+        let (mut weft, _) = super::super::test_weft(|_| (), 2);
+
+        let code = "(do (def n 0) (while (not (= n 2000)) (set n (+ n 1))) n)";
+
+        let thrs = (0..4)
+            .map(|_| {
+                let thr = ThreadHull::summon(&mut weft, 1000, 10000, None);
+                super::super::thread_env_setup(thr);
+                unsafe { (*thr).load_from_text(code, false).unwrap() };
+                unsafe { &*thr }
+            })
+            .collect::<Vec<_>>();
+
+        // everything starts on the first worker, so the second has
+        // to steal if it is to help; whether it does depends on timing
+        let posts = weft.crew.posts.read().unwrap().clone();
+        for t in thrs.iter() {
+            *t.home.lock().unwrap() = Some(posts[0].clone());
+            posts[0].post((*t as *const ThreadHull).cast_mut());
+        }
+
+        let start = Instant::now();
+        while !thrs.iter().all(|t| t.done_p()) {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            std::thread::yield_now();
+        }
+
+        for t in thrs.iter() {
+            assert_eq!(super::super::i64_get(t.result().unwrap()), 2000);
+        }
+
+        // so the steal itself is checked among posts with no workers
+        let crew = Arc::new(Crew {
+            posts: RwLock::new(vec![]),
+        });
+        let idle = (0..3)
+            .map(|_| Arc::new(WorkerPost::new(&crew)))
+            .collect::<Vec<_>>();
+        *crew.posts.write().unwrap() = idle.clone();
+
+        idle[1].load.store(100, Ordering::Relaxed);
+        for t in thrs.iter() {
+            idle[1].post((*t as *const ThreadHull).cast_mut());
+        }
+        idle[2].post((thrs[0] as *const ThreadHull).cast_mut());
+
+        // the busiest gives up the thread it would run last
+        let stolen = crew.steal(&idle[0]);
+        assert_eq!(stolen, Some(thrs[3] as *const ThreadHull as usize));
+        assert!(Arc::ptr_eq(
+            &thrs[3].home.lock().unwrap().clone().unwrap(),
+            &idle[0]
+        ));
+        assert_eq!(idle[1].runq.lock().unwrap().len(), 3);
+        assert_eq!(idle[2].runq.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn budgets_and_interrupts() {
+        let (mut weft, thr) = super::super::test_weft(|_| (), 0);
+        let main = unsafe { &mut *thr };
+
+        let describe =
+            |t: &ThreadHull, obj| super::super::context(t.context().symtab(), obj).to_string();
+
+        // the interrupt unwinds only as far as the eval
+        let code = "(do (def r (eval '(while #T ()))) (link :back r))";
+        main.load_from_text(code, false).unwrap();
+
+        for _ in 0..1000 {
+            assert!(main.advance());
+        }
+
+        main.interrupt();
+        while main.advance() {}
+
+        assert_eq!(main.status(), ThreadStatus::Done);
+        let back = super::super::get_next_list_elt(main.result().unwrap()).unwrap();
+        assert!(describe(main, back).contains("ThreadInterrupted"));
+
+        // as from Ctrl-C: only code within an eval is interrupted, and
+        // an interrupt pending outside of one is forgotten
+        let count = "(do (def n 0) (while (not (= n 50)) (set n (+ n 1))) n)";
+        main.load_from_text(count, false).unwrap();
+        assert!(main.advance());
+        assert!(!main.interrupt_bounded());
+
+        main.interrupted.store(INTR_BOUNDED, Ordering::Release);
+        while main.advance() {}
+        assert_eq!(describe(main, main.result().unwrap()), "50");
+
+        main.load_from_text(code, false).unwrap();
+        for _ in 0..1000 {
+            assert!(main.advance());
+        }
+        assert!(main.interrupt_bounded());
+        while main.advance() {}
+        let back = super::super::get_next_list_elt(main.result().unwrap()).unwrap();
+        assert!(describe(main, back).contains("ThreadInterrupted"));
+
+        main.set_budget(500);
+        main.load_from_text("(while #T ())", false).unwrap();
+        while main.advance() {}
+
+        assert_eq!(main.status(), ThreadStatus::Failed);
+        assert_eq!(main.prog_steps, 500);
+        assert!(describe(main, main.result().unwrap()).contains("ThreadOverBudget"));
+
+        // the more important thread gets more of the one worker's time
+        weft.add_worker();
+
+        let (hi, lo) = (
+            ThreadHull::summon(&mut weft, 1000, 10000, None),
+            ThreadHull::summon(&mut weft, 1000, 10000, None),
+        );
+
+        let code = "(do (def n 0) (while (not (= n 5000)) (set n (+ n 1))) n)";
+        let posts = weft.crew.posts.read().unwrap().clone();
+        for (t, p) in [(hi, PRIORITY_MAX), (lo, 1)] {
+            super::super::thread_env_setup(t);
+
+            let t = unsafe { &mut *t };
+            t.load_from_text(code, false).unwrap();
+            t.set_priority(p);
+
+            *t.home.lock().unwrap() = Some(posts[0].clone());
+            posts[0].post(t);
+        }
+
+        let (hi, lo) = unsafe { (&*hi, &*lo) };
+
+        let start = Instant::now();
+        while !hi.done_p() {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            std::thread::yield_now();
+        }
+
+        assert!(lo.steps() < hi.steps() / 2);
+    }
+
+    #[test]
+    fn reclaims_killed() {
+        let (weft, thr) = super::super::test_weft(|_| (), 1);
+        let main = unsafe { &mut *thr };
+
+        let code = "(do (def t (th-spawn (fn [] (qrx-wait)))) (th-monitor t) (th-kill t) t)";
+        main.load_from_text(code, false).unwrap();
+        while main.advance() {}
+
+        let id = super::super::thread_ref_id(main.result().unwrap());
+
+        // removed without being joined, leaving only its outcome
+        let start = Instant::now();
+        while weft.with_thread(id, |_| ()).is_some() {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            std::thread::yield_now();
+        }
+
+        assert_eq!(weft.sl_threads.len(), 1);
+        assert!(weft.frozen.is_empty() && weft.assignments.is_empty());
+        assert_eq!(weft.status_of(id), ThreadStatus::Killed);
+
+        // the notice was sent before the thread was removed
+        let (from, notice) = unsafe { (*main.queue_inlet()).receive() };
+        assert_eq!(from, id);
+        assert_eq!(
+            super::super::context(main.context().symtab(), notice.unwrap()).to_string(),
+            ":killed"
+        );
+
+        // joining takes the outcome, after which nothing is kept
+        main.load_from_text("(err-code (th-join t))", false)
+            .unwrap();
+        while main.advance() {}
+
+        assert_eq!(
+            super::super::context(main.context().symtab(), main.result().unwrap()).to_string(),
+            ":thread-killed"
+        );
+        assert!(weft.kept.lock().unwrap().is_empty());
+        assert_eq!(weft.status_of(id), ThreadStatus::Gone);
+    }
+}